@@ -1,3 +1,3 @@
 mod lnk;
 
-pub use lnk::{Lnk, LnkParseError};
+pub use lnk::{Lnk, LnkParseError, LnkWriteError, ShowCommand};
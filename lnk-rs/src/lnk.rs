@@ -1,5 +1,5 @@
 use bitflags::bitflags;
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDateTime};
 use std::io::{self, Read, Write};
 
 use crate::lnk::{
@@ -67,6 +67,69 @@ pub struct Lnk {
 }
 
 impl Lnk {
+    /// A shortcut to a file on a local drive, e.g. `C:\\Program Files\\App\\app.exe`
+    pub fn new(target: impl Into<String>) -> Self {
+        let epoch = DateTime::UNIX_EPOCH.naive_utc();
+        Self {
+            link_flags: LinkFlags::empty(),
+            file_flags: FileAttributeFlags::FILE_ATTRIBUTE_NORMAL,
+            creation_time: epoch,
+            access_time: epoch,
+            modification_time: epoch,
+            file_size_lower_bytes: 0,
+            icon_index: 0,
+            show_command: ShowCommand::Normal,
+            id_list: None,
+            link_info: Some(LinkInfo::local(target.into())),
+            name: None,
+            relative_path: None,
+            working_dir: None,
+            arguments: None,
+            icon_location: None,
+            block_data: BlockData::default(),
+        }
+    }
+
+    /// Description shown as the tooltip of the shortcut
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn working_dir(mut self, working_dir: impl Into<String>) -> Self {
+        self.working_dir = Some(working_dir.into());
+        self
+    }
+
+    pub fn arguments(mut self, arguments: impl Into<String>) -> Self {
+        self.arguments = Some(arguments.into());
+        self
+    }
+
+    /// File containing the icon and the index of the icon inside of it
+    pub fn icon(mut self, icon_location: impl Into<String>, icon_index: i32) -> Self {
+        self.icon_location = Some(icon_location.into());
+        self.icon_index = icon_index;
+        self
+    }
+
+    /// The AppUserModelID, which groups the windows of the application with the shortcut on the taskbar
+    pub fn app_user_model_id(mut self, id: impl Into<String>) -> Self {
+        self.block_data
+            .property_store
+            .app_user_model
+            .get_or_insert_default()
+            .id = Some(id.into());
+        self
+    }
+
+    /// Serializes the shortcut into the bytes of a `.lnk` file
+    pub fn to_bytes(&self) -> Result<Vec<u8>, LnkWriteError> {
+        let mut data = Vec::new();
+        self.write(&mut data)?;
+        Ok(data)
+    }
+
     pub fn parse(data: &mut impl Read) -> Result<Self, LnkParseError> {
         let mut signature = [0u8; 4];
         data.read_exact(&mut signature)?;
@@ -185,6 +248,7 @@ impl Lnk {
         let mut link_flags = self.link_flags.clone();
         link_flags.insert(LinkFlags::IS_UNICODE);
         link_flags.set(LinkFlags::HAS_LINK_TARGET_ID_LIST, self.id_list.is_some());
+        link_flags.set(LinkFlags::HAS_LINK_INFO, self.link_info.is_some());
         link_flags.set(LinkFlags::HAS_NAME, self.name.is_some());
         link_flags.set(LinkFlags::HAS_RELATIVE_PATH, self.relative_path.is_some());
         link_flags.set(LinkFlags::HAS_WORKING_DIR, self.working_dir.is_some());
@@ -212,9 +276,9 @@ impl Lnk {
         // Reserved 1
         write_u16(data, 0)?;
         // Reserved 2
-        write_u16(data, 0)?;
+        write_u32(data, 0)?;
         // Reserved 3
-        write_u16(data, 0)?;
+        write_u32(data, 0)?;
 
         if let Some(id_list) = &self.id_list {
             id_list.write(data)?;
@@ -254,6 +318,8 @@ impl Lnk {
 pub enum LnkWriteError {
     #[error("I/O error: {0}")]
     Io(#[from] io::Error),
+    #[error("writing {0} is not supported")]
+    Unsupported(&'static str),
 }
 
 const SIGNATURE: &[u8] = b"L\x00\x00\x00";
@@ -462,3 +528,167 @@ bitflags! {
 
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lnk::{
+        helpers::{write_c_utf16, write_u64},
+        link_info::DriveType,
+    };
+
+    #[test]
+    fn round_trips_written_shortcut() {
+        let bytes = Lnk::new("C:\\Program Files\\App\\app.exe")
+            .name("App")
+            .working_dir("C:\\Program Files\\App")
+            .arguments("--open \"Ünïcode file.txt\"")
+            .icon("C:\\Program Files\\App\\app.exe", 2)
+            .app_user_model_id("Publisher.App")
+            .to_bytes()
+            .unwrap();
+
+        let lnk = Lnk::parse(&mut bytes.as_slice()).unwrap();
+        let link_info = lnk.link_info.unwrap();
+        assert_eq!(
+            link_info.local_base_path.as_deref(),
+            Some("C:\\Program Files\\App\\app.exe")
+        );
+        assert_eq!(link_info.common_path_suffix.as_deref(), Some(""));
+        assert!(matches!(
+            link_info.volume_id.unwrap().drive_type,
+            DriveType::Fixed
+        ));
+        assert_eq!(lnk.name.as_deref(), Some("App"));
+        assert_eq!(lnk.working_dir.as_deref(), Some("C:\\Program Files\\App"));
+        assert_eq!(
+            lnk.arguments.as_deref(),
+            Some("--open \"Ünïcode file.txt\"")
+        );
+        assert_eq!(
+            lnk.icon_location.as_deref(),
+            Some("C:\\Program Files\\App\\app.exe")
+        );
+        assert_eq!(lnk.icon_index, 2);
+        assert_eq!(lnk.relative_path, None);
+        assert!(lnk.id_list.is_none());
+        assert_eq!(
+            lnk.block_data
+                .property_store
+                .app_user_model
+                .unwrap()
+                .id
+                .as_deref(),
+            Some("Publisher.App")
+        );
+    }
+
+    #[test]
+    fn parses_link_info_without_unicode_offsets() {
+        // Laid out like the example in [MS-SHLLINK] section 3, which Windows writes for local files
+        let lnk = Lnk::parse(&mut fixture(false).as_slice()).unwrap();
+        assert_fixture(lnk);
+    }
+
+    #[test]
+    fn parses_link_info_with_unicode_offsets() {
+        // The unicode common path suffix used to be rejected unless the link pointed to a network share
+        let lnk = Lnk::parse(&mut fixture(true).as_slice()).unwrap();
+        assert_fixture(lnk);
+    }
+
+    fn assert_fixture(lnk: Lnk) {
+        let link_info = lnk.link_info.unwrap();
+        let volume_id = link_info.volume_id.unwrap();
+        assert!(matches!(volume_id.drive_type, DriveType::Fixed));
+        assert_eq!(volume_id.serial_number, 0x307A8A81);
+        assert_eq!(volume_id.label, "");
+        assert_eq!(
+            link_info.local_base_path.as_deref(),
+            Some("C:\\test\\a.txt")
+        );
+        assert_eq!(link_info.common_path_suffix.as_deref(), Some(""));
+        assert_eq!(lnk.relative_path.as_deref(), Some(".\\a.txt"));
+        assert_eq!(lnk.working_dir.as_deref(), Some("C:\\test"));
+        assert_eq!(lnk.name, None);
+    }
+
+    /// A shortcut to `C:\test\a.txt` as written by Windows, with or without the unicode strings in the link info
+    fn fixture(unicode: bool) -> Vec<u8> {
+        let link_flags = LinkFlags::HAS_LINK_INFO
+            | LinkFlags::HAS_RELATIVE_PATH
+            | LinkFlags::HAS_WORKING_DIR
+            | LinkFlags::IS_UNICODE;
+
+        let mut data = Vec::new();
+        data.extend_from_slice(SIGNATURE);
+        data.extend_from_slice(GUID);
+        write_u32(&mut data, link_flags.bits()).unwrap();
+        write_u32(&mut data, FileAttributeFlags::FILE_ATTRIBUTE_ARCHIVE.bits()).unwrap();
+        // Creation, access and write time
+        for time in [0x01C8_8A5F_6C56_8E00u64; 3] {
+            write_u64(&mut data, time).unwrap();
+        }
+        // File size, icon index, show command, hotkey with the first reserved field and the other two reserved fields
+        for value in [0, 0, 1, 0, 0, 0] {
+            write_u32(&mut data, value).unwrap();
+        }
+        assert_eq!(data.len(), 0x4C);
+
+        data.extend(link_info(unicode));
+        write_sized_utf16(&mut data, ".\\a.txt").unwrap();
+        write_sized_utf16(&mut data, "C:\\test").unwrap();
+        // Terminal block
+        write_u32(&mut data, 0).unwrap();
+        data
+    }
+
+    fn link_info(unicode: bool) -> Vec<u8> {
+        let header_size: u32 = if unicode { 0x24 } else { 0x1C };
+        #[rustfmt::skip]
+        let volume_id = [
+            0x11, 0, 0, 0, // Size
+            3, 0, 0, 0, // Fixed drive
+            0x81, 0x8A, 0x7A, 0x30, // Serial number
+            0x10, 0, 0, 0, // Label offset
+            0, // Empty label
+        ];
+
+        let mut body = Vec::new();
+        let volume_id_offset = header_size + body.len() as u32;
+        body.extend_from_slice(&volume_id);
+        let local_base_path_offset = header_size + body.len() as u32;
+        body.extend_from_slice(b"C:\\test\\a.txt\0");
+        let common_path_suffix_offset = header_size + body.len() as u32;
+        body.push(0);
+        let local_base_path_unicode_offset = header_size + body.len() as u32;
+        let common_path_suffix_unicode_offset;
+        if unicode {
+            write_c_utf16(&mut body, "C:\\test\\a.txt").unwrap();
+            common_path_suffix_unicode_offset = header_size + body.len() as u32;
+            write_c_utf16(&mut body, "").unwrap();
+        } else {
+            common_path_suffix_unicode_offset = 0;
+        }
+
+        let mut data = Vec::new();
+        write_u32(&mut data, header_size + body.len() as u32).unwrap();
+        write_u32(&mut data, header_size).unwrap();
+        // Volume id and local base path
+        write_u32(&mut data, 1).unwrap();
+        write_u32(&mut data, volume_id_offset).unwrap();
+        write_u32(&mut data, local_base_path_offset).unwrap();
+        // Common network relative link
+        write_u32(&mut data, 0).unwrap();
+        write_u32(&mut data, common_path_suffix_offset).unwrap();
+        if unicode {
+            write_u32(&mut data, local_base_path_unicode_offset).unwrap();
+            write_u32(&mut data, common_path_suffix_unicode_offset).unwrap();
+        }
+        data.extend(body);
+        if !unicode {
+            assert_eq!(data.len(), 0x3C);
+        }
+        data
+    }
+}
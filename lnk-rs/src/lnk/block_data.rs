@@ -10,7 +10,7 @@ use crate::lnk::{
         special_folder::{SpecialFolder, SpecialFolderDataBlockParseError},
         tracker::{Tracker, TrackerDataBlockParseError},
     },
-    helpers::read_u32,
+    helpers::{read_u32, write_u32},
 };
 
 mod console;
@@ -42,7 +42,7 @@ pub enum BlockDataParseError {
     KnownFolderDataBlockError(#[from] KnownFolderDataBlockParseError),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct BlockData {
     pub console: Option<Console>,
     pub tracker: Option<Tracker>,
@@ -54,14 +54,7 @@ pub struct BlockData {
 
 impl BlockData {
     pub fn parse(data: &mut impl Read) -> Result<Self, BlockDataParseError> {
-        let mut me = Self {
-            console: None,
-            tracker: None,
-            icon_environment: None,
-            special_folders: Vec::new(),
-            known_folders: Vec::new(),
-            property_store: PropertyStore::default(),
-        };
+        let mut me = Self::default();
 
         loop {
             let block_size = read_u32(data)?;
@@ -112,10 +105,40 @@ impl BlockData {
     }
 
     pub(crate) fn write(&self, data: &mut impl Write) -> Result<(), LnkWriteError> {
-        todo!()
+        if self.console.is_some() {
+            return Err(LnkWriteError::Unsupported("console data blocks"));
+        }
+        if self.tracker.is_some() {
+            return Err(LnkWriteError::Unsupported("tracker data blocks"));
+        }
+        if self.icon_environment.is_some() {
+            return Err(LnkWriteError::Unsupported("icon environment data blocks"));
+        }
+        if !self.special_folders.is_empty() {
+            return Err(LnkWriteError::Unsupported("special folder data blocks"));
+        }
+        if !self.known_folders.is_empty() {
+            return Err(LnkWriteError::Unsupported("known folder data blocks"));
+        }
+
+        let mut property_store = Vec::new();
+        self.property_store.write(&mut property_store)?;
+        if !property_store.is_empty() {
+            // Block size includes the size and signature fields
+            write_u32(data, property_store.len() as u32 + 8)?;
+            write_u32(data, PROPERTY_STORE_SIGNATURE)?;
+            data.write_all(&property_store)?;
+        }
+
+        // Terminal Block
+        write_u32(data, 0)?;
+
+        Ok(())
     }
 }
 
+const PROPERTY_STORE_SIGNATURE: u32 = 0xA0000009;
+
 #[derive(Debug)]
 pub enum BlockSignature {
     ConsoleDataBlock,
@@ -140,7 +163,7 @@ impl BlockSignature {
             0xA0000001 => Some(BlockSignature::EnvironmentVariableDataBlock),
             0xA0000007 => Some(BlockSignature::IconEnvironmentDataBlock),
             0xA000000B => Some(BlockSignature::KnownFolderDataBlock),
-            0xA0000009 => Some(BlockSignature::PropertyStoreDataBlock),
+            PROPERTY_STORE_SIGNATURE => Some(BlockSignature::PropertyStoreDataBlock),
             0xA0000008 => Some(BlockSignature::ShimDataBlock),
             0xA0000005 => Some(BlockSignature::SpecialFolderDataBlock),
             0xA0000003 => Some(BlockSignature::TrackerDataBlock),
@@ -1,18 +1,19 @@
 use chrono::NaiveDateTime;
 use std::{
     collections::{BTreeMap, HashMap},
-    io::{self, Cursor, Read},
+    io::{self, Cursor, Read, Write},
 };
 use thiserror::Error;
 
 use crate::lnk::{
+    LnkWriteError,
     block_data::property_store::{
         app_user_model_properties::AppUserModelProperties, link_properties::LinkProperties,
         system_basic_properties::SystemBasicProperties,
     },
     helpers::{
         Guid, StringReadError, WindowsDateTimeError, read_c_utf16, read_guid, read_u8, read_u16,
-        read_u32, read_u64, read_windows_datetime,
+        read_u32, read_u64, read_windows_datetime, write_c_utf16, write_u8, write_u16, write_u32,
     },
 };
 
@@ -73,10 +74,28 @@ impl Default for PropertyStore {
 
 impl PropertyStore {
     pub fn parse(&mut self, r: &mut impl Read) -> Result<(), PropertyStoreDataBlockParseError> {
+        // A sequence of Serialized Property Storages, terminated by a zero storage size.
+        // Some writers omit the terminator, so the end of the block ends the sequence too.
+        loop {
+            let storage_size = match read_u32(r) {
+                Ok(storage_size) => storage_size,
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err.into()),
+            };
+            if storage_size == 0 {
+                break;
+            }
+            self.parse_storage(r)?;
+        }
+
+        Ok(())
+    }
+
+    /// Parses one Serialized Property Storage after its size
+    fn parse_storage(&mut self, r: &mut impl Read) -> Result<(), PropertyStoreDataBlockParseError> {
         // Serialized Property Storage header ([MS-PROPSTORE] as used by [MS-SHLLINK])
-        let _storage_size = read_u32(r)?;
         let version = read_u32(r)?;
-        if version != 0x5350_5331 {
+        if version != STORAGE_VERSION {
             return Err(PropertyStoreDataBlockParseError::InvalidVersion(version));
         }
         let format_id = read_guid(r)?;
@@ -155,6 +174,93 @@ impl PropertyStore {
 
         Ok(())
     }
+
+    /// Writes the property store as the contents of a Property Store Data Block.
+    ///
+    /// Nothing is written if the store is empty. Only the app user model properties can be written so far.
+    pub(crate) fn write(&self, data: &mut impl Write) -> Result<(), LnkWriteError> {
+        if !self.unparsed_id_values.is_empty() || !self.unparsed_name_values.is_empty() {
+            return Err(LnkWriteError::Unsupported("unknown properties"));
+        }
+        if self.system_basic.is_some() {
+            return Err(LnkWriteError::Unsupported("system basic properties"));
+        }
+        if self.link_properties.is_some() {
+            return Err(LnkWriteError::Unsupported("link properties"));
+        }
+
+        let Some(app_user_model) = &self.app_user_model else {
+            return Ok(());
+        };
+        write_storage(data, &FMTID_APP_USER_MODEL, app_user_model.to_raw())?;
+
+        // Terminates the sequence of storages
+        write_u32(data, 0)?;
+
+        Ok(())
+    }
+}
+
+const STORAGE_VERSION: u32 = 0x5350_5331;
+
+const FMTID_APP_USER_MODEL: Guid = Guid {
+    data1: 0x9F4C2855,
+    data2: 0x9F79,
+    data3: 0x4B39,
+    data4: [0xA8, 0xD0, 0xE1, 0xD4, 0x2D, 0xE1, 0xD5, 0xF3],
+};
+
+/// Writes a Serialized Property Storage with integer property ids
+fn write_storage(
+    data: &mut impl Write,
+    format_id: &Guid,
+    properties: Vec<(u32, PropValue)>,
+) -> Result<(), LnkWriteError> {
+    let mut storage = Vec::new();
+    write_u32(&mut storage, STORAGE_VERSION)?;
+    format_id.write(&mut storage)?;
+
+    for (id, value) in properties {
+        let value = write_typed_property_value(&value)?;
+        // ValueSize (u32), Id (u32), Reserved (u8), Value (typed)
+        write_u32(&mut storage, value.len() as u32 + 9)?;
+        write_u32(&mut storage, id)?;
+        write_u8(&mut storage, 0)?;
+        storage.write_all(&value)?;
+    }
+    // Terminates the values
+    write_u32(&mut storage, 0)?;
+
+    // The storage size includes its own field
+    write_u32(data, storage.len() as u32 + 4)?;
+    data.write_all(&storage)?;
+    Ok(())
+}
+
+/// Serializes a value as [MS-OLEPS] TypedPropertyValue, the counterpart of [`parse_typed_property_value`]
+fn write_typed_property_value(value: &PropValue) -> Result<Vec<u8>, LnkWriteError> {
+    let mut buf = Vec::new();
+    match value {
+        PropValue::Bool(value) => {
+            write_u16(&mut buf, 0x000B)?;
+            write_u16(&mut buf, 0)?;
+            write_u16(&mut buf, if *value { 0xFFFF } else { 0 })?;
+            write_u16(&mut buf, 0)?;
+        }
+        PropValue::Unicode(text) => {
+            write_u16(&mut buf, 0x001F)?;
+            write_u16(&mut buf, 0)?;
+            // Length in characters including the NUL
+            write_u32(&mut buf, text.encode_utf16().count() as u32 + 1)?;
+            write_c_utf16(&mut buf, text)?;
+            // Pad to a 4-byte boundary
+            while buf.len() % 4 != 0 {
+                buf.push(0);
+            }
+        }
+        _ => return Err(LnkWriteError::Unsupported("this property type")),
+    }
+    Ok(buf)
 }
 
 /// Parse [MS-OLEPS] TypedPropertyValue into your PropValue.
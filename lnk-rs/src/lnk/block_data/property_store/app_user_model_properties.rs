@@ -64,4 +64,22 @@ impl AppUserModelProperties {
 
         Ok(me)
     }
+
+    /// The properties that are set, keyed by their PID
+    pub(crate) fn to_raw(&self) -> Vec<(u32, PropValue)> {
+        let strings = [
+            (5, &self.id),
+            (6, &self.relaunch_command),
+            (7, &self.relaunch_display_name_resource),
+            (8, &self.relaunch_icon_resource),
+        ];
+        let mut properties = strings
+            .into_iter()
+            .filter_map(|(pid, text)| Some((pid, PropValue::Unicode(text.clone()?))))
+            .collect::<Vec<_>>();
+        if let Some(is_dual_mode) = self.is_dual_mode {
+            properties.push((11, PropValue::Bool(is_dual_mode)));
+        }
+        properties
+    }
 }
@@ -131,10 +131,15 @@ pub fn read_c_utf16(data: &mut impl Read) -> Result<String, StringReadError> {
     Ok(decoded_string)
 }
 
+/// Writes a string prefixed by its length in UTF-16 code units, without a terminating NUL
 pub fn write_sized_utf16(data: &mut impl Write, string: &str) -> Result<(), io::Error> {
-    let size = string.chars().count() as u16;
+    let encoded_string: Vec<u16> = string.encode_utf16().collect();
+    let size = u16::try_from(encoded_string.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "string is too long"))?;
     write_u16(data, size)?;
-    write_c_utf16(data, string)?;
+    for short in encoded_string {
+        write_u16(data, short)?;
+    }
     Ok(())
 }
 
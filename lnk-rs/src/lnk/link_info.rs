@@ -4,7 +4,7 @@ use bitflags::bitflags;
 
 use crate::lnk::{
     LnkWriteError,
-    helpers::{StringReadError, read_c_utf8, read_c_utf16, read_u32},
+    helpers::{StringReadError, read_c_utf8, read_c_utf16, read_u32, write_c_utf16, write_u32},
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -87,11 +87,72 @@ impl LinkInfo {
         })
     }
 
+    /// Link info of a file on a fixed local drive
+    pub fn local(path: String) -> Self {
+        Self {
+            volume_id: Some(VolumeId {
+                drive_type: DriveType::Fixed,
+                serial_number: 0,
+                label: String::new(),
+            }),
+            local_base_path: Some(path),
+            common_path_suffix: Some(String::new()),
+        }
+    }
+
     pub(crate) fn write(&self, data: &mut impl Write) -> Result<(), LnkWriteError> {
-        todo!()
+        let (Some(volume_id), Some(local_base_path)) = (&self.volume_id, &self.local_base_path)
+        else {
+            return Err(LnkWriteError::Unsupported(
+                "link info without a local base path",
+            ));
+        };
+        let common_path_suffix = self.common_path_suffix.as_deref().unwrap_or_default();
+
+        // Everything after the header, offsets are relative to the start of the link info
+        let mut body = Vec::new();
+        let volume_id_offset = HEADER_SIZE + body.len() as u32;
+        volume_id.write(&mut body)?;
+        let local_base_path_offset = HEADER_SIZE + body.len() as u32;
+        write_c_ansi(&mut body, local_base_path)?;
+        let common_path_suffix_offset = HEADER_SIZE + body.len() as u32;
+        write_c_ansi(&mut body, common_path_suffix)?;
+        let local_base_path_unicode_offset = HEADER_SIZE + body.len() as u32;
+        write_c_utf16(&mut body, local_base_path)?;
+        let common_path_suffix_unicode_offset = HEADER_SIZE + body.len() as u32;
+        write_c_utf16(&mut body, common_path_suffix)?;
+
+        write_u32(data, HEADER_SIZE + body.len() as u32)?;
+        write_u32(data, HEADER_SIZE)?;
+        write_u32(data, LinkInfoFlags::VOLUME_ID_AND_LOCAL_BASE_PATH.bits())?;
+        write_u32(data, volume_id_offset)?;
+        write_u32(data, local_base_path_offset)?;
+        // Common network relative link
+        write_u32(data, 0)?;
+        write_u32(data, common_path_suffix_offset)?;
+        write_u32(data, local_base_path_unicode_offset)?;
+        write_u32(data, common_path_suffix_unicode_offset)?;
+        data.write_all(&body)?;
+
+        Ok(())
     }
 }
 
+/// Size of the link info header including the offsets of the unicode strings
+const HEADER_SIZE: u32 = 0x24;
+
+/// Writes a NUL terminated string in the system code page.
+///
+/// The code page isn't known here, so anything outside of ASCII is replaced. Readers prefer the unicode strings anyway.
+fn write_c_ansi(data: &mut impl Write, string: &str) -> std::io::Result<()> {
+    let bytes = string
+        .chars()
+        .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+        .chain([0])
+        .collect::<Vec<_>>();
+    data.write_all(&bytes)
+}
+
 #[derive(Debug, Default)]
 pub struct LinkOffsets {
     // Local
@@ -150,13 +211,10 @@ impl LinkOffsets {
                 }
             }
 
-            if link_info_flags.contains(LinkInfoFlags::COMMON_NETWORK_RELATIVE_LINK_AND_PATH_SUFFIX)
-            {
-                offsets.common_path_suffix_unicode = Some(read_u32(data)?);
-            } else {
-                if read_u32(data)? != 0 {
-                    return Err(LinkInfoParseError::InvalidOffset);
-                }
+            // The common path suffix is always present, but its unicode version is optional
+            let common_path_suffix_unicode = read_u32(data)?;
+            if common_path_suffix_unicode != 0 {
+                offsets.common_path_suffix_unicode = Some(common_path_suffix_unicode);
             }
         }
 
@@ -258,6 +316,25 @@ impl VolumeId {
             label,
         })
     }
+
+    /// Writes the volume id with its label in unicode
+    pub fn write(&self, data: &mut impl Write) -> Result<(), LnkWriteError> {
+        // Size, drive type, serial number, label offset and unicode label offset
+        const FIELDS_SIZE: u32 = 0x14;
+
+        let mut label = Vec::new();
+        write_c_utf16(&mut label, &self.label)?;
+
+        write_u32(data, FIELDS_SIZE + label.len() as u32)?;
+        write_u32(data, self.drive_type.to_u32())?;
+        write_u32(data, self.serial_number)?;
+        // A label offset of 0x14 means the unicode label offset follows
+        write_u32(data, FIELDS_SIZE)?;
+        write_u32(data, FIELDS_SIZE)?;
+        data.write_all(&label)?;
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -284,4 +361,16 @@ impl DriveType {
             _ => None,
         }
     }
+
+    pub fn to_u32(&self) -> u32 {
+        match self {
+            DriveType::Unknown => 0,
+            DriveType::NoRootDir => 1,
+            DriveType::Removable => 2,
+            DriveType::Fixed => 3,
+            DriveType::Remote => 4,
+            DriveType::CdRom => 5,
+            DriveType::RamDisk => 6,
+        }
+    }
 }
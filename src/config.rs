@@ -1,14 +1,207 @@
 use std::borrow::Cow;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component as PathComponent, Path, PathBuf, Prefix};
 
+mod component;
+mod config_file;
+mod file_association;
+mod hook;
 mod manifest;
+mod mime_type;
+mod payload_condition;
+mod scope;
+mod second_instance;
+mod service;
+mod url_scheme;
+pub use component::Component;
+use component::component_path;
+pub use config_file::ConfigFilePolicy;
+pub use file_association::FileAssociation;
+pub use hook::{HookFailurePolicy, HookStage, InstallHook};
 pub use manifest::AppManifest;
+pub use mime_type::MimeType;
+pub use payload_condition::{PayloadCondition, TargetSystem};
+pub use scope::InstallScope;
+pub use second_instance::SecondInstancePolicy;
+pub use service::{RestartPolicy, Service, StartType};
+pub use url_scheme::UrlScheme;
 
 pub struct InstallConfig {
     pub install_path: PathBuf,
+    pub scope: InstallScope,
     pub payloads: Vec<FilePayload>,
+    /// Optional parts of the application, installed in addition to `payloads` if selected
+    pub components: Vec<Component>,
+    /// Ids of the components that are installed
+    pub selected_components: BTreeSet<String>,
     pub create_start_menu_shortcut: bool,
     pub create_desktop_shortcut: bool,
+    /// Makes `bin_name` available on `PATH`
+    pub add_to_path: bool,
+    /// Values collected by the wizard, available as `{name}` in templates
+    pub variables: BTreeMap<String, String>,
+    /// Starts the application once the installation completed.
+    /// The wizard preselects the option on its last page instead.
+    pub launch: bool,
+    /// Where the install log is written, a file in the temporary directory if not set.
+    /// After a successful installation the log is also kept in the install directory.
+    pub log_file: Option<PathBuf>,
+    /// Staging directory every destination is redirected into, like `DESTDIR`, e.g. to build a container image.
+    /// Paths written into files stay the real ones. Nothing requires elevation and the running system is left alone:
+    /// setup tasks, registry entries, service registration and cache updates are skipped.
+    pub root: Option<PathBuf>,
+}
+
+impl InstallConfig {
+    /// Number of bytes the payloads take up once installed
+    pub fn required_space(&self) -> u64 {
+        let components = self
+            .all_components()
+            .filter(|component| self.is_component_selected(&component.id))
+            .map(Component::unpacked_size)
+            .sum::<u64>();
+        self.payloads
+            .iter()
+            .map(FilePayload::unpacked_size)
+            .sum::<u64>()
+            + components
+    }
+
+    /// Number of bytes that have to be downloaded, for remote payloads
+    pub fn download_size(&self) -> u64 {
+        let components = self
+            .all_components()
+            .filter(|component| self.is_component_selected(&component.id))
+            .flat_map(|component| &component.payloads)
+            .map(FilePayload::download_size)
+            .sum::<u64>();
+        self.payloads
+            .iter()
+            .map(FilePayload::download_size)
+            .sum::<u64>()
+            + components
+    }
+
+    /// Where a file meant for `path` is written, below [`root`](Self::root) if the installation is staged.
+    ///
+    /// Drive letters become a directory, e.g. `C:\Program Files` is staged at `<root>\C\Program Files`.
+    pub fn staged(&self, path: &Path) -> PathBuf {
+        let Some(root) = &self.root else {
+            return path.to_path_buf();
+        };
+        let mut staged = root.clone();
+        for component in path.components() {
+            match component {
+                PathComponent::Prefix(prefix) => {
+                    if let Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) = prefix.kind() {
+                        staged.push(char::from(letter).to_string());
+                    }
+                }
+                PathComponent::RootDir => (),
+                component => staged.push(component),
+            }
+        }
+        staged
+    }
+
+    /// The real path of a file [`staged`](Self::staged) wrote.
+    ///
+    /// `None` for paths outside of the staging directory and for the directories standing in for the root, like `<root>\C`.
+    pub(crate) fn unstaged(&self, path: &Path) -> Option<PathBuf> {
+        let Some(root) = &self.root else {
+            return Some(path.to_path_buf());
+        };
+        let relative = path.strip_prefix(root).ok()?;
+        let mut components = relative.components();
+        let base = if cfg!(windows) {
+            let letter = components
+                .next()?
+                .as_os_str()
+                .to_string_lossy()
+                .into_owned();
+            PathBuf::from(format!("{letter}:\\"))
+        } else {
+            PathBuf::from("/")
+        };
+        let rest = components.as_path();
+        (!rest.as_os_str().is_empty()).then(|| base.join(rest))
+    }
+
+    /// All components and their sub-components, parents before their children
+    pub fn all_components(&self) -> impl Iterator<Item = &Component> {
+        self.components.iter().flat_map(Component::iter)
+    }
+
+    pub fn is_component_selected(&self, id: &str) -> bool {
+        self.selected_components.contains(id)
+    }
+
+    /// Selects or deselects a component, unknown ids are ignored.
+    ///
+    /// Selecting a sub-component selects its parents too, deselecting a component deselects its sub-components.
+    /// Required components stay selected.
+    pub fn select_component(&mut self, id: &str, selected: bool) {
+        let Some(path) = component_path(&self.components, id) else {
+            return;
+        };
+        if selected {
+            self.selected_components
+                .extend(path.iter().map(|component| component.id.clone()));
+        } else if let Some(component) = path.last()
+            && !component.is_required()
+        {
+            for component in component.iter() {
+                self.selected_components.remove(&component.id);
+            }
+        }
+    }
+
+    /// Selects the required components and those selected by default, and nothing else
+    pub fn select_default_components(&mut self) {
+        let defaults = self
+            .all_components()
+            .filter(|component| component.selected_by_default || component.is_required())
+            .map(|component| component.id.clone())
+            .collect::<Vec<_>>();
+        self.selected_components.clear();
+        for id in defaults {
+            self.select_component(&id, true);
+        }
+    }
+
+    /// Restores the choices of an earlier installation, components it didn't know keep their current state
+    pub fn restore_component_selection(&mut self, selection: &BTreeMap<String, bool>) {
+        // Deselecting first, so selected sub-components can't be deselected with their parent afterwards
+        for (id, _) in selection.iter().filter(|(_, selected)| !**selected) {
+            self.select_component(id, false);
+        }
+        for (id, _) in selection.iter().filter(|(_, selected)| **selected) {
+            self.select_component(id, true);
+        }
+    }
+
+    /// Every component and whether it is selected, as recorded for later installations
+    pub(crate) fn component_selection(&self) -> BTreeMap<String, bool> {
+        self.all_components()
+            .map(|component| {
+                let selected = self.is_component_selected(&component.id);
+                (component.id.clone(), selected)
+            })
+            .collect()
+    }
+
+    /// Takes the payloads to install, including those of the selected components
+    pub(crate) fn take_payloads(&mut self) -> Vec<FilePayload> {
+        let mut payloads = std::mem::take(&mut self.payloads);
+        let mut stack = self.components.iter_mut().collect::<Vec<_>>();
+        while let Some(component) = stack.pop() {
+            if self.selected_components.contains(&component.id) {
+                payloads.append(&mut component.payloads);
+            }
+            stack.extend(component.children.iter_mut());
+        }
+        payloads
+    }
 }
 
 pub enum FilePayload {
@@ -22,6 +215,63 @@ pub enum FilePayload {
         unpacked_size: u64,
         reader: Box<dyn DirTrait + Send + Sync>,
     },
+    /// A zip packed directory that is downloaded during the installation, e.g. large optional content.
+    ///
+    /// Interrupted downloads are resumed, also by a later installation attempt.
+    /// `http` URLs are downloaded directly, others with `curl`. Proxies are taken from the usual environment variables.
+    Remote {
+        url: Cow<'static, str>,
+        /// SHA-256 hash of the archive as hex string, it is verified before anything is extracted
+        sha256: Cow<'static, str>,
+        /// Size of the archive in bytes. It also counts as the unpacked size, which isn't known before downloading.
+        size: u64,
+    },
+    /// A payload that is only installed if the condition is met, e.g. native libraries for one architecture
+    Conditional {
+        condition: PayloadCondition,
+        payload: Box<FilePayload>,
+    },
+}
+
+impl FilePayload {
+    /// Number of bytes the payload takes up once installed, nothing if its condition isn't met
+    pub fn unpacked_size(&self) -> u64 {
+        match self {
+            FilePayload::File { contents, .. } => contents.len() as u64,
+            FilePayload::Directory { unpacked_size, .. } => *unpacked_size,
+            FilePayload::Remote { size, .. } => *size,
+            FilePayload::Conditional { condition, payload } => {
+                if condition.is_met() {
+                    payload.unpacked_size()
+                } else {
+                    0
+                }
+            }
+        }
+    }
+
+    /// Number of bytes that have to be downloaded for the payload
+    pub fn download_size(&self) -> u64 {
+        match self {
+            FilePayload::File { .. } | FilePayload::Directory { .. } => 0,
+            FilePayload::Remote { size, .. } => *size,
+            FilePayload::Conditional { condition, payload } => {
+                if condition.is_met() {
+                    payload.download_size()
+                } else {
+                    0
+                }
+            }
+        }
+    }
+
+    /// Only installs the payload if the condition is met
+    pub fn when(self, condition: PayloadCondition) -> Self {
+        FilePayload::Conditional {
+            condition,
+            payload: Box::new(self),
+        }
+    }
 }
 
 pub trait DirTrait: std::io::Read + std::io::Seek {}
@@ -39,3 +289,51 @@ macro_rules! embed_directory {
         }
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::install_config;
+
+    #[cfg(unix)]
+    #[test]
+    fn unstages_staged_paths() {
+        let mut config = install_config(Path::new("/opt/test-app"));
+        let file = Path::new("/usr/share/applications/test-app.desktop");
+        assert_eq!(config.staged(file), file);
+        assert_eq!(config.unstaged(file).as_deref(), Some(file));
+
+        config.root = Some(PathBuf::from("/tmp/stage"));
+        let staged = config.staged(file);
+        assert_eq!(
+            staged,
+            Path::new("/tmp/stage/usr/share/applications/test-app.desktop")
+        );
+        assert_eq!(config.unstaged(&staged).as_deref(), Some(file));
+        assert_eq!(
+            config.unstaged(Path::new("/tmp/stage/opt")).as_deref(),
+            Some(Path::new("/opt"))
+        );
+        // The root stands in for `/`, which no installation creates
+        assert_eq!(config.unstaged(Path::new("/tmp/stage")), None);
+        assert_eq!(config.unstaged(Path::new("/tmp/other/opt")), None);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn unstages_staged_paths() {
+        let mut config = install_config(Path::new("C:\\Program Files\\Test App"));
+        config.root = Some(PathBuf::from("D:\\stage"));
+        let file = Path::new("C:\\Program Files\\Test App\\test-app.exe");
+
+        let staged = config.staged(file);
+        assert_eq!(
+            staged,
+            Path::new("D:\\stage\\C\\Program Files\\Test App\\test-app.exe")
+        );
+        assert_eq!(config.unstaged(&staged).as_deref(), Some(file));
+        // The directory standing in for the drive isn't part of the installation
+        assert_eq!(config.unstaged(Path::new("D:\\stage\\C")), None);
+        assert_eq!(config.unstaged(Path::new("D:\\stage")), None);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::config::FilePayload;
+
+/// A part of the application the user can choose to install, e.g. documentation or plugins.
+///
+/// Components form a tree, a sub-component can only be installed together with its parent.
+pub struct Component {
+    /// Identifies the component on the command line and across installations, e.g. `docs`
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Whether the component is selected unless the user or an earlier installation chose otherwise
+    pub selected_by_default: bool,
+    /// Required components are always installed
+    pub required: bool,
+    pub payloads: Vec<FilePayload>,
+    pub children: Vec<Component>,
+}
+
+impl Component {
+    pub fn new(id: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            description: None,
+            selected_by_default: true,
+            required: false,
+            payloads: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn selected_by_default(mut self, selected: bool) -> Self {
+        self.selected_by_default = selected;
+        self
+    }
+
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    pub fn payload(mut self, payload: FilePayload) -> Self {
+        self.payloads.push(payload);
+        self
+    }
+
+    pub fn child(mut self, component: Component) -> Self {
+        self.children.push(component);
+        self
+    }
+
+    /// Number of bytes the component's own payloads take up once installed, without its sub-components
+    pub fn unpacked_size(&self) -> u64 {
+        self.payloads.iter().map(FilePayload::unpacked_size).sum()
+    }
+
+    /// Whether the component can't be deselected, because it or one of its sub-components is required
+    pub fn is_required(&self) -> bool {
+        self.required || self.children.iter().any(Component::is_required)
+    }
+
+    /// The component and all its sub-components, parents before their children
+    pub fn iter(&self) -> impl Iterator<Item = &Component> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let component = stack.pop()?;
+            stack.extend(component.children.iter().rev());
+            Some(component)
+        })
+    }
+}
+
+/// The components leading to the one with the given id, from the outermost parent to the component itself
+pub(crate) fn component_path<'a>(
+    components: &'a [Component],
+    id: &str,
+) -> Option<Vec<&'a Component>> {
+    for component in components {
+        if component.id == id {
+            return Some(vec![component]);
+        }
+        if let Some(mut path) = component_path(&component.children, id) {
+            path.insert(0, component);
+            return Some(path);
+        }
+    }
+    None
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// What happens to a configuration file the user modified since it was installed, when a new version is installed over it.
+///
+/// Unmodified configuration files are always replaced.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConfigFilePolicy {
+    /// Keep the user's copy and write the new version next to it as `<name>.new`
+    #[default]
+    WriteNew,
+    /// Replace the user's copy with the new version
+    Overwrite,
+    /// Keep the user's copy and don't write the new version
+    Skip,
+    /// Save the user's copy as `<name>.bak` and replace it with the new version
+    Backup,
+}
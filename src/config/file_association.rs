@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+/// A file extension the application opens, e.g. `.frost` project files.
+///
+/// On Windows the extension is registered with its own type, icon and context menu verb.
+/// On Linux it becomes a MIME type the application's desktop entry handles.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct FileAssociation {
+    /// Extension without the leading dot, e.g. `frost`
+    pub extension: String,
+    /// MIME type of the files, `application/x-<extension>` if not set
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    /// Human readable name of the file type, e.g. "Frost project"
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Icon of the files, relative to the install directory. The application's icon is used if not set.
+    ///
+    /// Windows expects an `.ico` file, Linux a `.png` or `.svg` file.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Verb of the command opening the files, shown in the Windows context menu. `open` if not set.
+    #[serde(default)]
+    pub verb: Option<String>,
+}
+
+impl FileAssociation {
+    pub fn new(extension: impl Into<String>) -> Self {
+        Self {
+            extension: extension.into().trim_start_matches('.').to_string(),
+            mime_type: None,
+            description: None,
+            icon: None,
+            verb: None,
+        }
+    }
+
+    pub fn mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn icon(mut self, icon: impl Into<String>) -> Self {
+        self.icon = Some(icon.into());
+        self
+    }
+
+    pub fn verb(mut self, verb: impl Into<String>) -> Self {
+        self.verb = Some(verb.into());
+        self
+    }
+
+    /// The MIME type of the files, derived from the extension if none is set
+    pub fn mime_type_name(&self) -> String {
+        self.mime_type
+            .clone()
+            .unwrap_or_else(|| format!("application/x-{}", self.extension.to_lowercase()))
+    }
+
+    pub fn verb_name(&self) -> &str {
+        self.verb.as_deref().unwrap_or("open")
+    }
+}
@@ -0,0 +1,106 @@
+use std::{fmt::Display, path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// A command that is run as part of the installation, e.g. to migrate a database or register a plugin.
+///
+/// Relative program paths are resolved against the install directory if the file exists there, otherwise the program is looked up in `PATH`.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct InstallHook {
+    pub stage: HookStage,
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Working directory relative to the install directory, defaults to the install directory itself
+    #[serde(default)]
+    pub working_dir: Option<PathBuf>,
+    /// The command is killed and treated as failed after this many seconds
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub on_failure: HookFailurePolicy,
+    /// Shown to the user while the command is running
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookStage {
+    /// Runs before any files are written
+    PreInstall,
+    /// Runs after all files are in place
+    PostInstall,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookFailurePolicy {
+    /// Stop the installation and roll it back
+    #[default]
+    Abort,
+    /// Ignore the failure and continue with the installation
+    Continue,
+}
+
+impl InstallHook {
+    pub fn new(stage: HookStage, program: impl Into<String>) -> Self {
+        Self {
+            stage,
+            program: program.into(),
+            args: Vec::new(),
+            working_dir: None,
+            timeout_secs: None,
+            on_failure: HookFailurePolicy::default(),
+            description: None,
+        }
+    }
+
+    pub fn pre_install(program: impl Into<String>) -> Self {
+        Self::new(HookStage::PreInstall, program)
+    }
+
+    pub fn post_install(program: impl Into<String>) -> Self {
+        Self::new(HookStage::PostInstall, program)
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn working_dir(mut self, working_dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(working_dir.into());
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout_secs = Some(timeout.as_secs());
+        self
+    }
+
+    pub fn on_failure(mut self, policy: HookFailurePolicy) -> Self {
+        self.on_failure = policy;
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+impl Display for InstallHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {arg}")?;
+        }
+        Ok(())
+    }
+}
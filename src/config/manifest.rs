@@ -6,6 +6,11 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::config::{
+    ConfigFilePolicy, FileAssociation, InstallHook, InstallScope, MimeType, SecondInstancePolicy,
+    Service, UrlScheme,
+};
+
 pub type AppManifest = AppManifestBuilder<String, String, String>;
 
 impl AppManifest {
@@ -16,6 +21,21 @@ impl AppManifest {
             bin_name: (),
             publisher: None,
             icon: None,
+            hooks: Vec::new(),
+            templates: Vec::new(),
+            config_files: Vec::new(),
+            config_file_policy: ConfigFilePolicy::default(),
+            default_scope: InstallScope::default(),
+            allow_scope_choice: false,
+            mime_types: Vec::new(),
+            add_to_path: false,
+            product_guid: None,
+            url: None,
+            file_associations: Vec::new(),
+            url_schemes: Vec::new(),
+            services: Vec::new(),
+            readme: None,
+            second_instance: SecondInstancePolicy::default(),
         }
     }
 }
@@ -27,6 +47,53 @@ pub struct AppManifestBuilder<A, B, C> {
     pub bin_name: C,
     pub publisher: Option<String>,
     pub icon: Option<Vec<u8>>,
+    /// Commands run before and after the files are installed
+    #[serde(default)]
+    pub hooks: Vec<InstallHook>,
+    /// Payload files, relative to the install directory, that are rendered as templates.
+    /// Files ending in `.tmpl` are always rendered and written without the extension.
+    #[serde(default)]
+    pub templates: Vec<String>,
+    /// Payload files, relative to the install directory, the user may edit.
+    /// They aren't replaced blindly when a new version is installed over a modified copy.
+    #[serde(default)]
+    pub config_files: Vec<String>,
+    #[serde(default)]
+    pub config_file_policy: ConfigFilePolicy,
+    #[serde(default)]
+    pub default_scope: InstallScope,
+    /// Lets the user choose between a per-user and a per-machine installation
+    #[serde(default)]
+    pub allow_scope_choice: bool,
+    /// MIME types the application can open, listed in its desktop entry on Linux
+    #[serde(default)]
+    pub mime_types: Vec<MimeType>,
+    /// Offers to make `bin_name` available on `PATH`, which is preselected
+    #[serde(default)]
+    pub add_to_path: bool,
+    /// Identifies the product in the Windows registry, e.g. `{8F7A1D2C-0B3E-4F5A-9C6D-1E2F3A4B5C6D}`.
+    /// Derived from the publisher and `bin_name` if not set, so it stays the same across versions.
+    #[serde(default)]
+    pub product_guid: Option<String>,
+    /// Website of the application, shown in the list of installed programs
+    #[serde(default)]
+    pub url: Option<String>,
+    /// File extensions the application opens
+    #[serde(default)]
+    pub file_associations: Vec<FileAssociation>,
+    /// URL schemes the application handles
+    #[serde(default)]
+    pub url_schemes: Vec<UrlScheme>,
+    /// Background services installed with the application
+    #[serde(default)]
+    pub services: Vec<Service>,
+    /// Payload file, relative to the install directory, that is offered to be opened once the installation completed,
+    /// e.g. a README or release notes
+    #[serde(default)]
+    pub readme: Option<String>,
+    /// What happens if an installer of the product is started while another one is running
+    #[serde(default)]
+    pub second_instance: SecondInstancePolicy,
 }
 
 impl<A, B, C> AppManifestBuilder<A, B, C> {
@@ -37,6 +104,21 @@ impl<A, B, C> AppManifestBuilder<A, B, C> {
             bin_name: self.bin_name,
             publisher: self.publisher,
             icon: self.icon,
+            hooks: self.hooks,
+            templates: self.templates,
+            config_files: self.config_files,
+            config_file_policy: self.config_file_policy,
+            default_scope: self.default_scope,
+            allow_scope_choice: self.allow_scope_choice,
+            mime_types: self.mime_types,
+            add_to_path: self.add_to_path,
+            product_guid: self.product_guid,
+            url: self.url,
+            file_associations: self.file_associations,
+            url_schemes: self.url_schemes,
+            services: self.services,
+            readme: self.readme,
+            second_instance: self.second_instance,
         }
     }
 
@@ -47,6 +129,21 @@ impl<A, B, C> AppManifestBuilder<A, B, C> {
             bin_name: self.bin_name,
             publisher: self.publisher,
             icon: self.icon,
+            hooks: self.hooks,
+            templates: self.templates,
+            config_files: self.config_files,
+            config_file_policy: self.config_file_policy,
+            default_scope: self.default_scope,
+            allow_scope_choice: self.allow_scope_choice,
+            mime_types: self.mime_types,
+            add_to_path: self.add_to_path,
+            product_guid: self.product_guid,
+            url: self.url,
+            file_associations: self.file_associations,
+            url_schemes: self.url_schemes,
+            services: self.services,
+            readme: self.readme,
+            second_instance: self.second_instance,
         }
     }
 
@@ -57,6 +154,21 @@ impl<A, B, C> AppManifestBuilder<A, B, C> {
             bin_name: bin_name.into(),
             publisher: self.publisher,
             icon: self.icon,
+            hooks: self.hooks,
+            templates: self.templates,
+            config_files: self.config_files,
+            config_file_policy: self.config_file_policy,
+            default_scope: self.default_scope,
+            allow_scope_choice: self.allow_scope_choice,
+            mime_types: self.mime_types,
+            add_to_path: self.add_to_path,
+            product_guid: self.product_guid,
+            url: self.url,
+            file_associations: self.file_associations,
+            url_schemes: self.url_schemes,
+            services: self.services,
+            readme: self.readme,
+            second_instance: self.second_instance,
         }
     }
 
@@ -67,6 +179,21 @@ impl<A, B, C> AppManifestBuilder<A, B, C> {
             bin_name: self.bin_name,
             publisher: Some(publisher.into()),
             icon: self.icon,
+            hooks: self.hooks,
+            templates: self.templates,
+            config_files: self.config_files,
+            config_file_policy: self.config_file_policy,
+            default_scope: self.default_scope,
+            allow_scope_choice: self.allow_scope_choice,
+            mime_types: self.mime_types,
+            add_to_path: self.add_to_path,
+            product_guid: self.product_guid,
+            url: self.url,
+            file_associations: self.file_associations,
+            url_schemes: self.url_schemes,
+            services: self.services,
+            readme: self.readme,
+            second_instance: self.second_instance,
         }
     }
 
@@ -77,6 +204,96 @@ impl<A, B, C> AppManifestBuilder<A, B, C> {
             bin_name: self.bin_name,
             publisher: self.publisher,
             icon: Some(icon),
+            hooks: self.hooks,
+            templates: self.templates,
+            config_files: self.config_files,
+            config_file_policy: self.config_file_policy,
+            default_scope: self.default_scope,
+            allow_scope_choice: self.allow_scope_choice,
+            mime_types: self.mime_types,
+            add_to_path: self.add_to_path,
+            product_guid: self.product_guid,
+            url: self.url,
+            file_associations: self.file_associations,
+            url_schemes: self.url_schemes,
+            services: self.services,
+            readme: self.readme,
+            second_instance: self.second_instance,
         }
     }
+
+    pub fn hook(mut self, hook: InstallHook) -> AppManifestBuilder<A, B, C> {
+        self.hooks.push(hook);
+        self
+    }
+
+    pub fn template(mut self, path: impl Into<String>) -> AppManifestBuilder<A, B, C> {
+        self.templates.push(path.into());
+        self
+    }
+
+    pub fn config_file(mut self, path: impl Into<String>) -> AppManifestBuilder<A, B, C> {
+        self.config_files.push(path.into());
+        self
+    }
+
+    pub fn config_file_policy(mut self, policy: ConfigFilePolicy) -> AppManifestBuilder<A, B, C> {
+        self.config_file_policy = policy;
+        self
+    }
+
+    pub fn default_scope(mut self, scope: InstallScope) -> AppManifestBuilder<A, B, C> {
+        self.default_scope = scope;
+        self
+    }
+
+    pub fn allow_scope_choice(mut self, allow: bool) -> AppManifestBuilder<A, B, C> {
+        self.allow_scope_choice = allow;
+        self
+    }
+
+    pub fn mime_type(mut self, mime_type: MimeType) -> AppManifestBuilder<A, B, C> {
+        self.mime_types.push(mime_type);
+        self
+    }
+
+    pub fn file_association(mut self, association: FileAssociation) -> AppManifestBuilder<A, B, C> {
+        self.file_associations.push(association);
+        self
+    }
+
+    pub fn url_scheme(mut self, scheme: UrlScheme) -> AppManifestBuilder<A, B, C> {
+        self.url_schemes.push(scheme);
+        self
+    }
+
+    pub fn service(mut self, service: Service) -> AppManifestBuilder<A, B, C> {
+        self.services.push(service);
+        self
+    }
+
+    pub fn add_to_path(mut self, add_to_path: bool) -> AppManifestBuilder<A, B, C> {
+        self.add_to_path = add_to_path;
+        self
+    }
+
+    pub fn product_guid(mut self, guid: impl Into<String>) -> AppManifestBuilder<A, B, C> {
+        self.product_guid = Some(guid.into());
+        self
+    }
+
+    pub fn url(mut self, url: impl Into<String>) -> AppManifestBuilder<A, B, C> {
+        self.url = Some(url.into());
+        self
+    }
+
+    pub fn readme(mut self, path: impl Into<String>) -> AppManifestBuilder<A, B, C> {
+        self.readme = Some(path.into());
+        self
+    }
+
+    pub fn second_instance(mut self, policy: SecondInstancePolicy) -> AppManifestBuilder<A, B, C> {
+        self.second_instance = policy;
+        self
+    }
 }
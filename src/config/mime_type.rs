@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// A MIME type the application opens, e.g. `application/x-frost-project`.
+///
+/// Types with file name patterns are also defined on the system, so files matching them are recognized.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct MimeType {
+    pub name: String,
+    /// Human readable name of the type, e.g. "Frost project"
+    #[serde(default)]
+    pub description: Option<String>,
+    /// File name patterns like `*.frost`
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+impl MimeType {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            description: None,
+            patterns: Vec::new(),
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.patterns.push(pattern.into());
+        self
+    }
+}
@@ -0,0 +1,163 @@
+use std::sync::OnceLock;
+
+/// Decides whether a payload is installed on the system the installer runs on
+pub enum PayloadCondition {
+    /// The operating system, named like `std::env::consts::OS`, e.g. `linux` or `windows`
+    Os(String),
+    /// The architecture of the machine, named like `std::env::consts::ARCH`, e.g. `x86_64` or `aarch64`
+    Arch(String),
+    /// Met if all of the conditions are met
+    All(Vec<PayloadCondition>),
+    /// Met if any of the conditions is met
+    Any(Vec<PayloadCondition>),
+    /// A predicate for anything the other conditions don't cover, e.g. the Linux distribution
+    Custom(Box<dyn Fn(&TargetSystem) -> bool + Send + Sync>),
+}
+
+impl PayloadCondition {
+    pub fn os(os: impl Into<String>) -> Self {
+        Self::Os(os.into())
+    }
+
+    pub fn arch(arch: impl Into<String>) -> Self {
+        Self::Arch(arch.into())
+    }
+
+    pub fn custom(predicate: impl Fn(&TargetSystem) -> bool + Send + Sync + 'static) -> Self {
+        Self::Custom(Box::new(predicate))
+    }
+
+    pub fn is_met(&self) -> bool {
+        self.is_met_on(TargetSystem::current())
+    }
+
+    pub fn is_met_on(&self, system: &TargetSystem) -> bool {
+        match self {
+            PayloadCondition::Os(os) => os.eq_ignore_ascii_case(system.os),
+            PayloadCondition::Arch(arch) => arch.eq_ignore_ascii_case(&system.arch),
+            PayloadCondition::All(conditions) => conditions
+                .iter()
+                .all(|condition| condition.is_met_on(system)),
+            PayloadCondition::Any(conditions) => conditions
+                .iter()
+                .any(|condition| condition.is_met_on(system)),
+            PayloadCondition::Custom(predicate) => predicate(system),
+        }
+    }
+}
+
+/// The system the installer runs on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TargetSystem {
+    /// Named like `std::env::consts::OS`
+    pub os: &'static str,
+    /// Architecture of the machine rather than of the installer, which may be emulated.
+    /// Named like `std::env::consts::ARCH`.
+    pub arch: String,
+}
+
+impl TargetSystem {
+    /// Detected once and reused, conditions are evaluated whenever the required space is calculated
+    pub fn current() -> &'static TargetSystem {
+        static CURRENT: OnceLock<TargetSystem> = OnceLock::new();
+        CURRENT.get_or_init(|| TargetSystem {
+            os: std::env::consts::OS,
+            arch: machine_arch().unwrap_or_else(|| std::env::consts::ARCH.to_string()),
+        })
+    }
+}
+
+#[cfg(unix)]
+fn machine_arch() -> Option<String> {
+    use std::{ffi::CStr, mem::MaybeUninit};
+
+    let mut uname = MaybeUninit::<libc::utsname>::uninit();
+    if unsafe { libc::uname(uname.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let uname = unsafe { uname.assume_init() };
+    let machine = unsafe { CStr::from_ptr(uname.machine.as_ptr()) }.to_string_lossy();
+    Some(normalize_arch(&machine))
+}
+
+#[cfg(windows)]
+fn machine_arch() -> Option<String> {
+    crate::windows::native_arch().map(str::to_string)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn machine_arch() -> Option<String> {
+    None
+}
+
+/// Maps the names `uname` reports to those Rust uses
+#[cfg(unix)]
+fn normalize_arch(machine: &str) -> String {
+    match machine {
+        "amd64" => "x86_64",
+        "arm64" => "aarch64",
+        "i386" | "i486" | "i586" | "i686" => "x86",
+        machine if machine.starts_with("armv") => "arm",
+        machine => machine,
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system(os: &'static str, arch: &str) -> TargetSystem {
+        TargetSystem {
+            os,
+            arch: arch.to_string(),
+        }
+    }
+
+    #[test]
+    fn compares_os_and_arch_ignoring_case() {
+        let system = system("linux", "aarch64");
+
+        assert!(PayloadCondition::os("Linux").is_met_on(&system));
+        assert!(!PayloadCondition::os("windows").is_met_on(&system));
+        assert!(PayloadCondition::arch("AArch64").is_met_on(&system));
+        assert!(!PayloadCondition::arch("x86_64").is_met_on(&system));
+    }
+
+    #[test]
+    fn combines_conditions() {
+        let system = system("linux", "x86_64");
+        let linux = || PayloadCondition::os("linux");
+        let arm = || PayloadCondition::arch("aarch64");
+
+        assert!(
+            PayloadCondition::All(vec![linux(), PayloadCondition::arch("x86_64")])
+                .is_met_on(&system)
+        );
+        assert!(!PayloadCondition::All(vec![linux(), arm()]).is_met_on(&system));
+        assert!(PayloadCondition::Any(vec![arm(), linux()]).is_met_on(&system));
+        assert!(
+            !PayloadCondition::Any(vec![arm(), PayloadCondition::os("macos")]).is_met_on(&system)
+        );
+        // Like `Iterator::all` and `Iterator::any`
+        assert!(PayloadCondition::All(Vec::new()).is_met_on(&system));
+        assert!(!PayloadCondition::Any(Vec::new()).is_met_on(&system));
+    }
+
+    #[test]
+    fn custom_condition_gets_system() {
+        let condition = PayloadCondition::custom(|system| system.arch.starts_with("arm"));
+
+        assert!(condition.is_met_on(&system("linux", "arm")));
+        assert!(!condition.is_met_on(&system("linux", "x86")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn normalizes_uname_arch() {
+        assert_eq!(normalize_arch("amd64"), "x86_64");
+        assert_eq!(normalize_arch("i686"), "x86");
+        assert_eq!(normalize_arch("armv7l"), "arm");
+        assert_eq!(normalize_arch("riscv64"), "riscv64");
+    }
+}
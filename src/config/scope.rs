@@ -0,0 +1,58 @@
+use std::{fmt::Display, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Who an application is installed for
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Default, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum InstallScope {
+    /// For all users, into a system location. Requires administrator rights on Windows.
+    #[default]
+    Machine,
+    /// Only for the current user, into their profile. Never requires elevation.
+    User,
+}
+
+impl InstallScope {
+    /// Directory applications of this scope are installed into, e.g. `C:\Program Files` or `~/.local/opt`
+    pub fn programs_dir(self) -> PathBuf {
+        #[cfg(windows)]
+        match self {
+            InstallScope::Machine => std::env::var_os("ProgramFiles")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from("C:\\Program Files")),
+            InstallScope::User => std::env::var_os("LOCALAPPDATA")
+                .map(PathBuf::from)
+                .or_else(|| std::env::home_dir().map(|home| home.join("AppData").join("Local")))
+                .unwrap_or_default()
+                .join("Programs"),
+        }
+
+        #[cfg(unix)]
+        match self {
+            InstallScope::Machine => PathBuf::from("/opt"),
+            InstallScope::User => std::env::home_dir()
+                .unwrap_or_default()
+                .join(".local")
+                .join("opt"),
+        }
+    }
+
+    pub fn default_install_path(self, bin_name: &str) -> PathBuf {
+        self.programs_dir().join(bin_name)
+    }
+
+    /// Whether the installer has to run elevated to install in this scope
+    pub fn requires_elevation(self) -> bool {
+        cfg!(windows) && self == InstallScope::Machine
+    }
+}
+
+impl Display for InstallScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstallScope::Machine => write!(f, "Install for all users"),
+            InstallScope::User => write!(f, "Install just for me"),
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// What an installer does when another installer of the same product is already running.
+///
+/// Installers of a product are told apart by its product GUID, so different versions exclude each other too.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum SecondInstancePolicy {
+    /// Tell the user and exit with a dedicated exit code
+    #[default]
+    Exit,
+    /// Wait until the other installer exits, then continue
+    Wait,
+}
@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+/// A background service that is installed with the application, e.g. a server component.
+///
+/// On Linux it becomes a systemd unit, a system unit for per-machine installations and a user unit otherwise.
+/// On Windows it is registered with the service control manager, which requires a per-machine installation.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Service {
+    /// Name the service is managed by, e.g. `frost-server`
+    pub name: String,
+    /// Human readable name, shown by service managers
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Binary of the service, relative to the install directory
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Account the service runs as, root or `LocalSystem` if not set.
+    ///
+    /// Only system units and built-in Windows accounts like `NT AUTHORITY\LocalService` are supported, there's no way to pass a password.
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub restart: RestartPolicy,
+    #[serde(default)]
+    pub start: StartType,
+}
+
+/// When a stopped service is started again
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    Never,
+    /// When the service exits with an error or crashes
+    #[default]
+    OnFailure,
+    /// Whenever the service exits. Windows can only restart failed services, so there it is the same as `OnFailure`.
+    Always,
+}
+
+/// When the service is started
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum StartType {
+    /// At boot, and right after the installation
+    #[default]
+    Automatic,
+    /// Only when started by hand
+    Manual,
+    /// Can't be started on Windows. Linux has no equivalent for installed units, they behave like `Manual`.
+    Disabled,
+}
+
+impl Service {
+    pub fn new(name: impl Into<String>, program: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            display_name: None,
+            description: None,
+            program: program.into(),
+            args: Vec::new(),
+            user: None,
+            restart: RestartPolicy::default(),
+            start: StartType::default(),
+        }
+    }
+
+    pub fn display_name(mut self, display_name: impl Into<String>) -> Self {
+        self.display_name = Some(display_name.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    pub fn restart(mut self, restart: RestartPolicy) -> Self {
+        self.restart = restart;
+        self
+    }
+
+    pub fn start(mut self, start: StartType) -> Self {
+        self.start = start;
+        self
+    }
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// A URL scheme the application handles, e.g. `frostapp` for `frostapp://` links.
+///
+/// The URL is passed to the application as its only argument.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct UrlScheme {
+    /// The scheme without `://`
+    pub scheme: String,
+    /// Human readable name of the links, e.g. "Frost link"
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl UrlScheme {
+    pub fn new(scheme: impl Into<String>) -> Self {
+        Self {
+            scheme: scheme.into().trim_end_matches("://").to_lowercase(),
+            description: None,
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
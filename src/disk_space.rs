@@ -0,0 +1,53 @@
+//! Free space of the volume an installation goes to
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Returns the number of bytes available to the current user on the filesystem that holds `path`.
+///
+/// The path doesn't need to exist yet, the closest existing parent directory is used instead.
+pub fn available_space(path: &Path) -> io::Result<u64> {
+    let existing = existing_ancestor(path)?;
+    filesystem_available_space(&existing)
+}
+
+fn existing_ancestor(path: &Path) -> io::Result<PathBuf> {
+    let path = std::path::absolute(path)?;
+    path.ancestors()
+        .find(|ancestor| ancestor.exists())
+        .map(Path::to_path_buf)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No existing parent directory for {}", path.display()),
+            )
+        })
+}
+
+#[cfg(unix)]
+fn filesystem_available_space(path: &Path) -> io::Result<u64> {
+    use std::{ffi::CString, mem::MaybeUninit, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let stat = unsafe { stat.assume_init() };
+
+    // The field types differ between unix platforms
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+fn filesystem_available_space(path: &Path) -> io::Result<u64> {
+    use windows::{Win32::Storage::FileSystem::GetDiskFreeSpaceExW, core::HSTRING};
+
+    let mut available = 0u64;
+    unsafe { GetDiskFreeSpaceExW(&HSTRING::from(path), Some(&mut available), None, None) }?;
+
+    Ok(available)
+}
@@ -1,24 +1,70 @@
-#[cfg(target_os = "windows")]
-use std::path::Path;
-use std::{
-    fs,
-    io::{Read, Write},
-    time::Duration,
-};
+use std::{collections::BTreeMap, path::Path, time::Duration};
 
 use indicatif::{ProgressBar, ProgressStyle};
 use sipper::{FutureExt, Sipper, sipper};
 use tokio::sync::mpsc;
-use zip::{ZipArchive, result::ZipError};
+use zip::result::ZipError;
 
 use crate::{
-    config::{AppManifest, FilePayload, InstallConfig},
-    ui::InstallerUi,
+    config::{AppManifest, HookStage, InstallConfig},
+    disk_space::available_space,
+    requirement::{Requirement, check_requirements},
+    ui::{InstallerUi, format_size},
 };
 
+mod associations;
+mod cancel;
+mod config_files;
+#[cfg(target_os = "linux")]
+mod desktop;
+mod download;
+mod env_path;
+mod extract;
+mod hooks;
+mod instance;
+mod journal;
+mod launch;
+mod log;
+mod plan;
+mod private_dir;
+mod progress;
+mod receipt;
+mod services;
+#[cfg(any(windows, test))]
+mod shortcuts;
+mod template;
+mod uninstall;
+mod uninstall_entry;
+
+pub use cancel::CancelHandle;
+pub use config_files::ConfigFileOutcome;
+
+use config_files::ConfigFiles;
+pub use download::DownloadError;
+use download::Downloader;
+use extract::OpenedPayload;
+use hooks::run_hooks;
+use instance::lock_instance;
+use journal::Journal;
+pub(crate) use launch::{launch_application, open_file, open_path};
+use log::InstallLog;
+pub(crate) use log::log_path;
+pub use plan::PlanFormat;
+use progress::ProgressReporter;
+pub use progress::{Phase, Progress};
+use receipt::{Receipt, ReceiptError};
+use services::SystemCommandRunner;
+use template::Templates;
+
+/// Exit code of an unattended installation if requirements aren't met
+const REQUIREMENTS_NOT_MET_EXIT_CODE: i32 = 2;
+/// Exit code if another installer of the product is running and the manifest doesn't wait for it
+const ALREADY_RUNNING_EXIT_CODE: i32 = 3;
+
 pub struct Installer<Wizard> {
     manifest: AppManifest,
     wizard: Wizard,
+    requirements: Vec<Box<dyn Requirement>>,
 }
 
 impl<Wizard> Installer<Wizard>
@@ -27,23 +73,111 @@ where
 {
     pub fn from_wizard(wizard: Wizard) -> Self {
         let manifest = wizard.get_manifest();
-        Self { wizard, manifest }
+        Self {
+            wizard,
+            manifest,
+            requirements: Vec::new(),
+        }
+    }
+
+    /// Adds a prerequisite that is checked before the installation starts
+    pub fn require(mut self, requirement: impl Requirement + 'static) -> Self {
+        self.requirements.push(Box::new(requirement));
+        self
     }
 
     pub fn run(mut self) -> Result<(), iced::Error> {
+        if let Some(install_path) = self.wizard.unattended_uninstall() {
+            let name = &self.manifest.friendly_name;
+            let _instance = match lock_instance(&self.manifest, &CancelHandle::new(), || {
+                eprintln!("Waiting for another installer of {name} to finish...");
+            }) {
+                Ok(lock) => lock,
+                Err(err) => {
+                    eprintln!("{err}");
+                    std::process::exit(match err {
+                        InstallError::AlreadyRunning(_) => ALREADY_RUNNING_EXIT_CODE,
+                        _ => 1,
+                    });
+                }
+            };
+            match uninstall::uninstall(&install_path) {
+                Ok(()) => {
+                    println!("{} uninstalled successfully!", self.manifest.friendly_name);
+                    std::process::exit(0);
+                }
+                Err(err) => {
+                    eprintln!("Error during uninstall: {}", err);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        if let Some((config, format)) = self.wizard.unattended_plan() {
+            let mut plan = match plan::plan(config, &self.manifest) {
+                Ok(plan) => plan,
+                Err(err) => {
+                    eprintln!("The installation would fail: {}", err);
+                    std::process::exit(1);
+                }
+            };
+            plan.unmet_requirements = check_requirements(&self.requirements)
+                .into_iter()
+                .map(|requirement| format!("{}: {}", requirement.description, requirement.reason))
+                .collect();
+            match format {
+                PlanFormat::Text => print!("{plan}"),
+                PlanFormat::Json => println!("{}", plan.to_json()),
+            }
+            if plan.unmet_requirements.is_empty() {
+                std::process::exit(0);
+            } else {
+                std::process::exit(REQUIREMENTS_NOT_MET_EXIT_CODE);
+            }
+        }
+
         if let Some(config) = self.wizard.unattended_install() {
+            let unmet = check_requirements(&self.requirements);
+            if !unmet.is_empty() {
+                eprintln!(
+                    "Requirements for installing {} aren't met:",
+                    self.manifest.friendly_name
+                );
+                for requirement in unmet {
+                    eprintln!("- {}: {}", requirement.description, requirement.reason);
+                }
+                std::process::exit(REQUIREMENTS_NOT_MET_EXIT_CODE);
+            }
+
             // Perform unattended installation using the provided config
             let runtime = tokio::runtime::Runtime::new().unwrap();
-            let name = self.manifest.friendly_name.clone();
+            let manifest = self.manifest.clone();
+            // A staged installation can't run before it is deployed
+            let launch_path =
+                (config.launch && config.root.is_none()).then(|| config.install_path.clone());
+            let log_path = log_path(&config, &self.manifest);
             let install_result =
                 runtime.block_on(async { install_unattended(config, self.manifest).await });
             match install_result {
-                Ok(()) => {
-                    println!("{} installed successfully!", name);
+                Ok(report) => {
+                    println!("{} installed successfully!", manifest.friendly_name);
+                    for outcome in report.config_files {
+                        println!("{outcome}");
+                    }
+                    if let Some(install_path) = launch_path
+                        && let Err(err) = launch_application(&manifest, &install_path)
+                    {
+                        eprintln!("Failed to start {}:\n{}", manifest.friendly_name, err);
+                    }
                     std::process::exit(0);
                 }
+                Err(err @ InstallError::AlreadyRunning(_)) => {
+                    eprintln!("{err}");
+                    std::process::exit(ALREADY_RUNNING_EXIT_CODE);
+                }
                 Err(err) => {
                     eprintln!("Error during unattended install: {}", err);
+                    eprintln!("See the install log for details: {}", log_path.display());
                     std::process::exit(1);
                 }
             }
@@ -53,63 +187,145 @@ where
                 InstallerUi::<Wizard>::update,
                 InstallerUi::<Wizard>::view,
             )
-            .run_with(move || InstallerUi::start(self.wizard, self.manifest))
+            .subscription(InstallerUi::<Wizard>::subscription)
+            .exit_on_close_request(false)
+            .run_with(move || InstallerUi::start(self.wizard, self.manifest, self.requirements))
         }
     }
 }
 
-const BAR_FACTOR: f32 = 1000.0;
-
 async fn install_unattended(
     config: InstallConfig,
     manifest: AppManifest,
-) -> Result<(), InstallError> {
-    let mut sipper = install(config, manifest.clone(), |progress| progress).pin();
+) -> Result<InstallReport, InstallError> {
+    let cancel = CancelHandle::new();
+    let mut sipper = install(config, manifest.clone(), cancel.clone(), |progress| {
+        progress
+    })
+    .pin();
 
-    let bar = ProgressBar::new(BAR_FACTOR as u64)
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel.cancel();
+        }
+    });
+
+    let bar = ProgressBar::new(0)
         .with_style(
             ProgressStyle::with_template(
-                "{spinner} {msg}\n[{percent}%] {wide_bar:40.cyan/blue} [{elapsed}]",
+                "{spinner} {prefix}: {wide_msg}\n[{percent}%] {wide_bar:40.cyan/blue} {bytes}/{total_bytes} ({binary_bytes_per_sec}, ETA {eta}) [{elapsed}]",
             )
             .expect("Fixed template can't fail")
             .progress_chars("##-"),
         )
-        .with_message(format!("Installing {}", manifest.friendly_name));
+        .with_prefix(format!("Installing {}", manifest.friendly_name));
 
     bar.enable_steady_tick(Duration::from_millis(100));
 
     while let Some(progress) = sipper.sip().await {
-        bar.set_position((progress * BAR_FACTOR) as u64);
+        bar.set_length(progress.bytes_total);
+        bar.set_position(progress.bytes_done);
+        bar.set_message(match (&progress.current_file, &progress.current_task) {
+            (Some(file), _) => format!(
+                "{} ({}/{}) {}",
+                progress.phase,
+                progress.files_done,
+                progress.files_total,
+                file.display()
+            ),
+            (None, Some(task)) => format!("{}: {}", progress.phase, task),
+            (None, None) => progress.phase.to_string(),
+        });
     }
 
     let result = sipper.await;
 
+    bar.finish();
+
     result
 }
 
+/// Things the user should know about a completed installation
+#[derive(Debug, Clone, Default)]
+pub struct InstallReport {
+    /// Modified configuration files that weren't simply replaced
+    pub config_files: Vec<ConfigFileOutcome>,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum InstallError {
+    #[error("Installation was cancelled")]
+    Cancelled,
+    #[error("Another installer of {0} is already running")]
+    AlreadyRunning(String),
+    #[error("Failed to check for other running installers:\n{0}")]
+    InstanceLock(std::io::Error),
+    #[error("Failed to download {url}:\n{error}")]
+    Download { url: String, error: DownloadError },
     #[error("Failed to create install directory:\n{0}")]
     CreateInstallDir(std::io::Error),
     #[error("Failed to write payload into install directory:\n{0}")]
     WritePayload(std::io::Error),
     #[error("Failed to extract payload into install directory:\n{0}")]
     ZipError(ZipError),
+    #[error("Payload entry {0:?} would be written outside of the install directory")]
+    UnsafePayloadPath(String),
+    #[error("Payload symlink {link:?} points outside of the install directory: {target:?}")]
+    UnsafeSymlink { link: String, target: String },
+    #[error("Failed to run setup task `{command}`:\n{error}")]
+    HookError {
+        command: String,
+        error: std::io::Error,
+    },
+    #[error("Setup task `{command}` failed with exit code {}:\n{output}", code.map_or("unknown".to_string(), |code| code.to_string()))]
+    HookFailed {
+        command: String,
+        code: Option<i32>,
+        output: String,
+    },
+    #[error("Setup task `{command}` didn't finish within {timeout_secs} seconds")]
+    HookTimeout { command: String, timeout_secs: u64 },
+    #[error(
+        "Not enough disk space: {} required, but only {} available",
+        format_size(*required),
+        format_size(*available)
+    )]
+    InsufficientSpace { required: u64, available: u64 },
+    #[error("Failed to read the receipt of the previous installation:\n{0}")]
+    ReadReceipt(ReceiptError),
+    #[error("Failed to write install receipt:\n{0}")]
+    WriteReceipt(std::io::Error),
+    #[error("Failed to create menu entries and icons:\n{0}")]
+    DesktopIntegration(std::io::Error),
+    #[error("Failed to add the application to PATH:\n{0}")]
+    AddToPath(std::io::Error),
+    #[error("Failed to install services:\n{0}")]
+    InstallServices(std::io::Error),
+    #[cfg(windows)]
+    #[error("Failed to create shortcuts:\n{0}")]
+    CreateShortcuts(std::io::Error),
+    #[cfg(windows)]
+    #[error("Failed to register file types and URL schemes:\n{0}")]
+    RegisterAssociations(std::io::Error),
+    #[cfg(windows)]
+    #[error("Failed to write uninstaller:\n{0}")]
+    WriteUninstaller(std::io::Error),
     #[cfg(windows)]
     #[error("Failed to set Registry Keys:\n{0}")]
-    RegistryError(windows_result::Error),
+    RegistryError(std::io::Error),
 }
 
 pub(crate) fn install<Output>(
     config: InstallConfig,
     manifest: AppManifest,
-    mapper: impl Fn(Result<(), InstallError>) -> Output,
-) -> impl sipper::Sipper<Output, f32> {
+    cancel: CancelHandle,
+    mapper: impl Fn(Result<InstallReport, InstallError>) -> Output,
+) -> impl sipper::Sipper<Output, Progress> {
     let sipper = sipper(|mut sender| {
         async move {
             let (send, mut recv) = mpsc::channel(100);
 
-            let install_future = inner_install(send, config, manifest);
+            let install_future = inner_install(send, cancel, config, manifest);
 
             tokio::spawn(async move {
                 while let Some(progress) = recv.recv().await {
@@ -126,119 +342,364 @@ pub(crate) fn install<Output>(
 }
 
 async fn inner_install(
-    sender: mpsc::Sender<f32>,
+    sender: mpsc::Sender<Progress>,
+    cancel: CancelHandle,
     config: InstallConfig,
     manifest: AppManifest,
-) -> Result<(), InstallError> {
+) -> Result<InstallReport, InstallError> {
     tokio::task::spawn_blocking(move || {
-        fs::create_dir_all(&config.install_path).map_err(InstallError::CreateInstallDir)?;
-
-        let mut full_size = 0u64;
-
-        // Calculate overall size
-
-        for payload in &config.payloads {
-            match payload {
-                FilePayload::File { contents, .. } => full_size += contents.len() as u64,
-                FilePayload::Directory { unpacked_size, .. } => {
-                    full_size += *unpacked_size;
-                }
+        // Downloads are reported as progress too
+        let bytes_total = config.required_space() + config.download_size();
+        // Taken before the log is created, another installer may still be writing it
+        let _instance = lock_instance(&manifest, &cancel, || {
+            let _ = sender.blocking_send(Progress::new(Phase::Waiting, bytes_total));
+        })?;
+        let log = InstallLog::create(&config, &manifest);
+        let mut progress = ProgressReporter::new(sender, cancel, bytes_total, log);
+        let mut journal = Journal::default();
+
+        let result = install_blocking(config, &manifest, &mut progress, &mut journal);
+
+        match &result {
+            Ok(_) => progress.phase(Phase::Done),
+            Err(error) => {
+                progress.log().error("error", error);
+                progress.phase(Phase::RollingBack);
+                journal.rollback(&mut SystemCommandRunner);
             }
         }
 
-        let _full_size_kb = full_size / 1024;
-        // Size is a bit larger, so the progress isn't yet full when adding services and creating the registry entries
-        let full_size = full_size as f32 * 1.1;
+        result
+    })
+    .await
+    .unwrap()
+}
 
-        let mut written = 0u64;
+fn install_blocking(
+    mut config: InstallConfig,
+    manifest: &AppManifest,
+    progress: &mut ProgressReporter,
+    journal: &mut Journal,
+) -> Result<InstallReport, InstallError> {
+    let full_size = config.required_space();
+    progress.phase(Phase::Preparing);
+
+    // Files go below the staging root, paths written into them stay the real ones
+    let install_dir = config.staged(&config.install_path);
+    // Setup tasks, services and registrations act on the running system, which a staged installation leaves alone
+    let staged = config.root.is_some();
+    if let Some(root) = &config.root {
+        progress.log().info(
+            "staging",
+            format!(
+                "Writing below {}, setup tasks and system registration are skipped",
+                root.display()
+            ),
+        );
+    }
 
-        for payload in config.payloads {
-            match payload {
-                FilePayload::File { name, contents } => {
-                    let path = config.install_path.join(name.as_ref());
-                    fs::write(path, &contents).map_err(InstallError::WritePayload)?;
+    check_available_space(&install_dir, full_size)?;
+
+    journal
+        .create_dir_all(&install_dir)
+        .map_err(InstallError::CreateInstallDir)?;
+
+    let previous = Receipt::load(&install_dir)
+        .map_err(InstallError::ReadReceipt)?
+        .unwrap_or_default();
+    let templates = Templates::new(manifest, &config);
+    let mut config_files = ConfigFiles::new(manifest, previous.config_files);
+    let mut downloader = Downloader::new(config.scope);
+    let payloads = config
+        .take_payloads()
+        .into_iter()
+        .filter_map(|payload| OpenedPayload::open(payload, &mut downloader, progress).transpose())
+        .collect::<Result<Vec<_>, _>>()?;
+    progress.set_files_total(payloads.iter().map(OpenedPayload::file_count).sum());
+
+    if !staged {
+        run_hooks(
+            &manifest.hooks,
+            HookStage::PreInstall,
+            &config.install_path,
+            progress,
+        )?;
+
+        // Running services keep their binaries locked
+        let stopped =
+            services::stop_services(previous.scope, &previous.services, &mut SystemCommandRunner);
+        journal.track_stopped_services(previous.scope, stopped);
+    }
 
-                    written += contents.len() as u64;
+    progress.phase(Phase::Extracting);
 
-                    sender.blocking_send(written as f32 / full_size).unwrap();
-                }
-                FilePayload::Directory { reader, .. } => {
-                    let mut zip = ZipArchive::new(reader).map_err(InstallError::ZipError)?;
+    for payload in payloads {
+        payload.install(
+            &install_dir,
+            &templates,
+            &mut config_files,
+            progress,
+            journal,
+        )?;
+    }
 
-                    for index in 0..zip.len() {
-                        let mut reader = zip.by_index(index).map_err(InstallError::ZipError)?;
-                        let path = config.install_path.join(reader.name());
-                        if let Some(parent) = path.parent() {
-                            fs::create_dir_all(parent).map_err(InstallError::WritePayload)?;
-                        }
-                        let mut file =
-                            fs::File::create(path).map_err(InstallError::WritePayload)?;
+    if !staged {
+        run_hooks(
+            &manifest.hooks,
+            HookStage::PostInstall,
+            &config.install_path,
+            progress,
+        )?;
+    }
 
-                        let mut buf = [0; 8192];
+    // Past this point the installation can't be cancelled anymore
+    progress.check_cancelled()?;
+    progress.phase(Phase::Finalizing);
+
+    #[cfg(target_os = "linux")]
+    let desktop_data_dir =
+        desktop::integrate(manifest, &config, journal).map_err(InstallError::DesktopIntegration)?;
+    #[cfg(not(target_os = "linux"))]
+    let desktop_data_dir = None;
+
+    let path_entry = if config.add_to_path {
+        env_path::add_to_path(manifest, &config, journal).map_err(InstallError::AddToPath)?
+    } else {
+        None
+    };
+
+    #[cfg(target_os = "windows")]
+    shortcuts::create_shortcuts(manifest, &config, journal)
+        .map_err(InstallError::CreateShortcuts)?;
+
+    #[cfg(target_os = "windows")]
+    let associations = if staged {
+        associations::Associations::default()
+    } else {
+        let hive = crate::registry::Hive::for_scope(config.scope);
+        let registry = &mut crate::registry::SystemRegistry;
+        // Types an earlier version registered may not be part of this one anymore
+        associations::unregister(registry, hive, &previous.associations)
+            .map_err(InstallError::RegisterAssociations)?;
+        let install_path = config.install_path.to_string_lossy();
+        let associations = associations::register(registry, hive, manifest, &install_path)
+            .map_err(InstallError::RegisterAssociations)?;
+        if associations != previous.associations {
+            crate::windows::notify_association_change();
+        }
+        associations
+    };
+    #[cfg(not(target_os = "windows"))]
+    let associations = associations::Associations::default();
+
+    #[cfg(target_os = "windows")]
+    let uninstall_key = {
+        uninstall_entry::install_uninstaller(&install_dir, journal)
+            .map_err(InstallError::WriteUninstaller)?;
+        // The entry points to the uninstaller at its real location
+        let uninstaller = uninstall_entry::uninstaller_path(&config.install_path);
+        let entry = uninstall_entry::UninstallEntry {
+            manifest,
+            scope: config.scope,
+            install_path: &config.install_path,
+            uninstaller: &uninstaller,
+            size: full_size,
+            install_date: time::OffsetDateTime::now_utc().date(),
+        };
+        if staged {
+            None
+        } else {
+            Some(
+                entry
+                    .write(&mut crate::registry::SystemRegistry)
+                    .map_err(InstallError::RegistryError)?,
+            )
+        }
+    };
+    #[cfg(not(target_os = "windows"))]
+    let uninstall_key = None;
+
+    let services = services::install_services(
+        manifest,
+        &config,
+        &previous.services,
+        journal,
+        &mut SystemCommandRunner,
+    )
+    .map_err(InstallError::InstallServices)?;
+
+    // Files an earlier installation created stay part of the installation
+    let mut created = previous.created;
+    // Staging creates every directory leading to a file, outside of the install directory the target system has them already
+    let owned = |path: &Path| {
+        !staged
+            || path.starts_with(&install_dir)
+            || !std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir())
+    };
+    // Directories above the staging root aren't part of the installed system
+    for path in journal
+        .created()
+        .filter(|path| owned(path))
+        .filter_map(|path| config.unstaged(path))
+    {
+        if !created.contains(&path) {
+            created.push(path);
+        }
+    }
 
-                        loop {
-                            let n = reader.read(&mut buf).map_err(InstallError::WritePayload)?;
-                            if n == 0 {
-                                break;
-                            }
+    let receipt = Receipt {
+        version: manifest.version.clone(),
+        config_files: config_files.shipped,
+        created,
+        desktop_data_dir,
+        scope: config.scope,
+        // An earlier installation may have added the directory already
+        path_entry: path_entry.or(previous.path_entry),
+        uninstall_key,
+        associations,
+        services,
+        components: config.component_selection(),
+    };
+    receipt
+        .save(&install_dir, journal)
+        .map_err(InstallError::WriteReceipt)?;
+
+    // Kept with the installation once nothing can fail anymore, the log is removed when uninstalling
+    if let Err(error) = progress.log().keep_in(&install_dir) {
+        progress
+            .log()
+            .warn("log", format!("Failed to keep the log:\n{error}"));
+    }
 
-                            file.write_all(&buf[..n])
-                                .map_err(InstallError::WritePayload)?;
+    // Kept until now, so a failed installation can be retried without downloading again
+    downloader.remove_downloads();
 
-                            written += n as u64;
+    Ok(InstallReport {
+        config_files: config_files.outcomes,
+    })
+}
 
-                            sender.blocking_send(written as f32 / full_size).unwrap();
-                        }
-                    }
-                }
-            }
-        }
+/// The components an earlier installation in `install_path` offered and whether they were selected
+///
+/// An unreadable receipt is treated like none here, the installation itself reports it.
+pub fn installed_components(install_path: &Path) -> Option<BTreeMap<String, bool>> {
+    Receipt::load(install_path)
+        .ok()
+        .flatten()
+        .map(|receipt| receipt.components)
+}
 
-        let mut bin_path = config.install_path.clone();
-        bin_path.push(&manifest.bin_name);
+fn check_available_space(install_path: &Path, required: u64) -> Result<(), InstallError> {
+    // If the free space can't be determined, the installation is attempted anyway
+    if let Ok(available) = available_space(install_path)
+        && available < required
+    {
+        return Err(InstallError::InsufficientSpace {
+            required,
+            available,
+        });
+    }
+    Ok(())
+}
 
-        #[cfg(target_os = "windows")]
-        {
-            set_registry_keys(&manifest, &config.install_path, written)
-                .map_err(InstallError::RegistryError)?;
-            if config.create_start_menu_shortcut {}
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+    use crate::{
+        config::{FilePayload, InstallScope},
+        test_util::{TempDir, install_config, manifest},
+    };
+
+    #[test]
+    fn stages_installation_below_root() {
+        let root = TempDir::new();
+        let install_path = if cfg!(windows) {
+            PathBuf::from("C:\\Program Files\\Test App")
+        } else {
+            PathBuf::from("/opt/test-app")
+        };
+        let mut config = install_config(&install_path);
+        config.root = Some(root.path().to_path_buf());
+        config.scope = InstallScope::Machine;
+        config.create_start_menu_shortcut = true;
+        config.add_to_path = true;
+        config.payloads = vec![FilePayload::File {
+            name: "test-app".into(),
+            contents: b"binary".as_slice().into(),
+        }];
+        let install_dir = config.staged(&install_path);
+        let (mut progress, _receiver) = ProgressReporter::for_tests();
+        let mut journal = Journal::default();
+
+        install_blocking(config, &manifest(), &mut progress, &mut journal).unwrap();
+
+        assert!(install_dir.starts_with(root.path()));
+        assert_eq!(fs::read(install_dir.join("test-app")).unwrap(), b"binary");
+
+        // The receipt lists the real paths, only directories of the installation itself are removed when uninstalling
+        let receipt = Receipt::load(&install_dir).unwrap().unwrap();
+        let mut expected = vec![install_path.clone(), install_path.join("test-app")];
+        if cfg!(target_os = "linux") {
+            expected.push(PathBuf::from("/usr/share/applications/test-app.desktop"));
+            assert_eq!(receipt.desktop_data_dir, Some(PathBuf::from("/usr/share")));
         }
+        if cfg!(unix) {
+            expected.push(PathBuf::from("/usr/local/bin/test-app"));
+        }
+        assert_eq!(receipt.created, expected);
+    }
 
-        sender.blocking_send(1.0).unwrap();
-
-        Ok(())
-    })
-    .await
-    .unwrap()
-}
-
-#[cfg(target_os = "windows")]
-fn set_registry_keys(
-    manifest: &AppManifest,
-    install_location: &Path,
-    size: u64,
-) -> Result<(), windows_result::Error> {
-    let name_for_path = manifest
-        .friendly_name
-        .chars()
-        .filter(|c| c.is_alphanumeric())
-        .collect::<String>();
-    let registry_path = format!(
-        "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\{}",
-        name_for_path
-    );
-    let key = windows_registry::LOCAL_MACHINE.create(&registry_path)?;
-    key.set_string("DisplayName", &manifest.friendly_name)?;
-    key.set_string("DisplayVersion", &manifest.version)?;
-    key.set_string(
-        "InstallLocation",
-        install_location.to_string_lossy().as_ref(),
-    )?;
-    key.set_u32("EstimatedSize", (size / 1024) as u32)?;
-
-    if let Some(publisher) = &manifest.publisher {
-        key.set_string("Publisher", publisher)?;
+    #[cfg(unix)]
+    #[test]
+    fn staged_files_point_to_real_paths() {
+        let root = TempDir::new();
+        let mut config = install_config(Path::new("/opt/test-app"));
+        config.root = Some(root.path().to_path_buf());
+        config.scope = InstallScope::Machine;
+        config.create_start_menu_shortcut = true;
+        config.add_to_path = true;
+        let (mut progress, _receiver) = ProgressReporter::for_tests();
+
+        install_blocking(config, &manifest(), &mut progress, &mut Journal::default()).unwrap();
+
+        let staged = |path: &str| root.path().join(path.trim_start_matches('/'));
+        assert_eq!(
+            fs::read_link(staged("/usr/local/bin/test-app")).unwrap(),
+            Path::new("/opt/test-app/test-app")
+        );
+        if cfg!(target_os = "linux") {
+            let entry =
+                fs::read_to_string(staged("/usr/share/applications/test-app.desktop")).unwrap();
+            assert!(entry.contains("\nExec=/opt/test-app/test-app"), "{entry}");
+            assert!(!entry.contains(&*root.path().to_string_lossy()), "{entry}");
+        }
+        assert!(staged("/opt/test-app/.install-receipt").is_file());
     }
 
-    Ok(())
+    #[test]
+    fn refuses_installation_larger_than_free_space() {
+        let dir = TempDir::new();
+        let install_path = dir.path().join("app");
+        let mut config = install_config(&install_path);
+        config.payloads.push(FilePayload::Remote {
+            url: "https://example.com/payload.zip".into(),
+            sha256: "".into(),
+            size: u64::MAX,
+        });
+        let (mut progress, _receiver) = ProgressReporter::for_tests();
+
+        let result = install_blocking(config, &manifest(), &mut progress, &mut Journal::default());
+
+        assert!(
+            matches!(
+                result,
+                Err(InstallError::InsufficientSpace { required: u64::MAX, available })
+                    if available < u64::MAX
+            ),
+            "{result:?}"
+        );
+        assert!(!install_path.exists());
+    }
 }
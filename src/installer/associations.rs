@@ -0,0 +1,410 @@
+#[cfg(any(windows, test))]
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(any(windows, test))]
+use crate::{
+    config::{AppManifest, FileAssociation, UrlScheme},
+    installer::shortcuts::app_user_model_id,
+    registry::{Hive, RegistryBackend, RegistryValue},
+};
+
+/// Parent key of file types and URL schemes. Below `HKEY_CURRENT_USER` it only applies to the user.
+#[cfg(any(windows, test))]
+const CLASSES_KEY: &str = "SOFTWARE\\Classes";
+
+/// The default value of a key
+#[cfg(any(windows, test))]
+const DEFAULT_VALUE: &str = "";
+
+/// File types and URL schemes an installation registered, so uninstalling removes exactly those
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct Associations {
+    /// Keys below `SOFTWARE\Classes` that belong to the installation, the ProgIDs of file types and URL schemes
+    pub classes: Vec<String>,
+    /// Extensions with their leading dot and the ProgID registered for them
+    pub extensions: Vec<(String, String)>,
+}
+
+/// Registers the file associations and URL schemes of the manifest for the application in `install_path`.
+///
+/// An extension only becomes the default of the registered type if no other application claimed it,
+/// otherwise the application is just offered in "Open with".
+#[cfg(any(windows, test))]
+pub(super) fn register(
+    registry: &mut impl RegistryBackend,
+    hive: Hive,
+    manifest: &AppManifest,
+    install_path: &str,
+) -> io::Result<Associations> {
+    let install_path = install_path.trim_end_matches('\\');
+    let executable = format!("{install_path}\\{}", manifest.bin_name);
+    let command = format!("\"{executable}\" \"%1\"");
+    let mut associations = Associations::default();
+
+    for association in &manifest.file_associations {
+        let prog_id = prog_id(manifest, association);
+        let prog_id_key = class_key(&prog_id);
+        let description = association
+            .description
+            .clone()
+            .unwrap_or_else(|| format!("{} file", manifest.friendly_name));
+        let icon = match &association.icon {
+            Some(icon) => format!("{install_path}\\{}", icon.replace('/', "\\")),
+            None => format!("{executable},0"),
+        };
+        let verb = association.verb_name();
+
+        set_default(registry, hive, &prog_id_key, description)?;
+        set_default(registry, hive, &format!("{prog_id_key}\\DefaultIcon"), icon)?;
+        set_default(
+            registry,
+            hive,
+            &format!("{prog_id_key}\\shell"),
+            verb.to_string(),
+        )?;
+        set_default(
+            registry,
+            hive,
+            &format!("{prog_id_key}\\shell\\{verb}\\command"),
+            command.clone(),
+        )?;
+        associations.classes.push(prog_id.clone());
+
+        let extension = format!(".{}", association.extension);
+        let extension_key = class_key(&extension);
+        registry.set_value(
+            hive,
+            &format!("{extension_key}\\OpenWithProgids"),
+            &prog_id,
+            RegistryValue::String(String::new()),
+        )?;
+        let unclaimed = match registry.get_value(hive, &extension_key, DEFAULT_VALUE)? {
+            Some(RegistryValue::String(current)) => current.is_empty() || current == prog_id,
+            Some(_) => false,
+            None => true,
+        };
+        if unclaimed {
+            set_default(registry, hive, &extension_key, prog_id.clone())?;
+        }
+        if registry
+            .get_value(hive, &extension_key, "Content Type")?
+            .is_none()
+        {
+            registry.set_value(
+                hive,
+                &extension_key,
+                "Content Type",
+                RegistryValue::String(association.mime_type_name()),
+            )?;
+        }
+        associations.extensions.push((extension, prog_id));
+    }
+
+    for scheme in &manifest.url_schemes {
+        let scheme_key = class_key(&scheme.scheme);
+        set_default(
+            registry,
+            hive,
+            &scheme_key,
+            url_description(manifest, scheme),
+        )?;
+        registry.set_value(
+            hive,
+            &scheme_key,
+            "URL Protocol",
+            RegistryValue::String(String::new()),
+        )?;
+        set_default(
+            registry,
+            hive,
+            &format!("{scheme_key}\\DefaultIcon"),
+            format!("{executable},0"),
+        )?;
+        set_default(
+            registry,
+            hive,
+            &format!("{scheme_key}\\shell\\open\\command"),
+            command.clone(),
+        )?;
+        associations.classes.push(scheme.scheme.clone());
+    }
+
+    Ok(associations)
+}
+
+/// Removes what [`register`] registered.
+///
+/// Extensions keep their key, other applications may be registered for them too. They only lose their default if it still points to the application.
+#[cfg(any(windows, test))]
+pub(super) fn unregister(
+    registry: &mut impl RegistryBackend,
+    hive: Hive,
+    associations: &Associations,
+) -> io::Result<()> {
+    for (extension, prog_id) in &associations.extensions {
+        let extension_key = class_key(extension);
+        registry.delete_value(hive, &format!("{extension_key}\\OpenWithProgids"), prog_id)?;
+        if registry.get_value(hive, &extension_key, DEFAULT_VALUE)?
+            == Some(RegistryValue::String(prog_id.clone()))
+        {
+            registry.delete_value(hive, &extension_key, DEFAULT_VALUE)?;
+        }
+    }
+
+    for class in &associations.classes {
+        registry.delete_key(hive, &class_key(class))?;
+    }
+
+    Ok(())
+}
+
+/// Identifies the file type of an extension, e.g. `Publisher.App.frost`
+#[cfg(any(windows, test))]
+fn prog_id(manifest: &AppManifest, association: &FileAssociation) -> String {
+    let extension = association
+        .extension
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>();
+    format!("{}.{extension}", app_user_model_id(manifest))
+}
+
+#[cfg(any(windows, test))]
+fn url_description(manifest: &AppManifest, scheme: &UrlScheme) -> String {
+    let description = scheme
+        .description
+        .clone()
+        .unwrap_or_else(|| format!("{} link", manifest.friendly_name));
+    // Windows recognizes URL schemes by this prefix
+    format!("URL:{description}")
+}
+
+#[cfg(any(windows, test))]
+fn class_key(class: &str) -> String {
+    format!("{CLASSES_KEY}\\{class}")
+}
+
+#[cfg(any(windows, test))]
+fn set_default(
+    registry: &mut impl RegistryBackend,
+    hive: Hive,
+    key: &str,
+    value: String,
+) -> io::Result<()> {
+    registry.set_value(hive, key, DEFAULT_VALUE, RegistryValue::String(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{registry::MemoryRegistry, test_util::manifest};
+
+    const HIVE: Hive = Hive::CurrentUser;
+    const PROG_ID: &str = "FrostCorp.app.frost";
+
+    fn test_manifest() -> AppManifest {
+        manifest()
+            .bin_name("app.exe")
+            .publisher("Frost Corp.")
+            .file_association(
+                FileAssociation::new(".frost")
+                    .description("Frost project")
+                    .icon("icons/frost.ico"),
+            )
+            .file_association(FileAssociation::new("txt").verb("edit"))
+            .url_scheme(UrlScheme::new("FrostApp://"))
+    }
+
+    fn string(value: &str) -> RegistryValue {
+        RegistryValue::String(value.to_string())
+    }
+
+    fn values<const N: usize>(
+        entries: [(&str, &str); N],
+    ) -> Option<BTreeMap<String, RegistryValue>> {
+        Some(
+            entries
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), string(value)))
+                .collect(),
+        )
+    }
+
+    fn key_values(registry: &MemoryRegistry, key: &str) -> Option<BTreeMap<String, RegistryValue>> {
+        registry
+            .values(HIVE, &format!("{CLASSES_KEY}\\{key}"))
+            .cloned()
+    }
+
+    /// A registry in which another application owns `.txt` files
+    fn registry_with_text_editor() -> MemoryRegistry {
+        let mut registry = MemoryRegistry::new();
+        let txt_key = class_key(".txt");
+        registry
+            .set_value(HIVE, &txt_key, "", string("txtfile"))
+            .unwrap();
+        registry
+            .set_value(HIVE, &txt_key, "Content Type", string("text/plain"))
+            .unwrap();
+        registry
+            .set_value(
+                HIVE,
+                &format!("{txt_key}\\OpenWithProgids"),
+                "txtfile",
+                string(""),
+            )
+            .unwrap();
+        registry
+    }
+
+    #[test]
+    fn registers_file_types() {
+        let mut registry = registry_with_text_editor();
+
+        let associations = register(
+            &mut registry,
+            HIVE,
+            &test_manifest(),
+            "C:\\Program Files\\App\\",
+        )
+        .unwrap();
+
+        let command = "\"C:\\Program Files\\App\\app.exe\" \"%1\"";
+        assert_eq!(
+            key_values(&registry, PROG_ID),
+            values([("", "Frost project")])
+        );
+        assert_eq!(
+            key_values(&registry, &format!("{PROG_ID}\\DefaultIcon")),
+            values([("", "C:\\Program Files\\App\\icons\\frost.ico")])
+        );
+        assert_eq!(
+            key_values(&registry, &format!("{PROG_ID}\\shell")),
+            values([("", "open")])
+        );
+        assert_eq!(
+            key_values(&registry, &format!("{PROG_ID}\\shell\\open\\command")),
+            values([("", command)])
+        );
+        assert_eq!(
+            key_values(&registry, ".frost"),
+            values([("", PROG_ID), ("Content Type", "application/x-frost")])
+        );
+        assert_eq!(
+            key_values(&registry, ".frost\\OpenWithProgids"),
+            values([(PROG_ID, "")])
+        );
+
+        // The type without its own icon and description uses the application's
+        assert_eq!(
+            key_values(&registry, "FrostCorp.app.txt"),
+            values([("", "Test App file")])
+        );
+        assert_eq!(
+            key_values(&registry, "FrostCorp.app.txt\\DefaultIcon"),
+            values([("", "C:\\Program Files\\App\\app.exe,0")])
+        );
+        assert_eq!(
+            key_values(&registry, "FrostCorp.app.txt\\shell\\edit\\command"),
+            values([("", command)])
+        );
+
+        assert_eq!(
+            associations,
+            Associations {
+                classes: vec![
+                    PROG_ID.to_string(),
+                    "FrostCorp.app.txt".to_string(),
+                    "frostapp".to_string()
+                ],
+                extensions: vec![
+                    (".frost".to_string(), PROG_ID.to_string()),
+                    (".txt".to_string(), "FrostCorp.app.txt".to_string()),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn only_offers_extensions_claimed_by_others() {
+        let mut registry = registry_with_text_editor();
+
+        register(&mut registry, HIVE, &test_manifest(), "C:\\App").unwrap();
+
+        assert_eq!(
+            key_values(&registry, ".txt"),
+            values([("", "txtfile"), ("Content Type", "text/plain")])
+        );
+        assert_eq!(
+            key_values(&registry, ".txt\\OpenWithProgids"),
+            values([("FrostCorp.app.txt", ""), ("txtfile", "")])
+        );
+    }
+
+    #[test]
+    fn registers_url_schemes() {
+        let mut registry = MemoryRegistry::new();
+
+        register(&mut registry, HIVE, &test_manifest(), "C:\\App").unwrap();
+
+        assert_eq!(
+            key_values(&registry, "frostapp"),
+            values([("", "URL:Test App link"), ("URL Protocol", "")])
+        );
+        assert_eq!(
+            key_values(&registry, "frostapp\\DefaultIcon"),
+            values([("", "C:\\App\\app.exe,0")])
+        );
+        assert_eq!(
+            key_values(&registry, "frostapp\\shell\\open\\command"),
+            values([("", "\"C:\\App\\app.exe\" \"%1\"")])
+        );
+    }
+
+    #[test]
+    fn unregister_leaves_other_applications_alone() {
+        let mut registry = registry_with_text_editor();
+        let associations = register(&mut registry, HIVE, &test_manifest(), "C:\\App").unwrap();
+        // Another application registered for `.frost` files after the installation
+        registry
+            .set_value(
+                HIVE,
+                &class_key(".frost\\OpenWithProgids"),
+                "Other.frost",
+                string(""),
+            )
+            .unwrap();
+
+        unregister(&mut registry, HIVE, &associations).unwrap();
+
+        assert_eq!(
+            registry.keys(HIVE).collect::<Vec<_>>(),
+            [
+                class_key(".frost"),
+                class_key(".frost\\OpenWithProgids"),
+                class_key(".txt"),
+                class_key(".txt\\OpenWithProgids"),
+            ]
+        );
+        assert_eq!(
+            key_values(&registry, ".frost"),
+            values([("Content Type", "application/x-frost")])
+        );
+        assert_eq!(
+            key_values(&registry, ".frost\\OpenWithProgids"),
+            values([("Other.frost", "")])
+        );
+        assert_eq!(
+            key_values(&registry, ".txt"),
+            values([("", "txtfile"), ("Content Type", "text/plain")])
+        );
+        assert_eq!(
+            key_values(&registry, ".txt\\OpenWithProgids"),
+            values([("txtfile", "")])
+        );
+    }
+}
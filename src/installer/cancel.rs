@@ -0,0 +1,153 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// Allows stopping a running installation, e.g. from the UI or a signal handler.
+///
+/// The installation checks the handle between chunks and rolls back what it created so far.
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Cursor, Read, Seek, SeekFrom, Write},
+        sync::atomic::AtomicU64,
+    };
+
+    use tokio::sync::mpsc;
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+    use crate::{
+        config::FilePayload,
+        installer::{
+            InstallError, install_blocking,
+            journal::Journal,
+            log::InstallLog,
+            progress::{Phase, ProgressReporter},
+            services::SystemCommandRunner,
+        },
+        test_util::{TempDir, install_config, manifest},
+    };
+
+    const FILE_SIZE: usize = 64 * 1024;
+    const FILE_COUNT: usize = 32;
+
+    /// A payload that cancels the installation once a few of its files were read
+    struct CancellingReader {
+        archive: Cursor<Vec<u8>>,
+        cancel: CancelHandle,
+        read: Arc<AtomicU64>,
+    }
+
+    impl Read for CancellingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.archive.read(buf)?;
+            let read = self.read.fetch_add(n as u64, Ordering::Relaxed) + n as u64;
+            if read > (4 * FILE_SIZE) as u64 {
+                self.cancel.cancel();
+            }
+            Ok(n)
+        }
+    }
+
+    impl Seek for CancellingReader {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.archive.seek(pos)
+        }
+    }
+
+    fn archive() -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for index in 0..FILE_COUNT {
+            zip.start_file(format!("data/{index}.bin"), options)
+                .unwrap();
+            zip.write_all(&[index as u8; FILE_SIZE]).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn cancelling_during_extraction_rolls_back() {
+        let dir = TempDir::new();
+        let install_path = dir.path().join("app");
+        let cancel = CancelHandle::new();
+        let read = Arc::new(AtomicU64::new(0));
+        let archive = archive();
+        let archive_size = archive.len() as u64;
+        let mut config = install_config(&install_path);
+        config.payloads = vec![FilePayload::Directory {
+            unpacked_size: (FILE_SIZE * FILE_COUNT) as u64,
+            reader: Box::new(CancellingReader {
+                archive: Cursor::new(archive),
+                cancel: cancel.clone(),
+                read: read.clone(),
+            }),
+        }];
+        let (sender, _receiver) = mpsc::channel(1024);
+        let mut progress = ProgressReporter::new(sender, cancel, 0, InstallLog::disabled());
+        let mut journal = Journal::default();
+
+        let result = install_blocking(config, &manifest(), &mut progress, &mut journal);
+        let written = journal
+            .created()
+            .filter(|path| path.extension().is_some_and(|extension| extension == "bin"))
+            .count();
+        journal.rollback(&mut SystemCommandRunner);
+
+        assert!(matches!(result, Err(InstallError::Cancelled)));
+        // The extraction stopped instead of reading the rest of the payload
+        assert!(
+            written > 0 && written < FILE_COUNT,
+            "{written} files written"
+        );
+        assert!(read.load(Ordering::Relaxed) < archive_size);
+        assert!(!install_path.exists());
+    }
+
+    #[test]
+    fn dropping_the_receiver_cancels() {
+        let cancel = CancelHandle::new();
+        let (sender, receiver) = mpsc::channel(1024);
+        let mut progress = ProgressReporter::new(sender, cancel.clone(), 0, InstallLog::disabled());
+        progress.phase(Phase::Extracting);
+        assert!(!cancel.is_cancelled());
+
+        drop(receiver);
+        progress.phase(Phase::Finalizing);
+
+        assert!(cancel.is_cancelled());
+        assert!(matches!(
+            progress.check_cancelled(),
+            Err(InstallError::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn clones_share_the_cancellation() {
+        let cancel = CancelHandle::new();
+        let clone = cancel.clone();
+
+        clone.cancel();
+
+        assert!(cancel.is_cancelled());
+    }
+}
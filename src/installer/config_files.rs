@@ -0,0 +1,305 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::{AppManifest, ConfigFilePolicy},
+    installer::journal::Journal,
+};
+
+/// Decides how configuration files are written when installing over a previous installation.
+///
+/// A configuration file counts as modified if it neither matches the hash recorded when it was installed nor the new version.
+pub(super) struct ConfigFiles {
+    marked: Vec<PathBuf>,
+    policy: ConfigFilePolicy,
+    previous: BTreeMap<PathBuf, [u8; 32]>,
+    /// Hashes of the configuration files shipped with this installation
+    pub shipped: BTreeMap<PathBuf, [u8; 32]>,
+    /// Modified configuration files that weren't simply replaced
+    pub outcomes: Vec<ConfigFileOutcome>,
+}
+
+/// What happened to a configuration file the user modified
+#[derive(Debug, Clone)]
+pub struct ConfigFileOutcome {
+    /// Path of the configuration file, relative to the install directory
+    pub path: PathBuf,
+    pub action: ConfigFileAction,
+}
+
+#[derive(Debug, Clone)]
+pub enum ConfigFileAction {
+    /// The user's copy was kept and the new version written to this path
+    WroteNew(PathBuf),
+    /// The user's copy was replaced
+    Overwritten,
+    /// The user's copy was kept and the new version discarded
+    Skipped,
+    /// The user's copy was saved to this path and replaced
+    BackedUp(PathBuf),
+}
+
+impl ConfigFileAction {
+    /// Where the new version of the configuration file at `relative_path` is written, `None` if it is discarded
+    pub fn destination(&self, root: &Path, relative_path: &Path) -> Option<PathBuf> {
+        match self {
+            ConfigFileAction::WroteNew(new) => Some(root.join(new)),
+            ConfigFileAction::Overwritten | ConfigFileAction::BackedUp(_) => {
+                Some(root.join(relative_path))
+            }
+            ConfigFileAction::Skipped => None,
+        }
+    }
+}
+
+impl Display for ConfigFileOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = self.path.display();
+        match &self.action {
+            ConfigFileAction::WroteNew(new) => write!(
+                f,
+                "{path}: kept your changes, the new version was saved as {}",
+                new.display()
+            ),
+            ConfigFileAction::Overwritten => {
+                write!(f, "{path}: your changes were replaced by the new version")
+            }
+            ConfigFileAction::Skipped => {
+                write!(
+                    f,
+                    "{path}: kept your changes, the new version wasn't installed"
+                )
+            }
+            ConfigFileAction::BackedUp(backup) => write!(
+                f,
+                "{path}: replaced by the new version, your changes were saved as {}",
+                backup.display()
+            ),
+        }
+    }
+}
+
+impl ConfigFiles {
+    pub fn new(manifest: &AppManifest, previous: BTreeMap<PathBuf, [u8; 32]>) -> Self {
+        Self {
+            marked: manifest.config_files.iter().map(PathBuf::from).collect(),
+            policy: manifest.config_file_policy,
+            previous,
+            shipped: BTreeMap::new(),
+            outcomes: Vec::new(),
+        }
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.marked.iter().any(|marked| marked == path)
+    }
+
+    /// Returns where the new version of the configuration file at `relative_path` should be written, or `None` if it should be discarded
+    pub fn destination(
+        &mut self,
+        root: &Path,
+        relative_path: &Path,
+        contents: &[u8],
+        journal: &mut Journal,
+    ) -> io::Result<Option<PathBuf>> {
+        let path = root.join(relative_path);
+        let action = self.conflict(root, relative_path, contents)?;
+        self.shipped
+            .insert(relative_path.to_path_buf(), hash(contents));
+        let Some(action) = action else {
+            return Ok(Some(path));
+        };
+
+        if let ConfigFileAction::BackedUp(backup) = &action {
+            let backup_path = root.join(backup);
+            journal.track_file(&backup_path);
+            fs::copy(&path, backup_path)?;
+        }
+        let destination = action.destination(root, relative_path);
+
+        self.outcomes.push(ConfigFileOutcome {
+            path: relative_path.to_path_buf(),
+            action,
+        });
+        Ok(destination)
+    }
+
+    /// Decides what happens if the user modified the configuration file at `relative_path`, without changing anything.
+    ///
+    /// Returns `None` if the new version can simply be written.
+    pub fn conflict(
+        &self,
+        root: &Path,
+        relative_path: &Path,
+        contents: &[u8],
+    ) -> io::Result<Option<ConfigFileAction>> {
+        let new_hash = hash(contents);
+        let existing_hash = match fs::read(root.join(relative_path)) {
+            Ok(existing) => hash(&existing),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        if existing_hash == new_hash || self.previous.get(relative_path) == Some(&existing_hash) {
+            return Ok(None);
+        }
+
+        Ok(Some(match self.policy {
+            ConfigFilePolicy::WriteNew => {
+                ConfigFileAction::WroteNew(with_suffix(relative_path, "new"))
+            }
+            ConfigFilePolicy::Overwrite => ConfigFileAction::Overwritten,
+            ConfigFilePolicy::Skip => ConfigFileAction::Skipped,
+            ConfigFilePolicy::Backup => {
+                ConfigFileAction::BackedUp(with_suffix(relative_path, "bak"))
+            }
+        }))
+    }
+}
+
+fn hash(contents: &[u8]) -> [u8; 32] {
+    Sha256::digest(contents).into()
+}
+
+/// Appends an extension to the full file name, e.g. `app.toml` becomes `app.toml.new`
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(OsStr::new("."));
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{TempDir, manifest};
+
+    const NEW: &[u8] = b"port = 80\n";
+
+    /// Configuration files with `app.toml` marked, the receipt records the hash of `installed` if given
+    fn config_files(policy: ConfigFilePolicy, installed: Option<&[u8]>) -> ConfigFiles {
+        let manifest = manifest()
+            .config_file("app.toml")
+            .config_file_policy(policy);
+        let previous = installed
+            .map(|contents| (PathBuf::from("app.toml"), hash(contents)))
+            .into_iter()
+            .collect();
+        ConfigFiles::new(&manifest, previous)
+    }
+
+    /// The install directory, with the user's copy of `app.toml`
+    fn root(contents: &[u8]) -> TempDir {
+        let root = TempDir::new();
+        fs::write(root.path().join("app.toml"), contents).unwrap();
+        root
+    }
+
+    fn destination(
+        config_files: &mut ConfigFiles,
+        root: &Path,
+        journal: &mut Journal,
+    ) -> Option<PathBuf> {
+        config_files
+            .destination(root, Path::new("app.toml"), NEW, journal)
+            .unwrap()
+    }
+
+    #[test]
+    fn replaces_unmodified_file() {
+        let root = root(b"port = 8080\n");
+        let mut config_files = config_files(ConfigFilePolicy::Skip, Some(b"port = 8080\n"));
+
+        let destination = destination(&mut config_files, root.path(), &mut Journal::default());
+
+        assert_eq!(destination, Some(root.path().join("app.toml")));
+        assert!(config_files.outcomes.is_empty());
+        assert_eq!(config_files.shipped[Path::new("app.toml")], hash(NEW));
+    }
+
+    #[test]
+    fn writes_new_version_next_to_modified_file() {
+        let root = root(b"port = 8080\n");
+        let mut config_files = config_files(ConfigFilePolicy::WriteNew, Some(b"port = 70\n"));
+
+        let destination = destination(&mut config_files, root.path(), &mut Journal::default());
+
+        assert_eq!(destination, Some(root.path().join("app.toml.new")));
+        assert!(matches!(
+            &config_files.outcomes[0].action,
+            ConfigFileAction::WroteNew(new) if new == Path::new("app.toml.new")
+        ));
+    }
+
+    #[test]
+    fn skips_modified_file() {
+        let root = root(b"port = 8080\n");
+        let mut config_files = config_files(ConfigFilePolicy::Skip, Some(b"port = 70\n"));
+
+        let destination = destination(&mut config_files, root.path(), &mut Journal::default());
+
+        assert_eq!(destination, None);
+        assert!(matches!(
+            config_files.outcomes[0].action,
+            ConfigFileAction::Skipped
+        ));
+        // Recorded anyway, so the next installation recognizes the skipped version
+        assert_eq!(config_files.shipped[Path::new("app.toml")], hash(NEW));
+    }
+
+    #[test]
+    fn backs_up_modified_file() {
+        let root = root(b"port = 8080\n");
+        let mut config_files = config_files(ConfigFilePolicy::Backup, Some(b"port = 70\n"));
+        let mut journal = Journal::default();
+
+        let destination = destination(&mut config_files, root.path(), &mut journal);
+
+        let backup = root.path().join("app.toml.bak");
+        assert_eq!(destination, Some(root.path().join("app.toml")));
+        assert_eq!(fs::read(&backup).unwrap(), b"port = 8080\n");
+        assert!(journal.created().any(|path| path == backup));
+        assert!(matches!(
+            &config_files.outcomes[0].action,
+            ConfigFileAction::BackedUp(path) if path == Path::new("app.toml.bak")
+        ));
+    }
+
+    #[test]
+    fn file_without_receipt_hash_counts_as_modified() {
+        let root = root(b"port = 8080\n");
+        let config_files = config_files(ConfigFilePolicy::Overwrite, None);
+
+        let conflict = config_files
+            .conflict(root.path(), Path::new("app.toml"), NEW)
+            .unwrap();
+
+        assert!(matches!(conflict, Some(ConfigFileAction::Overwritten)));
+        // Unless it already is the new version
+        fs::write(root.path().join("app.toml"), NEW).unwrap();
+        assert!(
+            config_files
+                .conflict(root.path(), Path::new("app.toml"), NEW)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn missing_file_is_written() {
+        let root = TempDir::new();
+        let config_files = config_files(ConfigFilePolicy::Skip, Some(b"port = 70\n"));
+
+        let conflict = config_files
+            .conflict(root.path(), Path::new("app.toml"), NEW)
+            .unwrap();
+
+        assert!(conflict.is_none());
+    }
+}
@@ -0,0 +1,498 @@
+use std::{
+    fmt::Write,
+    fs, io,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use crate::{
+    config::{AppManifest, FileAssociation, InstallConfig, InstallScope},
+    installer::journal::Journal,
+};
+
+mod icon;
+
+use icon::theme_icons;
+
+/// Integrates the installed application into freedesktop environments.
+///
+/// Menu entries, icons and MIME definitions go below `/usr/share` for per-machine installations and below `~/.local/share` otherwise.
+/// Desktop shortcuts are put on the desktop of the user running the installer.
+///
+/// Returns the data directory that was written to, so the caches can be refreshed again after uninstalling.
+/// Staged installations write below their root instead, see [`InstallConfig::staged`].
+pub(super) fn integrate(
+    manifest: &AppManifest,
+    config: &InstallConfig,
+    journal: &mut Journal,
+) -> io::Result<Option<PathBuf>> {
+    let read_installed = |path: &Path| fs::read(config.staged(path)).map(Some);
+    let Some((data_dir, files)) = desktop_files(manifest, config, read_installed)? else {
+        return Ok(None);
+    };
+
+    for file in files {
+        let path = config.staged(&file.path);
+        if let Some(parent) = path.parent() {
+            journal.create_dir_all(parent)?;
+        }
+        journal.track_file(&path);
+        fs::write(&path, &file.contents)?;
+        if file.executable {
+            // File managers only launch desktop files that are executable
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        }
+    }
+
+    // The caches of a staged installation are rebuilt wherever it is deployed
+    if config.root.is_none() {
+        refresh_caches(&data_dir);
+    }
+
+    Ok(Some(data_dir))
+}
+
+/// A file of the desktop integration
+pub(super) struct DesktopFile {
+    pub path: PathBuf,
+    pub contents: Vec<u8>,
+    pub executable: bool,
+    /// Menu entries and desktop shortcuts, rather than icons or MIME definitions
+    pub is_shortcut: bool,
+}
+
+/// The files [`integrate`] writes and the data directory they mostly go to, without writing anything.
+///
+/// Icons of file types are payload files, `read_installed` reads them from the install directory.
+/// If it returns `None` their icons are left out.
+pub(super) fn desktop_files(
+    manifest: &AppManifest,
+    config: &InstallConfig,
+    read_installed: impl Fn(&Path) -> io::Result<Option<Vec<u8>>>,
+) -> io::Result<Option<(PathBuf, Vec<DesktopFile>)>> {
+    let show_in_menu = config.create_start_menu_shortcut;
+    let handles_types = !handled_mime_types(manifest).is_empty();
+    if !show_in_menu && !config.create_desktop_shortcut && !handles_types {
+        return Ok(None);
+    }
+
+    let data_dir = data_dir(config.scope);
+    let entry_name = format!("{}.desktop", manifest.bin_name);
+    let mut files = Vec::new();
+    let mut add = |path: PathBuf, contents: Vec<u8>, executable: bool, is_shortcut: bool| {
+        files.push(DesktopFile {
+            path,
+            contents,
+            executable,
+            is_shortcut,
+        });
+    };
+
+    let icons = manifest
+        .icon
+        .as_deref()
+        .map(theme_icons)
+        .unwrap_or_default();
+    let has_icon = !icons.is_empty();
+    for icon in icons {
+        let path = data_dir
+            .join("icons")
+            .join("hicolor")
+            .join(icon.size_dir)
+            .join("apps")
+            .join(format!("{}.{}", manifest.bin_name, icon.extension));
+        add(path, icon.contents, false, false);
+    }
+
+    for association in &manifest.file_associations {
+        let Some(icon) = &association.icon else {
+            continue;
+        };
+        let Some(contents) = read_installed(&config.install_path.join(icon))? else {
+            continue;
+        };
+        for icon in theme_icons(&contents) {
+            let path = data_dir
+                .join("icons")
+                .join("hicolor")
+                .join(icon.size_dir)
+                .join("mimetypes")
+                .join(format!(
+                    "{}.{}",
+                    file_type_icon_name(manifest, association),
+                    icon.extension
+                ));
+            add(path, icon.contents, false, false);
+        }
+    }
+
+    // The MIME types are associated through the menu entry, so it's written hidden without a start menu shortcut
+    if show_in_menu || handles_types {
+        let entry = desktop_entry(manifest, &config.install_path, has_icon, show_in_menu);
+        let path = data_dir.join("applications").join(&entry_name);
+        add(path, entry.into_bytes(), false, show_in_menu);
+    }
+
+    if config.create_desktop_shortcut
+        && let Some(desktop_dir) = desktop_dir()
+    {
+        let entry = desktop_entry(manifest, &config.install_path, has_icon, true);
+        add(
+            desktop_dir.join(&entry_name),
+            entry.into_bytes(),
+            true,
+            true,
+        );
+    }
+
+    if let Some(package) = mime_package(manifest) {
+        let path = data_dir
+            .join("mime")
+            .join("packages")
+            .join(format!("{}.xml", manifest.bin_name));
+        add(path, package.into_bytes(), false, false);
+    }
+
+    Ok(Some((data_dir, files)))
+}
+
+/// Rebuilds the caches desktop environments use to find menu entries, MIME types and icons.
+///
+/// The tools aren't installed everywhere, so failures are ignored.
+pub(super) fn refresh_caches(data_dir: &Path) {
+    let caches: [(&str, &[&str], PathBuf); 3] = [
+        (
+            "update-desktop-database",
+            &["-q"],
+            data_dir.join("applications"),
+        ),
+        ("update-mime-database", &[], data_dir.join("mime")),
+        (
+            "gtk-update-icon-cache",
+            &["-q", "-f"],
+            data_dir.join("icons").join("hicolor"),
+        ),
+    ];
+
+    // The database tool refuses to run without a packages directory, which uninstalling may have removed.
+    // Its generated files would keep the removed MIME types registered otherwise.
+    let mime_dir = data_dir.join("mime");
+    if mime_dir.is_dir() {
+        let _ = fs::create_dir_all(mime_dir.join("packages"));
+    }
+
+    for (program, args, dir) in caches {
+        if dir.is_dir() {
+            let _ = Command::new(program)
+                .args(args)
+                .arg(dir)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+        }
+    }
+}
+
+/// Base directory for menu entries, icons and MIME types of the given scope
+fn data_dir(scope: InstallScope) -> PathBuf {
+    match scope {
+        InstallScope::Machine => PathBuf::from("/usr/share"),
+        InstallScope::User => std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .unwrap_or_else(|| {
+                std::env::home_dir()
+                    .unwrap_or_default()
+                    .join(".local")
+                    .join("share")
+            }),
+    }
+}
+
+/// The user's desktop directory, which may be localized
+fn desktop_dir() -> Option<PathBuf> {
+    let configured = Command::new("xdg-user-dir")
+        .arg("DESKTOP")
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| PathBuf::from(String::from_utf8_lossy(&output.stdout).trim_end()))
+        .filter(|dir| dir.is_dir());
+
+    configured.or_else(|| {
+        std::env::home_dir()
+            .map(|home| home.join("Desktop"))
+            .filter(|dir| dir.is_dir())
+    })
+}
+
+/// Generates the `.desktop` file that starts the application
+fn desktop_entry(
+    manifest: &AppManifest,
+    install_path: &Path,
+    has_icon: bool,
+    show_in_menu: bool,
+) -> String {
+    let executable = install_path.join(&manifest.bin_name);
+    let mut exec = quote_exec_argument(&executable.to_string_lossy());
+    let mime_types = handled_mime_types(manifest);
+    if !manifest.url_schemes.is_empty() {
+        // Files are passed as `file://` URLs then, a desktop entry can only receive one kind of argument
+        exec.push_str(" %U");
+    } else if !mime_types.is_empty() {
+        exec.push_str(" %F");
+    }
+
+    let mut entry = String::from("[Desktop Entry]\nType=Application\nVersion=1.5\n");
+    let _ = writeln!(entry, "Name={}", escape_value(&manifest.friendly_name));
+    let _ = writeln!(entry, "Exec={}", escape_value(&exec));
+    let _ = writeln!(
+        entry,
+        "Path={}",
+        escape_value(&install_path.to_string_lossy())
+    );
+    if has_icon {
+        let _ = writeln!(entry, "Icon={}", escape_value(&manifest.bin_name));
+    }
+    entry.push_str("Terminal=false\n");
+    if !show_in_menu {
+        entry.push_str("NoDisplay=true\n");
+    }
+    if !mime_types.is_empty() {
+        let mut types = String::new();
+        for mime_type in &mime_types {
+            let _ = write!(types, "{mime_type};");
+        }
+        let _ = writeln!(entry, "MimeType={}", escape_value(&types));
+    }
+    entry
+}
+
+/// MIME types the desktop entry handles: the declared ones, those of the file associations and the URL schemes
+fn handled_mime_types(manifest: &AppManifest) -> Vec<String> {
+    let declared = manifest
+        .mime_types
+        .iter()
+        .map(|mime_type| mime_type.name.clone());
+    let files = manifest
+        .file_associations
+        .iter()
+        .map(FileAssociation::mime_type_name);
+    let schemes = manifest
+        .url_schemes
+        .iter()
+        .map(|scheme| format!("x-scheme-handler/{}", scheme.scheme));
+
+    let mut types = Vec::new();
+    for mime_type in declared.chain(files).chain(schemes) {
+        if !types.contains(&mime_type) {
+            types.push(mime_type);
+        }
+    }
+    types
+}
+
+/// Name of the themed icon of an associated file type
+fn file_type_icon_name(manifest: &AppManifest, association: &FileAssociation) -> String {
+    format!("{}-{}", manifest.bin_name, association.extension)
+}
+
+/// Generates a shared-mime-info package defining the MIME types that have file name patterns
+fn mime_package(manifest: &AppManifest) -> Option<String> {
+    let declared = manifest
+        .mime_types
+        .iter()
+        .filter(|mime_type| !mime_type.patterns.is_empty())
+        .map(|mime_type| {
+            (
+                mime_type.name.clone(),
+                mime_type.description.as_deref(),
+                mime_type.patterns.clone(),
+                None,
+            )
+        });
+    let files = manifest.file_associations.iter().map(|association| {
+        (
+            association.mime_type_name(),
+            association.description.as_deref(),
+            vec![format!("*.{}", association.extension)],
+            association
+                .icon
+                .as_ref()
+                .map(|_| file_type_icon_name(manifest, association)),
+        )
+    });
+    let defined = declared.chain(files).collect::<Vec<_>>();
+    if defined.is_empty() {
+        return None;
+    }
+
+    let mut package = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<mime-info xmlns=\"http://www.freedesktop.org/standards/shared-mime-info\">\n",
+    );
+    for (name, description, patterns, icon) in defined {
+        let _ = writeln!(package, "  <mime-type type=\"{}\">", escape_xml(&name));
+        if let Some(description) = description {
+            let _ = writeln!(
+                package,
+                "    <comment>{}</comment>",
+                escape_xml(description)
+            );
+        }
+        if let Some(icon) = icon {
+            let _ = writeln!(package, "    <icon name=\"{}\"/>", escape_xml(&icon));
+        }
+        for pattern in patterns {
+            let _ = writeln!(package, "    <glob pattern=\"{}\"/>", escape_xml(&pattern));
+        }
+        package.push_str("  </mime-type>\n");
+    }
+    package.push_str("</mime-info>\n");
+    Some(package)
+}
+
+/// Escapes a string value of a desktop entry
+fn escape_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Quotes an argument of the `Exec` key, see the "Exec key" section of the desktop entry specification
+fn quote_exec_argument(arg: &str) -> String {
+    const RESERVED: &[char] = &[
+        ' ', '\t', '\n', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(',
+        ')', '`',
+    ];
+
+    let arg = arg.replace('%', "%%");
+    if !arg.contains(RESERVED) {
+        return arg;
+    }
+
+    let mut quoted = String::from('"');
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{MimeType, UrlScheme},
+        test_util::manifest,
+    };
+
+    #[test]
+    fn generates_plain_entry() {
+        assert_eq!(
+            desktop_entry(&manifest(), Path::new("/opt/test-app"), false, true),
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Version=1.5\n\
+             Name=Test App\n\
+             Exec=/opt/test-app/test-app\n\
+             Path=/opt/test-app\n\
+             Terminal=false\n"
+        );
+    }
+
+    #[test]
+    fn generates_entry_handling_types() {
+        let manifest = manifest()
+            .mime_type(MimeType::new("application/x-frost-project"))
+            .file_association(FileAssociation::new("frost"))
+            .url_scheme(UrlScheme::new("frost"));
+
+        assert_eq!(
+            desktop_entry(&manifest, Path::new("/opt/my apps/100%"), true, false),
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Version=1.5\n\
+             Name=Test App\n\
+             Exec=\"/opt/my apps/100%%/test-app\" %U\n\
+             Path=/opt/my apps/100%\n\
+             Icon=test-app\n\
+             Terminal=false\n\
+             NoDisplay=true\n\
+             MimeType=application/x-frost-project;application/x-frost;x-scheme-handler/frost;\n"
+        );
+    }
+
+    #[test]
+    fn passes_files_without_url_schemes() {
+        let manifest = manifest().file_association(FileAssociation::new("frost"));
+
+        let entry = desktop_entry(&manifest, Path::new("/opt/test-app"), false, true);
+        assert!(entry.contains("Exec=/opt/test-app/test-app %F\n"));
+        assert!(entry.ends_with("MimeType=application/x-frost;\n"));
+    }
+
+    #[test]
+    fn generates_mime_package() {
+        let manifest = manifest()
+            .mime_type(
+                MimeType::new("application/x-frost-project")
+                    .description("Frost <project>")
+                    .pattern("*.frost")
+                    .pattern("*.frostproj"),
+            )
+            .mime_type(MimeType::new("application/x-without-patterns"))
+            .file_association(
+                FileAssociation::new("fsnap")
+                    .description("Frost snapshot")
+                    .icon("icons/snapshot.png"),
+            );
+
+        assert_eq!(
+            mime_package(&manifest).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <mime-info xmlns=\"http://www.freedesktop.org/standards/shared-mime-info\">\n\
+             \x20 <mime-type type=\"application/x-frost-project\">\n\
+             \x20   <comment>Frost &lt;project&gt;</comment>\n\
+             \x20   <glob pattern=\"*.frost\"/>\n\
+             \x20   <glob pattern=\"*.frostproj\"/>\n\
+             \x20 </mime-type>\n\
+             \x20 <mime-type type=\"application/x-fsnap\">\n\
+             \x20   <comment>Frost snapshot</comment>\n\
+             \x20   <icon name=\"test-app-fsnap\"/>\n\
+             \x20   <glob pattern=\"*.fsnap\"/>\n\
+             \x20 </mime-type>\n\
+             </mime-info>\n"
+        );
+    }
+
+    #[test]
+    fn defines_no_package_without_patterns() {
+        let manifest = manifest()
+            .mime_type(MimeType::new("application/x-frost-project"))
+            .url_scheme(UrlScheme::new("frost"));
+
+        assert_eq!(mime_package(&manifest), None);
+    }
+}
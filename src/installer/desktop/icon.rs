@@ -0,0 +1,229 @@
+use std::io::Cursor;
+
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+
+/// Sizes of the hicolor theme's fixed size directories, e.g. `48x48`
+const ICON_SIZES: [u32; 8] = [16, 24, 32, 48, 64, 128, 256, 512];
+
+/// An icon file ready to be put into the hicolor theme
+pub(super) struct ThemeIcon {
+    /// Directory inside the theme, e.g. `48x48` or `scalable`
+    pub size_dir: String,
+    pub extension: &'static str,
+    pub contents: Vec<u8>,
+}
+
+/// Converts the manifest icon into the icon files of the hicolor theme.
+///
+/// PNG icons are scaled down to every standard size up to their own size, they are never scaled up.
+/// SVG icons are installed as the scalable icon. Other formats aren't supported and produce no icons.
+pub(super) fn theme_icons(icon: &[u8]) -> Vec<ThemeIcon> {
+    if is_svg(icon) {
+        return vec![ThemeIcon {
+            size_dir: "scalable".to_string(),
+            extension: "svg",
+            contents: icon.to_vec(),
+        }];
+    }
+
+    let Some(image) = Image::decode_png(icon) else {
+        return Vec::new();
+    };
+    let largest = image.width.max(image.height);
+
+    let mut sizes = ICON_SIZES
+        .into_iter()
+        .filter(|&size| size <= largest)
+        .collect::<Vec<_>>();
+    if sizes.is_empty() {
+        sizes.push(largest);
+    }
+
+    sizes
+        .into_iter()
+        .map(|size| ThemeIcon {
+            size_dir: format!("{size}x{size}"),
+            extension: "png",
+            contents: image.fit_square(size).encode_png(),
+        })
+        .collect()
+}
+
+fn is_svg(data: &[u8]) -> bool {
+    let start = String::from_utf8_lossy(&data[..data.len().min(256)]);
+    let start = start.trim_start();
+    start.starts_with("<svg") || (start.starts_with("<?xml") && start.contains("<svg"))
+}
+
+/// An image with straight RGBA8 pixels
+struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    fn decode_png(data: &[u8]) -> Option<Self> {
+        let mut decoder = Decoder::new(Cursor::new(data));
+        decoder.set_transformations(Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().ok()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).ok()?;
+        let buffer = &buffer[..info.buffer_size()];
+
+        let pixels = match info.color_type {
+            ColorType::Rgba => buffer.to_vec(),
+            ColorType::Rgb => buffer
+                .chunks_exact(3)
+                .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], u8::MAX])
+                .collect(),
+            ColorType::GrayscaleAlpha => buffer
+                .chunks_exact(2)
+                .flat_map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]])
+                .collect(),
+            ColorType::Grayscale => buffer
+                .iter()
+                .flat_map(|&gray| [gray, gray, gray, u8::MAX])
+                .collect(),
+            // Palettes are expanded by the transformations
+            ColorType::Indexed => return None,
+        };
+
+        Some(Self {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+
+    fn encode_png(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut encoder = Encoder::new(&mut data, self.width, self.height);
+        encoder.set_color(ColorType::Rgba);
+        encoder.set_depth(BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .expect("Writing into memory can't fail");
+        writer
+            .write_image_data(&self.pixels)
+            .expect("Pixel data matches the header");
+        writer.finish().expect("Writing into memory can't fail");
+        data
+    }
+
+    /// Scales the image to fit into a `size`x`size` square, centered on a transparent background.
+    ///
+    /// Every target pixel averages the source pixels it covers, weighted by their alpha so transparent pixels don't darken edges.
+    fn fit_square(&self, size: u32) -> Image {
+        let scale = size as f64 / self.width.max(self.height) as f64;
+        let scaled_width = ((self.width as f64 * scale).round() as u32).clamp(1, size);
+        let scaled_height = ((self.height as f64 * scale).round() as u32).clamp(1, size);
+        let offset_x = (size - scaled_width) / 2;
+        let offset_y = (size - scaled_height) / 2;
+
+        let mut pixels = vec![0; (size * size * 4) as usize];
+        for y in 0..scaled_height {
+            let (source_top, source_bottom) = source_span(y, scaled_height, self.height);
+            for x in 0..scaled_width {
+                let (source_left, source_right) = source_span(x, scaled_width, self.width);
+
+                let mut sums = [0u64; 4];
+                let mut count = 0u64;
+                for source_y in source_top..source_bottom {
+                    for source_x in source_left..source_right {
+                        let index = ((source_y * self.width + source_x) * 4) as usize;
+                        let pixel = &self.pixels[index..index + 4];
+                        let alpha = pixel[3] as u64;
+                        for channel in 0..3 {
+                            sums[channel] += pixel[channel] as u64 * alpha;
+                        }
+                        sums[3] += alpha;
+                        count += 1;
+                    }
+                }
+
+                let index = (((y + offset_y) * size + x + offset_x) * 4) as usize;
+                // Fully transparent pixels stay black
+                for channel in 0..3 {
+                    pixels[index + channel] = sums[channel].checked_div(sums[3]).unwrap_or(0) as u8;
+                }
+                pixels[index + 3] = (sums[3] / count) as u8;
+            }
+        }
+
+        Image {
+            width: size,
+            height: size,
+            pixels,
+        }
+    }
+}
+
+/// Range of source pixels covered by target pixel `index`, always at least one pixel wide
+fn source_span(index: u32, target_len: u32, source_len: u32) -> (u32, u32) {
+    let start = (index as u64 * source_len as u64 / target_len as u64) as u32;
+    let end = ((index as u64 + 1) * source_len as u64).div_ceil(target_len as u64) as u32;
+    (start, end.clamp(start + 1, source_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        Image {
+            width,
+            height,
+            pixels: [200, 100, 50, 255].repeat((width * height) as usize),
+        }
+        .encode_png()
+    }
+
+    fn sizes(icons: &[ThemeIcon]) -> Vec<(&str, &str)> {
+        icons
+            .iter()
+            .map(|icon| (icon.size_dir.as_str(), icon.extension))
+            .collect()
+    }
+
+    #[test]
+    fn installs_svg_as_is() {
+        let svg = b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>";
+
+        let icons = theme_icons(svg);
+        assert_eq!(sizes(&icons), [("scalable", "svg")]);
+        assert_eq!(icons[0].contents, svg);
+    }
+
+    #[test]
+    fn scales_png_down_to_standard_sizes() {
+        let icons = theme_icons(&png(40, 20));
+        assert_eq!(
+            sizes(&icons),
+            [("16x16", "png"), ("24x24", "png"), ("32x32", "png")]
+        );
+
+        let image = Image::decode_png(&icons[2].contents).unwrap();
+        assert_eq!((image.width, image.height), (32, 32));
+        // Centered, transparent above and below the scaled image
+        assert_eq!(image.pixels[..4], [0, 0, 0, 0]);
+        let center = ((16 * 32 + 16) * 4) as usize;
+        assert_eq!(image.pixels[center..center + 4], [200, 100, 50, 255]);
+    }
+
+    #[test]
+    fn keeps_size_of_small_png() {
+        let icons = theme_icons(&png(8, 8));
+        assert_eq!(sizes(&icons), [("8x8", "png")]);
+
+        let image = Image::decode_png(&icons[0].contents).unwrap();
+        assert_eq!((image.width, image.height), (8, 8));
+        assert_eq!(image.pixels, [200, 100, 50, 255].repeat(64));
+    }
+
+    #[test]
+    fn ignores_unsupported_formats() {
+        assert!(theme_icons(b"GIF89a").is_empty());
+        assert!(theme_icons(&[]).is_empty());
+    }
+}
@@ -0,0 +1,602 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::InstallScope,
+    installer::{InstallError, private_dir, progress::Phase, progress::ProgressReporter},
+};
+
+mod curl;
+mod http;
+
+/// Attempts per download before giving up, interrupted attempts are resumed
+const MAX_ATTEMPTS: u32 = 4;
+/// Delay before the second attempt, it doubles with every further one
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Redirects followed per attempt
+const MAX_REDIRECTS: u32 = 5;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DownloadError {
+    #[error(
+        "Couldn't connect to {host}, check the internet connection and proxy settings:\n{error}"
+    )]
+    Connect { host: String, error: io::Error },
+    #[error("Server responded with status {0}")]
+    Status(u16),
+    #[error("Unsupported URL {0}")]
+    UnsupportedUrl(String),
+    #[error("Too many redirects")]
+    TooManyRedirects,
+    #[error("Invalid SHA-256 hash {0:?}")]
+    InvalidHash(String),
+    #[error("Downloaded {actual} bytes, but {expected} were expected")]
+    SizeMismatch { expected: u64, actual: u64 },
+    #[error(
+        "The downloaded file doesn't match its SHA-256 hash, it is damaged or was tampered with"
+    )]
+    HashMismatch,
+    #[error("Download was cancelled")]
+    Cancelled,
+    #[error("{0:?} is a link or belongs to another user, it isn't used as download cache")]
+    UnsafeCache(PathBuf),
+    #[error("{0}")]
+    Io(io::Error),
+}
+
+impl DownloadError {
+    /// Whether another attempt may succeed
+    fn is_transient(&self) -> bool {
+        match self {
+            DownloadError::Connect { .. } | DownloadError::Io(_) => true,
+            DownloadError::Status(status) => *status == 408 || *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+/// Outcome of a single request
+pub(super) enum Fetched {
+    Done,
+    /// The resource moved, the URL may use another scheme
+    Redirect(String),
+}
+
+/// Fetches URLs into files, continuing after the data already in them
+pub(super) trait Transport {
+    /// `on_progress` is called with the current size of the file, returning `false` cancels the download
+    fn fetch(
+        &mut self,
+        url: &str,
+        path: &Path,
+        on_progress: &mut dyn FnMut(u64) -> bool,
+    ) -> Result<Fetched, DownloadError>;
+}
+
+/// Downloads `http` URLs directly and hands everything else to curl, which brings TLS support
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct SystemTransport;
+
+impl Transport for SystemTransport {
+    fn fetch(
+        &mut self,
+        url: &str,
+        path: &Path,
+        on_progress: &mut dyn FnMut(u64) -> bool,
+    ) -> Result<Fetched, DownloadError> {
+        if url
+            .get(..7)
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case("http://"))
+        {
+            http::fetch(url, path, on_progress)
+        } else {
+            curl::fetch(url, path, on_progress).map(|()| Fetched::Done)
+        }
+    }
+}
+
+/// Downloads remote payloads into a cache directory, where interrupted downloads are picked up again by later installations
+pub(super) struct Downloader<T> {
+    transport: T,
+    cache_dir: PathBuf,
+    first_retry_delay: Duration,
+    /// Verified archives, removed once the installation succeeded
+    downloaded: Vec<PathBuf>,
+}
+
+impl Downloader<SystemTransport> {
+    pub fn new(scope: InstallScope) -> Self {
+        Self::with_transport(SystemTransport, cache_dir(scope))
+    }
+}
+
+/// Directory downloads of a scope are cached in, so that only the installing user can write to it
+fn cache_dir(scope: InstallScope) -> PathBuf {
+    #[cfg(unix)]
+    let base = match scope {
+        InstallScope::Machine => PathBuf::from("/var/cache"),
+        InstallScope::User => std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .unwrap_or_else(|| std::env::home_dir().unwrap_or_default().join(".cache")),
+    };
+    // The temporary directory is in the profile of the installing user
+    #[cfg(not(unix))]
+    let base = {
+        let _ = scope;
+        std::env::temp_dir()
+    };
+    base.join("frost-wizard").join("downloads")
+}
+
+impl<T: Transport> Downloader<T> {
+    pub fn with_transport(transport: T, cache_dir: PathBuf) -> Self {
+        Self {
+            transport,
+            cache_dir,
+            first_retry_delay: FIRST_RETRY_DELAY,
+            downloaded: Vec::new(),
+        }
+    }
+
+    /// Downloads an archive and verifies its size and hash, the verified archive in the download cache is returned opened.
+    ///
+    /// The downloaded bytes are reported as written, the progress total has to include them.
+    pub fn download(
+        &mut self,
+        url: &str,
+        sha256: &str,
+        size: u64,
+        progress: &mut ProgressReporter,
+    ) -> Result<File, InstallError> {
+        let error = |error| match error {
+            DownloadError::Cancelled => InstallError::Cancelled,
+            error => InstallError::Download {
+                url: url.to_string(),
+                error,
+            },
+        };
+
+        let expected = parse_sha256(sha256)
+            .ok_or_else(|| error(DownloadError::InvalidHash(sha256.to_string())))?;
+        let path = self.cache_dir.join(hex(&expected));
+        private_dir::create_dir(&self.cache_dir).map_err(|err| error(DownloadError::Io(err)))?;
+
+        progress.phase(Phase::Downloading);
+        progress.start_task(url.to_string());
+
+        let mut reported = 0;
+        let mut report = |length: u64, progress: &mut ProgressReporter| {
+            // Bytes downloaded again after a failed resume aren't counted twice
+            let length = length.min(size);
+            if length > reported {
+                progress.written(length - reported);
+                reported = length;
+            }
+            progress.check_cancelled().is_ok()
+        };
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let result = self
+                .fetch_once(url, &path, size, &mut |length| report(length, progress))
+                .and_then(|()| verify(&path, &expected, size));
+            match result {
+                Ok(file) => {
+                    self.downloaded.push(path.clone());
+                    return Ok(file);
+                }
+                Err(DownloadError::HashMismatch) if attempt < MAX_ATTEMPTS => {
+                    progress.log().warn("download", DownloadError::HashMismatch);
+                    // Data from an earlier attempt may have been damaged, so the next one starts over
+                    let _ = fs::remove_file(&path);
+                }
+                Err(err @ DownloadError::HashMismatch)
+                | Err(err @ DownloadError::SizeMismatch { .. }) => {
+                    let _ = fs::remove_file(&path);
+                    return Err(error(err));
+                }
+                Err(err) if err.is_transient() && attempt < MAX_ATTEMPTS => {
+                    let delay = self.first_retry_delay * 2u32.pow(attempt - 1);
+                    progress
+                        .log()
+                        .warn("download", format!("Retrying in {delay:?}:\n{err}"));
+                    wait(delay, progress).map_err(|()| InstallError::Cancelled)?;
+                }
+                Err(err) => return Err(error(err)),
+            }
+        }
+    }
+
+    /// Fetches the rest of the archive, following redirects
+    fn fetch_once(
+        &mut self,
+        url: &str,
+        path: &Path,
+        size: u64,
+        on_progress: &mut dyn FnMut(u64) -> bool,
+    ) -> Result<(), DownloadError> {
+        let length = match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.is_file() && private_dir::is_own(&metadata) => metadata.len(),
+            Ok(_) => return Err(DownloadError::UnsafeCache(path.to_path_buf())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+            Err(err) => return Err(DownloadError::Io(err)),
+        };
+        if !on_progress(length) {
+            return Err(DownloadError::Cancelled);
+        }
+        if length == size {
+            return Ok(());
+        }
+        if length > size {
+            truncate_entry(path)?;
+        }
+
+        let mut url = url.to_string();
+        for _ in 0..=MAX_REDIRECTS {
+            match self.transport.fetch(&url, path, on_progress)? {
+                Fetched::Done => return Ok(()),
+                Fetched::Redirect(location) => url = location,
+            }
+        }
+        Err(DownloadError::TooManyRedirects)
+    }
+
+    /// Removes the downloaded archives, once they aren't needed for resuming anymore
+    pub fn remove_downloads(&mut self) {
+        for path in self.downloaded.drain(..) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Opens a file in the download cache, refusing links and files of other users
+pub(super) fn open_entry(path: &Path, options: &mut OpenOptions) -> Result<File, DownloadError> {
+    private_dir::open_file(path, options).map_err(|err| match err.kind() {
+        io::ErrorKind::PermissionDenied => DownloadError::UnsafeCache(path.to_path_buf()),
+        _ => DownloadError::Io(err),
+    })
+}
+
+/// Empties a file in the download cache, the next attempt starts over
+pub(super) fn truncate_entry(path: &Path) -> Result<(), DownloadError> {
+    open_entry(path, OpenOptions::new().write(true))?
+        .set_len(0)
+        .map_err(DownloadError::Io)
+}
+
+/// Checks size and hash of a downloaded archive and opens it
+fn verify(path: &Path, expected: &[u8; 32], size: u64) -> Result<File, DownloadError> {
+    let mut file = open_entry(path, OpenOptions::new().read(true))?;
+    let actual = file.metadata().map_err(DownloadError::Io)?.len();
+    if actual < size {
+        return Err(DownloadError::Io(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("Download stopped after {actual} of {size} bytes"),
+        )));
+    }
+    if actual > size {
+        return Err(DownloadError::SizeMismatch {
+            expected: size,
+            actual,
+        });
+    }
+
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).map_err(DownloadError::Io)?;
+    if hasher.finalize().as_slice() != expected {
+        return Err(DownloadError::HashMismatch);
+    }
+    file.seek(SeekFrom::Start(0)).map_err(DownloadError::Io)?;
+    Ok(file)
+}
+
+/// Sleeps in small steps, so a cancellation isn't delayed
+fn wait(delay: Duration, progress: &ProgressReporter) -> Result<(), ()> {
+    const STEP: Duration = Duration::from_millis(100);
+    let mut waited = Duration::ZERO;
+    while waited < delay {
+        if progress.check_cancelled().is_err() {
+            return Err(());
+        }
+        thread::sleep(STEP.min(delay - waited));
+        waited += STEP;
+    }
+    Ok(())
+}
+
+fn parse_sha256(hash: &str) -> Option<[u8; 32]> {
+    let hash = hash.trim();
+    if hash.len() != 64 || !hash.is_ascii() {
+        return None;
+    }
+    let mut bytes = [0; 32];
+    for (byte, pair) in bytes.iter_mut().zip(hash.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(bytes)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread::JoinHandle,
+    };
+
+    use super::*;
+    use crate::test_util::TempDir;
+
+    /// Serves one response per connection, `respond` gets the number of the request and its head.
+    ///
+    /// Returns the URL of the server and the requests it received, once all responses were sent.
+    fn serve(
+        responses: usize,
+        mut respond: impl FnMut(usize, &str) -> Vec<u8> + Send + 'static,
+    ) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/payload.zip", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for index in 0..responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request = String::new();
+                while !request.ends_with("\r\n\r\n") {
+                    if reader.read_line(&mut request).unwrap() == 0 {
+                        break;
+                    }
+                }
+                let response = respond(index, &request);
+                // Closing the connection ends the body, HTTP/1.0 has no keep-alive
+                let _ = reader.into_inner().write_all(&response);
+                requests.push(request);
+            }
+            requests
+        });
+        (url, server)
+    }
+
+    fn response(status: &str, headers: &str, body: &[u8]) -> Vec<u8> {
+        let mut response = format!("HTTP/1.0 {status}\r\n{headers}\r\n").into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    fn payload() -> Vec<u8> {
+        (0..100_000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn sha256(data: &[u8]) -> String {
+        hex(&Sha256::digest(data))
+    }
+
+    fn downloader(cache: &TempDir) -> Downloader<SystemTransport> {
+        let mut downloader =
+            Downloader::with_transport(SystemTransport, cache.path().to_path_buf());
+        downloader.first_retry_delay = Duration::ZERO;
+        downloader
+    }
+
+    fn range(request: &str) -> Option<&str> {
+        request
+            .lines()
+            .find_map(|line| line.strip_prefix("Range: "))
+    }
+
+    #[test]
+    fn resumes_interrupted_download() {
+        let data = payload();
+        let served = data.clone();
+        let (url, server) = serve(2, move |index, _| match index {
+            // The connection drops in the middle of the body
+            0 => response(
+                "200 OK",
+                &format!("Content-Length: {}\r\n", served.len()),
+                &served[..40_000],
+            ),
+            _ => response(
+                "206 Partial Content",
+                &format!("Content-Length: {}\r\n", served.len() - 40_000),
+                &served[40_000..],
+            ),
+        });
+        let cache = TempDir::new();
+        let (mut progress, _receiver) = ProgressReporter::for_tests();
+
+        let mut file = downloader(&cache)
+            .download(&url, &sha256(&data), data.len() as u64, &mut progress)
+            .unwrap();
+
+        let mut downloaded = Vec::new();
+        file.read_to_end(&mut downloaded).unwrap();
+        assert!(downloaded == data);
+        let requests = server.join().unwrap();
+        assert_eq!(range(&requests[0]), None);
+        assert_eq!(range(&requests[1]), Some("bytes=40000-"));
+    }
+
+    #[test]
+    fn starts_over_if_server_cant_resume() {
+        let data = payload();
+        let served = data.clone();
+        // The server ignores the range and sends everything again
+        let (url, server) = serve(2, move |index, _| {
+            let body = if index == 0 {
+                &served[..10]
+            } else {
+                &served[..]
+            };
+            response(
+                "200 OK",
+                &format!("Content-Length: {}\r\n", served.len()),
+                body,
+            )
+        });
+        let cache = TempDir::new();
+        let (mut progress, _receiver) = ProgressReporter::for_tests();
+
+        let mut file = downloader(&cache)
+            .download(&url, &sha256(&data), data.len() as u64, &mut progress)
+            .unwrap();
+
+        let mut downloaded = Vec::new();
+        file.read_to_end(&mut downloaded).unwrap();
+        assert!(downloaded == data);
+        assert_eq!(range(&server.join().unwrap()[1]), Some("bytes=10-"));
+    }
+
+    #[test]
+    fn retries_server_errors() {
+        let data = payload();
+        let served = data.clone();
+        let (url, server) = serve(3, move |index, _| match index {
+            0 => response("503 Service Unavailable", "", b""),
+            1 => response("429 Too Many Requests", "", b""),
+            _ => response("200 OK", "", &served),
+        });
+        let cache = TempDir::new();
+        let (mut progress, _receiver) = ProgressReporter::for_tests();
+
+        downloader(&cache)
+            .download(&url, &sha256(&data), data.len() as u64, &mut progress)
+            .unwrap();
+
+        assert_eq!(server.join().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn gives_up_after_last_attempt() {
+        let (url, server) = serve(MAX_ATTEMPTS as usize, |_, _| {
+            response("500 Internal Server Error", "", b"")
+        });
+        let cache = TempDir::new();
+        let (mut progress, _receiver) = ProgressReporter::for_tests();
+
+        let result = downloader(&cache).download(&url, &sha256(b"data"), 4, &mut progress);
+
+        assert!(matches!(
+            result,
+            Err(InstallError::Download {
+                error: DownloadError::Status(500),
+                ..
+            })
+        ));
+        assert_eq!(server.join().unwrap().len(), MAX_ATTEMPTS as usize);
+    }
+
+    #[test]
+    fn doesnt_retry_missing_files() {
+        let (url, server) = serve(1, |_, _| response("404 Not Found", "", b""));
+        let cache = TempDir::new();
+        let (mut progress, _receiver) = ProgressReporter::for_tests();
+
+        let result = downloader(&cache).download(&url, &sha256(b"data"), 4, &mut progress);
+
+        assert!(matches!(
+            result,
+            Err(InstallError::Download {
+                error: DownloadError::Status(404),
+                ..
+            })
+        ));
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn rejects_hash_mismatch() {
+        let data = payload();
+        let mut tampered = data.clone();
+        tampered[1234] ^= 0xFF;
+        let (url, server) = serve(MAX_ATTEMPTS as usize, move |_, _| {
+            response("200 OK", "", &tampered)
+        });
+        let cache = TempDir::new();
+        let (mut progress, _receiver) = ProgressReporter::for_tests();
+
+        let result =
+            downloader(&cache).download(&url, &sha256(&data), data.len() as u64, &mut progress);
+
+        assert!(matches!(
+            result,
+            Err(InstallError::Download {
+                error: DownloadError::HashMismatch,
+                ..
+            })
+        ));
+        // Every attempt starts over, instead of resuming the damaged data
+        let requests = server.join().unwrap();
+        assert!(requests.iter().all(|request| range(request).is_none()));
+        // Nothing damaged is left to resume from
+        assert_eq!(fs::read_dir(cache.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn uses_verified_download_from_cache() {
+        let data = payload();
+        let cache = TempDir::new();
+        fs::write(cache.path().join(sha256(&data)), &data).unwrap();
+        let (mut progress, _receiver) = ProgressReporter::for_tests();
+
+        // Nothing listens on the URL
+        downloader(&cache)
+            .download(
+                "http://127.0.0.1:9/payload.zip",
+                &sha256(&data),
+                data.len() as u64,
+                &mut progress,
+            )
+            .unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_symlinked_cache_entry() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let data = payload();
+        let cache = TempDir::new();
+        let other = TempDir::new();
+        let target = other.path().join("target");
+        fs::write(&target, b"unrelated").unwrap();
+        let entry = cache.path().join(sha256(&data));
+        std::os::unix::fs::symlink(&target, &entry).unwrap();
+        let (mut progress, _receiver) = ProgressReporter::for_tests();
+
+        // Nothing listens on the URL
+        let result = downloader(&cache).download(
+            "http://127.0.0.1:9/payload.zip",
+            &sha256(&data),
+            data.len() as u64,
+            &mut progress,
+        );
+
+        assert!(matches!(
+            result,
+            Err(InstallError::Download {
+                error: DownloadError::UnsafeCache(_),
+                ..
+            })
+        ));
+        assert!(matches!(
+            open_entry(&entry, OpenOptions::new().append(true)),
+            Err(DownloadError::UnsafeCache(_))
+        ));
+        assert_eq!(fs::read(&target).unwrap(), b"unrelated");
+        let mode = fs::metadata(cache.path()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use crate::installer::download::{DownloadError, truncate_entry};
+
+/// Ships with Windows 10 and most Linux distributions
+const CURL: &str = "curl";
+
+/// How often the size of the file is checked while curl is running
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Downloads a URL curl supports, like `https`, into `path`, continuing after the data already in the file.
+///
+/// Proxies are taken from the environment by curl itself.
+pub(super) fn fetch(
+    url: &str,
+    path: &Path,
+    on_progress: &mut dyn FnMut(u64) -> bool,
+) -> Result<(), DownloadError> {
+    let mut child = Command::new(CURL)
+        .args([
+            "--fail",
+            "--location",
+            "--silent",
+            "--show-error",
+            "--connect-timeout",
+            "15",
+            "--speed-limit",
+            "1",
+            "--speed-time",
+            "30",
+            "--continue-at",
+            "-",
+            "--output",
+        ])
+        .arg(path)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| {
+            if error.kind() == io::ErrorKind::NotFound {
+                DownloadError::UnsupportedUrl(format!("{url}, curl is required to download it"))
+            } else {
+                DownloadError::Io(error)
+            }
+        })?;
+
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(DownloadError::Io)? {
+            break status;
+        }
+        let length = fs::metadata(path).map_or(0, |metadata| metadata.len());
+        if !on_progress(length) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(DownloadError::Cancelled);
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    on_progress(fs::metadata(path).map_or(0, |metadata| metadata.len()));
+
+    let mut stderr = String::new();
+    if let Some(mut pipe) = child.stderr.take() {
+        let _ = pipe.read_to_string(&mut stderr);
+    }
+    let message = || io::Error::other(stderr.trim_end().to_string());
+
+    // See the exit codes in the curl manual
+    match status.code() {
+        Some(0) => Ok(()),
+        Some(5 | 6 | 7 | 28) => Err(DownloadError::Connect {
+            host: host(url).to_string(),
+            error: message(),
+        }),
+        Some(22) => Err(DownloadError::Status(
+            http_status(&stderr).unwrap_or_default(),
+        )),
+        Some(33) => {
+            // The server can't resume, the next attempt starts over
+            truncate_entry(path)?;
+            Err(DownloadError::Io(message()))
+        }
+        _ => Err(DownloadError::Io(message())),
+    }
+}
+
+/// The host part of a URL, for error messages
+fn host(url: &str) -> &str {
+    let authority = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = authority.split(['/', '?', '#']).next().unwrap_or_default();
+    authority.rsplit('@').next().unwrap_or_default()
+}
+
+/// Finds the status in messages like `The requested URL returned error: 404`
+fn http_status(stderr: &str) -> Option<u16> {
+    let (_, status) = stderr.rsplit_once("error:")?;
+    status.split_whitespace().next()?.parse().ok()
+}
@@ -0,0 +1,287 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::Path,
+    time::Duration,
+};
+
+use crate::installer::download::{DownloadError, Fetched, open_entry};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+/// A stalled connection is given up on after this long, the download is resumed by the next attempt
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Downloads a plain `http` URL into `path`, continuing after the data already in the file.
+///
+/// HTTP/1.0 is spoken, so responses are never chunked. Proxies are taken from `http_proxy` and `no_proxy`.
+pub(super) fn fetch(
+    url: &str,
+    path: &Path,
+    on_progress: &mut dyn FnMut(u64) -> bool,
+) -> Result<Fetched, DownloadError> {
+    let target =
+        HttpUrl::parse(url).ok_or_else(|| DownloadError::UnsupportedUrl(url.to_string()))?;
+    let proxy = proxy_for(&target.host);
+    let (connect_host, connect_port) = match &proxy {
+        Some(proxy) => (proxy.host.as_str(), proxy.port),
+        None => (target.host.as_str(), target.port),
+    };
+    let mut stream = connect(connect_host, connect_port)?;
+
+    let mut file = open_entry(path, OpenOptions::new().create(true).append(true))?;
+    let offset = file.metadata().map_err(DownloadError::Io)?.len();
+
+    // Proxies get the absolute URL, servers just the path
+    let request_target = if proxy.is_some() { url } else { &target.path };
+    let mut request = format!(
+        "GET {request_target} HTTP/1.0\r\nHost: {}\r\nUser-Agent: frost-wizard\r\nAccept-Encoding: identity\r\n",
+        target.host_header()
+    );
+    if offset > 0 {
+        request.push_str(&format!("Range: bytes={offset}-\r\n"));
+    }
+    request.push_str("\r\n");
+    stream
+        .write_all(request.as_bytes())
+        .map_err(DownloadError::Io)?;
+
+    let mut reader = BufReader::new(stream);
+    let response = Response::read(&mut reader)?;
+    match response.status {
+        200 => {
+            // The server sends everything again, it doesn't support resuming
+            file.set_len(0).map_err(DownloadError::Io)?;
+        }
+        206 => (),
+        // Everything was downloaded already
+        416 if offset > 0 => return Ok(Fetched::Done),
+        301 | 302 | 303 | 307 | 308 => {
+            let location = response
+                .location
+                .ok_or(DownloadError::Status(response.status))?;
+            return Ok(Fetched::Redirect(target.resolve(&location)));
+        }
+        status => return Err(DownloadError::Status(status)),
+    }
+
+    copy_body(&mut reader, &mut file, response.content_length, on_progress)?;
+    Ok(Fetched::Done)
+}
+
+fn copy_body(
+    reader: &mut impl Read,
+    file: &mut File,
+    content_length: Option<u64>,
+    on_progress: &mut dyn FnMut(u64) -> bool,
+) -> Result<(), DownloadError> {
+    let mut buffer = vec![0; BUFFER_SIZE];
+    let mut received = 0;
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(DownloadError::Io(error)),
+        };
+        file.write_all(&buffer[..read]).map_err(DownloadError::Io)?;
+        received += read as u64;
+        let length = file.metadata().map_err(DownloadError::Io)?.len();
+        if !on_progress(length) {
+            return Err(DownloadError::Cancelled);
+        }
+    }
+
+    match content_length {
+        Some(expected) if received < expected => Err(DownloadError::Io(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("Connection closed after {received} of {expected} bytes"),
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn connect(host: &str, port: u16) -> Result<TcpStream, DownloadError> {
+    let offline = |error| DownloadError::Connect {
+        host: host.to_string(),
+        error,
+    };
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "Host has no addresses");
+    for address in (host, port).to_socket_addrs().map_err(offline)? {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => {
+                stream
+                    .set_read_timeout(Some(READ_TIMEOUT))
+                    .map_err(DownloadError::Io)?;
+                return Ok(stream);
+            }
+            Err(error) => last_error = error,
+        }
+    }
+    Err(offline(last_error))
+}
+
+/// The parts of a response head the download needs
+struct Response {
+    status: u16,
+    content_length: Option<u64>,
+    location: Option<String>,
+}
+
+impl Response {
+    fn read(reader: &mut impl BufRead) -> Result<Self, DownloadError> {
+        let invalid = || {
+            DownloadError::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid HTTP response",
+            ))
+        };
+
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(DownloadError::Io)?;
+        let status = line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(invalid)?;
+
+        let mut response = Response {
+            status,
+            content_length: None,
+            location: None,
+        };
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).map_err(DownloadError::Io)? == 0 {
+                return Err(invalid());
+            }
+            let header = line.trim_end();
+            if header.is_empty() {
+                return Ok(response);
+            }
+            let Some((name, value)) = header.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                response.content_length = value.parse().ok();
+            } else if name.eq_ignore_ascii_case("location") {
+                response.location = Some(value.to_string());
+            }
+        }
+    }
+}
+
+/// An `http` URL split into the parts needed for a request
+#[derive(Debug, PartialEq, Eq)]
+struct HttpUrl {
+    host: String,
+    port: u16,
+    /// Path and query, starting with `/`
+    path: String,
+}
+
+impl HttpUrl {
+    fn parse(url: &str) -> Option<Self> {
+        let scheme_end = url.find("://")?;
+        if !url[..scheme_end].eq_ignore_ascii_case("http") {
+            return None;
+        }
+        let rest = &url[scheme_end + 3..];
+        let rest = rest.split('#').next().unwrap_or_default();
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        // Credentials aren't supported
+        let authority = authority.rsplit('@').next().unwrap_or_default();
+        let (host, port) = split_host_port(authority, 80)?;
+        let path = if path.starts_with('?') {
+            format!("/{path}")
+        } else {
+            path.to_string()
+        };
+        Some(Self { host, port, path })
+    }
+
+    fn host_header(&self) -> String {
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        if self.port == 80 {
+            host
+        } else {
+            format!("{host}:{}", self.port)
+        }
+    }
+
+    /// Resolves the target of a redirect, which may be relative to this URL
+    fn resolve(&self, location: &str) -> String {
+        if location.contains("://") {
+            location.to_string()
+        } else if location.starts_with("//") {
+            format!("http:{location}")
+        } else if location.starts_with('/') {
+            format!("http://{}{location}", self.host_header())
+        } else {
+            let path = self.path.split('?').next().unwrap_or_default();
+            let directory = &path[..path.rfind('/').map_or(0, |index| index + 1)];
+            format!("http://{}{directory}{location}", self.host_header())
+        }
+    }
+}
+
+/// Splits `host:port`, also with bracketed IPv6 addresses
+fn split_host_port(authority: &str, default_port: u16) -> Option<(String, u16)> {
+    let (host, port) = if let Some(rest) = authority.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']')?;
+        (host, rest.strip_prefix(':'))
+    } else {
+        match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        }
+    };
+    if host.is_empty() {
+        return None;
+    }
+    let port = match port {
+        Some(port) => port.parse().ok()?,
+        None => default_port,
+    };
+    Some((host.to_string(), port))
+}
+
+/// The proxy from `http_proxy`, unless `no_proxy` excludes the host
+fn proxy_for(host: &str) -> Option<HttpUrl> {
+    let variable = |name: &str| {
+        std::env::var(name)
+            .or_else(|_| std::env::var(name.to_uppercase()))
+            .ok()
+            .filter(|value| !value.is_empty())
+    };
+
+    if let Some(no_proxy) = variable("no_proxy")
+        && no_proxy.split(',').map(str::trim).any(|pattern| {
+            let pattern = pattern.trim_start_matches('.');
+            pattern == "*"
+                || host.eq_ignore_ascii_case(pattern)
+                || host
+                    .to_ascii_lowercase()
+                    .ends_with(&format!(".{}", pattern.to_ascii_lowercase()))
+        })
+    {
+        return None;
+    }
+
+    let proxy = variable("http_proxy")?;
+    // The scheme is optional in proxy variables
+    if proxy.contains("://") {
+        HttpUrl::parse(&proxy)
+    } else {
+        HttpUrl::parse(&format!("http://{proxy}"))
+    }
+}
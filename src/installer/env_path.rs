@@ -0,0 +1,214 @@
+#[cfg(windows)]
+use std::path::Path;
+use std::{io, path::PathBuf};
+
+#[cfg(windows)]
+use crate::registry::{Hive, SystemRegistry};
+use crate::{
+    config::{AppManifest, InstallConfig, InstallScope},
+    installer::journal::Journal,
+};
+
+#[cfg(any(windows, test))]
+mod path_value;
+
+#[cfg(windows)]
+pub(super) use path_value::add_path_entry;
+#[cfg(windows)]
+use path_value::remove_path_entry;
+
+/// Makes `bin_name` available on `PATH`.
+///
+/// On Unix a symlink to the binary is placed in `/usr/local/bin` or `~/.local/bin`, which is recorded by the journal.
+/// Only a symlink into the install directory, left by an earlier installation, is replaced.
+/// On Windows the install directory is appended to the system or user `Path` value, unless the installation is staged.
+///
+/// Returns the directory that was added to `Path`, if one was, so it can be removed again when uninstalling.
+pub(super) fn add_to_path(
+    manifest: &AppManifest,
+    config: &InstallConfig,
+    journal: &mut Journal,
+) -> io::Result<Option<PathBuf>> {
+    #[cfg(unix)]
+    {
+        // The link points to the real location, also when it is staged
+        let link = config.staged(&path_link(manifest, config));
+        let target = config.install_path.join(&manifest.bin_name);
+
+        if let Some(link_dir) = link.parent() {
+            journal.create_dir_all(link_dir)?;
+        }
+        if existing_link(&link, &config.install_path)? {
+            std::fs::remove_file(&link)?;
+        } else {
+            journal.track_file(&link);
+        }
+        std::os::unix::fs::symlink(target, link)?;
+        Ok(None)
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = journal;
+        if config.root.is_some() {
+            // `Path` is part of the registry, which staged installations leave alone
+            return Ok(None);
+        }
+        let hive = Hive::for_scope(config.scope);
+        let dir = config.install_path.to_string_lossy();
+        if !add_path_entry(&mut SystemRegistry, hive, &dir)? {
+            // Already on Path, so it must not be removed when uninstalling either
+            return Ok(None);
+        }
+        crate::windows::broadcast_environment_change();
+        Ok(Some(config.install_path.clone()))
+    }
+}
+
+/// The symlink to `bin_name` [`add_to_path`] creates, in `/usr/local/bin` or `~/.local/bin`
+#[cfg(unix)]
+pub(super) fn path_link(manifest: &AppManifest, config: &InstallConfig) -> PathBuf {
+    let link_dir = match config.scope {
+        InstallScope::Machine => PathBuf::from("/usr/local/bin"),
+        InstallScope::User => std::env::home_dir()
+            .unwrap_or_default()
+            .join(".local")
+            .join("bin"),
+    };
+    link_dir.join(&manifest.bin_name)
+}
+
+/// Whether there is a link at `path` that [`add_to_path`] replaces, which is one into `install_path`.
+///
+/// Anything else at the path belongs to someone else, e.g. another version installed elsewhere, and is an error.
+#[cfg(unix)]
+pub(super) fn existing_link(
+    link: &std::path::Path,
+    install_path: &std::path::Path,
+) -> io::Result<bool> {
+    let already_exists =
+        |message: String| Err(io::Error::new(io::ErrorKind::AlreadyExists, message));
+    match std::fs::symlink_metadata(link) {
+        Ok(metadata) if metadata.is_symlink() => {
+            let target = std::fs::read_link(link)?;
+            // Relative targets are resolved against the directory of the link
+            let resolved = link
+                .parent()
+                .map_or(target.clone(), |dir| dir.join(&target));
+            if resolved.starts_with(install_path) {
+                Ok(true)
+            } else {
+                already_exists(format!(
+                    "{} already exists and points to {}",
+                    link.display(),
+                    target.display()
+                ))
+            }
+        }
+        Ok(_) => already_exists(format!("{} already exists", link.display())),
+        Err(_) => Ok(false),
+    }
+}
+
+/// Removes a directory [`add_to_path`] added to the `Path` value
+#[cfg(windows)]
+pub(super) fn remove_from_path(scope: InstallScope, dir: &Path) -> io::Result<()> {
+    if remove_path_entry(
+        &mut SystemRegistry,
+        Hive::for_scope(scope),
+        &dir.to_string_lossy(),
+    )? {
+        crate::windows::broadcast_environment_change();
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{fs, os::unix::fs::symlink};
+
+    use super::*;
+    use crate::test_util::{TempDir, install_config, manifest};
+
+    /// A machine-wide configuration staged below `root`, so the link ends up in `root/usr/local/bin`
+    fn staged_config(root: &std::path::Path) -> InstallConfig {
+        let mut config = install_config(std::path::Path::new("/opt/test-app"));
+        config.scope = InstallScope::Machine;
+        config.root = Some(root.to_path_buf());
+        config
+    }
+
+    #[test]
+    fn links_binary() {
+        let dir = TempDir::new();
+        let config = staged_config(dir.path());
+        let mut journal = Journal::default();
+
+        add_to_path(&manifest(), &config, &mut journal).unwrap();
+
+        let link = dir.path().join("usr/local/bin/test-app");
+        assert_eq!(
+            fs::read_link(&link).unwrap(),
+            std::path::Path::new("/opt/test-app/test-app")
+        );
+        assert!(journal.created().any(|created| created == link));
+    }
+
+    #[test]
+    fn replaces_own_link() {
+        let dir = TempDir::new();
+        let config = staged_config(dir.path());
+        let link = dir.path().join("usr/local/bin/test-app");
+        fs::create_dir_all(link.parent().unwrap()).unwrap();
+        symlink("/opt/test-app/old-name", &link).unwrap();
+
+        add_to_path(&manifest(), &config, &mut Journal::default()).unwrap();
+
+        assert_eq!(
+            fs::read_link(&link).unwrap(),
+            std::path::Path::new("/opt/test-app/test-app")
+        );
+    }
+
+    #[test]
+    fn keeps_links_of_others() {
+        let dir = TempDir::new();
+        let config = staged_config(dir.path());
+        let link = dir.path().join("usr/local/bin/test-app");
+        fs::create_dir_all(link.parent().unwrap()).unwrap();
+        symlink("/opt/other-app/test-app", &link).unwrap();
+
+        let error = add_to_path(&manifest(), &config, &mut Journal::default()).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(
+            fs::read_link(&link).unwrap(),
+            std::path::Path::new("/opt/other-app/test-app")
+        );
+    }
+
+    #[test]
+    fn keeps_files_of_others() {
+        let dir = TempDir::new();
+        let file = dir.path().join("test-app");
+        fs::write(&file, "script").unwrap();
+
+        assert!(existing_link(&file, std::path::Path::new("/opt/test-app")).is_err());
+        assert!(
+            !existing_link(
+                &dir.path().join("missing"),
+                std::path::Path::new("/opt/test-app")
+            )
+            .unwrap()
+        );
+        // Relative links are resolved against their directory
+        symlink("../opt/test-app/test-app", dir.path().join("relative")).unwrap();
+        assert!(
+            existing_link(
+                &dir.path().join("relative"),
+                &dir.path().join("../opt/test-app")
+            )
+            .unwrap()
+        );
+    }
+}
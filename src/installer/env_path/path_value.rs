@@ -0,0 +1,298 @@
+use std::io;
+
+use crate::registry::{Hive, RegistryBackend, RegistryValue};
+
+/// Name of the value holding the environment variable
+const PATH_VALUE: &str = "Path";
+/// Separator between the entries of the Windows `Path` variable
+const SEPARATOR: char = ';';
+
+/// Key holding the environment variables of a hive
+fn environment_key(hive: Hive) -> &'static str {
+    match hive {
+        Hive::LocalMachine => "SYSTEM\\CurrentControlSet\\Control\\Session Manager\\Environment",
+        Hive::CurrentUser => "Environment",
+    }
+}
+
+fn path_value(registry: &impl RegistryBackend, hive: Hive) -> io::Result<String> {
+    match registry.get_value(hive, environment_key(hive), PATH_VALUE)? {
+        Some(RegistryValue::String(value) | RegistryValue::ExpandString(value)) => Ok(value),
+        Some(RegistryValue::U32(_)) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The Path value isn't a string",
+        )),
+        None => Ok(String::new()),
+    }
+}
+
+/// Appends `dir` to the `Path` value of a hive, unless it is already on it.
+///
+/// Returns whether the value was changed.
+pub(crate) fn add_path_entry(
+    registry: &mut impl RegistryBackend,
+    hive: Hive,
+    dir: &str,
+) -> io::Result<bool> {
+    let Some(updated) = with_entry(&path_value(registry, hive)?, dir) else {
+        return Ok(false);
+    };
+    // Expandable, so entries like `%SystemRoot%` keep working
+    registry.set_value(
+        hive,
+        environment_key(hive),
+        PATH_VALUE,
+        RegistryValue::ExpandString(updated),
+    )?;
+    Ok(true)
+}
+
+/// Removes `dir` from the `Path` value of a hive.
+///
+/// Returns whether the value was changed.
+pub(super) fn remove_path_entry(
+    registry: &mut impl RegistryBackend,
+    hive: Hive,
+    dir: &str,
+) -> io::Result<bool> {
+    let Some(updated) = without_entry(&path_value(registry, hive)?, dir) else {
+        return Ok(false);
+    };
+    registry.set_value(
+        hive,
+        environment_key(hive),
+        PATH_VALUE,
+        RegistryValue::ExpandString(updated),
+    )?;
+    Ok(true)
+}
+
+/// Appends `entry` to a `Path` value, unless it is already part of it.
+///
+/// Returns `None` if nothing has to change.
+fn with_entry(value: &str, entry: &str) -> Option<String> {
+    if entries(value).any(|existing| same_entry(existing, entry)) {
+        return None;
+    }
+
+    let mut updated = value.trim_end_matches(SEPARATOR).to_string();
+    if !updated.is_empty() {
+        updated.push(SEPARATOR);
+    }
+    updated.push_str(entry);
+    Some(updated)
+}
+
+/// Removes every occurrence of `entry` from a `Path` value, keeping the order of the other entries.
+///
+/// Returns `None` if nothing has to change.
+fn without_entry(value: &str, entry: &str) -> Option<String> {
+    if !entries(value).any(|existing| same_entry(existing, entry)) {
+        return None;
+    }
+
+    let remaining = entries(value)
+        .filter(|existing| !same_entry(existing, entry))
+        .collect::<Vec<_>>();
+    Some(remaining.join(&SEPARATOR.to_string()))
+}
+
+fn entries(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(SEPARATOR)
+        .filter(|entry| !entry.trim().is_empty())
+}
+
+/// Whether two `Path` entries name the same directory.
+///
+/// Variables are expanded, and quotes, trailing backslashes and case are ignored, like Windows does when resolving commands.
+fn same_entry(a: &str, b: &str) -> bool {
+    let normalize = |entry: &str| {
+        let entry = expand_variables(entry, |name| std::env::var(name).ok());
+        let entry = entry.trim().trim_matches('"');
+        entry
+            .trim_end_matches(['/', '\\'])
+            .replace('/', "\\")
+            .to_lowercase()
+    };
+    normalize(a) == normalize(b)
+}
+
+/// Replaces `%VARIABLE%` references like Windows does when reading an expandable value, unknown variables are kept as they are
+fn expand_variables(value: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('%') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('%') else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        match Some(&after[..end])
+            .filter(|name| !name.is_empty())
+            .and_then(&lookup)
+        {
+            Some(variable) => {
+                expanded.push_str(&variable);
+                rest = &after[end + 1..];
+            }
+            // The closing `%` may start another reference
+            None => {
+                expanded.push('%');
+                rest = after;
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::MemoryRegistry;
+
+    const USER_KEY: &str = "Environment";
+
+    fn registry_with_path(value: RegistryValue) -> MemoryRegistry {
+        let mut registry = MemoryRegistry::new();
+        registry
+            .set_value(Hive::CurrentUser, USER_KEY, PATH_VALUE, value)
+            .unwrap();
+        registry
+    }
+
+    fn path(registry: &MemoryRegistry) -> Option<RegistryValue> {
+        registry
+            .get_value(Hive::CurrentUser, USER_KEY, PATH_VALUE)
+            .unwrap()
+    }
+
+    #[test]
+    fn adds_entry() {
+        let mut registry = registry_with_path(RegistryValue::ExpandString(
+            "C:\\Windows;C:\\Tools;".to_string(),
+        ));
+
+        assert!(
+            add_path_entry(&mut registry, Hive::CurrentUser, "C:\\Program Files\\App").unwrap()
+        );
+        assert_eq!(
+            path(&registry),
+            Some(RegistryValue::ExpandString(
+                "C:\\Windows;C:\\Tools;C:\\Program Files\\App".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn creates_missing_value() {
+        let mut registry = MemoryRegistry::new();
+
+        assert!(add_path_entry(&mut registry, Hive::LocalMachine, "C:\\App").unwrap());
+        assert_eq!(
+            registry
+                .get_value(
+                    Hive::LocalMachine,
+                    environment_key(Hive::LocalMachine),
+                    PATH_VALUE
+                )
+                .unwrap(),
+            Some(RegistryValue::ExpandString("C:\\App".to_string()))
+        );
+        assert!(registry.values(Hive::CurrentUser, USER_KEY).is_none());
+    }
+
+    #[test]
+    fn doesnt_add_entry_twice() {
+        let value =
+            RegistryValue::ExpandString("C:\\Windows;\"c:/program files/app/\"".to_string());
+        let mut registry = registry_with_path(value.clone());
+
+        assert!(
+            !add_path_entry(&mut registry, Hive::CurrentUser, "C:\\Program Files\\App").unwrap()
+        );
+        assert_eq!(path(&registry), Some(value));
+    }
+
+    #[test]
+    fn keeps_variables_expandable() {
+        // A plain string value is turned into an expandable one, the references in it stay as they are
+        let mut registry = registry_with_path(RegistryValue::String(
+            "%SystemRoot%\\system32;%USERPROFILE%\\bin".to_string(),
+        ));
+
+        assert!(add_path_entry(&mut registry, Hive::CurrentUser, "%LOCALAPPDATA%\\App").unwrap());
+        assert_eq!(
+            path(&registry),
+            Some(RegistryValue::ExpandString(
+                "%SystemRoot%\\system32;%USERPROFILE%\\bin;%LOCALAPPDATA%\\App".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn compares_expanded_entries() {
+        let home_variable = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+        let home = std::env::var(home_variable).unwrap();
+        let value = RegistryValue::ExpandString(format!("%{home_variable}%\\bin"));
+        let mut registry = registry_with_path(value.clone());
+
+        assert!(
+            !add_path_entry(&mut registry, Hive::CurrentUser, &format!("{home}\\bin")).unwrap()
+        );
+        assert_eq!(path(&registry), Some(value));
+        assert!(
+            remove_path_entry(&mut registry, Hive::CurrentUser, &format!("{home}\\bin")).unwrap()
+        );
+    }
+
+    #[test]
+    fn expands_variables() {
+        let lookup = |name: &str| match name {
+            "SystemRoot" => Some("C:\\Windows".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+
+        assert_eq!(
+            expand_variables("%SystemRoot%\\system32", lookup),
+            "C:\\Windows\\system32"
+        );
+        assert_eq!(expand_variables("a%EMPTY%b", lookup), "ab");
+        assert_eq!(expand_variables("%UNKNOWN%\\bin", lookup), "%UNKNOWN%\\bin");
+        assert_eq!(
+            expand_variables("100%%SystemRoot%", lookup),
+            "100%C:\\Windows"
+        );
+        assert_eq!(expand_variables("50% off", lookup), "50% off");
+    }
+
+    #[test]
+    fn rejects_values_that_arent_strings() {
+        let mut registry = registry_with_path(RegistryValue::U32(1));
+
+        assert!(add_path_entry(&mut registry, Hive::CurrentUser, "C:\\App").is_err());
+        assert_eq!(path(&registry), Some(RegistryValue::U32(1)));
+    }
+
+    #[test]
+    fn removes_every_occurrence_on_uninstall() {
+        let mut registry = registry_with_path(RegistryValue::ExpandString(
+            "C:\\App;%SystemRoot%;c:\\app\\;C:\\Tools".to_string(),
+        ));
+
+        assert!(remove_path_entry(&mut registry, Hive::CurrentUser, "C:\\App").unwrap());
+        assert_eq!(
+            path(&registry),
+            Some(RegistryValue::ExpandString(
+                "%SystemRoot%;C:\\Tools".to_string()
+            ))
+        );
+
+        assert!(!remove_path_entry(&mut registry, Hive::CurrentUser, "C:\\App").unwrap());
+        assert!(
+            !remove_path_entry(&mut MemoryRegistry::new(), Hive::CurrentUser, "C:\\App").unwrap()
+        );
+    }
+}
@@ -0,0 +1,1051 @@
+use std::{
+    borrow::Cow,
+    fs,
+    io::{self, Read, Seek, SeekFrom, Write},
+    num::NonZero,
+    path::{Component, Path, PathBuf},
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread,
+    time::SystemTime,
+};
+
+use time::OffsetDateTime;
+use zip::{ZipArchive, read::ZipFile};
+
+use crate::{
+    config::{DirTrait, FilePayload},
+    installer::{
+        InstallError,
+        config_files::{ConfigFileAction, ConfigFiles},
+        download::{Downloader, Transport},
+        journal::Journal,
+        plan::{FileAction, InstallPlan, PlannedDownload, PlannedFile},
+        progress::ProgressReporter,
+        template::Templates,
+    },
+};
+
+type Archive = ZipArchive<SharedReader>;
+
+/// A payload reader shared by the threads extracting an archive, each clone has its own position.
+///
+/// Reading is serialized, decompressing and writing the files still happens in parallel.
+#[derive(Clone)]
+pub(super) struct SharedReader {
+    reader: Arc<Mutex<Box<dyn DirTrait + Send + Sync>>>,
+    position: u64,
+}
+
+impl SharedReader {
+    fn new(reader: Box<dyn DirTrait + Send + Sync>) -> Self {
+        Self {
+            reader: Arc::new(Mutex::new(reader)),
+            position: 0,
+        }
+    }
+
+    fn reader(&self) -> MutexGuard<'_, Box<dyn DirTrait + Send + Sync>> {
+        self.reader.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Read for SharedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = {
+            let mut reader = self.reader();
+            reader.seek(SeekFrom::Start(self.position))?;
+            reader.read(buf)?
+        };
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for SharedReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(pos) => pos,
+            SeekFrom::Current(offset) => self
+                .position
+                .checked_add_signed(offset)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?,
+            // Only the underlying reader knows where the end is
+            SeekFrom::End(_) => self.reader().seek(pos)?,
+        };
+        Ok(self.position)
+    }
+}
+
+/// A payload that is ready to be written, with zip archives already opened.
+pub(super) enum OpenedPayload {
+    File {
+        name: Cow<'static, str>,
+        contents: Cow<'static, [u8]>,
+    },
+    Archive(Archive),
+}
+
+impl OpenedPayload {
+    /// Opens the payload, unless its condition isn't met on this system. Remote payloads are downloaded first.
+    pub fn open(
+        payload: FilePayload,
+        downloader: &mut Downloader<impl Transport>,
+        progress: &mut ProgressReporter,
+    ) -> Result<Option<Self>, InstallError> {
+        match payload {
+            FilePayload::File { name, contents } => {
+                Ok(Some(OpenedPayload::File { name, contents }))
+            }
+            FilePayload::Directory { reader, .. } => Self::open_archive(reader).map(Some),
+            FilePayload::Remote { url, sha256, size } => {
+                let archive = downloader.download(&url, &sha256, size, progress)?;
+                Self::open_archive(Box::new(archive)).map(Some)
+            }
+            FilePayload::Conditional { condition, payload } => {
+                if condition.is_met() {
+                    Self::open(*payload, downloader, progress)
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    fn open_archive(reader: Box<dyn DirTrait + Send + Sync>) -> Result<Self, InstallError> {
+        ZipArchive::new(SharedReader::new(reader))
+            .map(OpenedPayload::Archive)
+            .map_err(InstallError::ZipError)
+    }
+
+    /// Number of files and symlinks the payload contains
+    pub fn file_count(&self) -> u64 {
+        match self {
+            OpenedPayload::File { .. } => 1,
+            OpenedPayload::Archive(zip) => {
+                zip.file_names().filter(|name| !name.ends_with('/')).count() as u64
+            }
+        }
+    }
+
+    pub fn install(
+        self,
+        root: &Path,
+        templates: &Templates,
+        config_files: &mut ConfigFiles,
+        progress: &mut ProgressReporter,
+        journal: &mut Journal,
+    ) -> Result<(), InstallError> {
+        match self {
+            OpenedPayload::File { name, contents } => {
+                progress.check_cancelled()?;
+                let size = contents.len() as u64;
+                let (relative_path, contents) = file_target(root, &name, contents, templates)?;
+
+                progress.start_file(&relative_path);
+                if let Some(parent) = root.join(&relative_path).parent() {
+                    journal
+                        .create_dir_all(parent)
+                        .map_err(InstallError::WritePayload)?;
+                }
+                if let Some(path) =
+                    destination(root, &relative_path, &contents, config_files, journal)?
+                {
+                    journal.track_file(&path);
+                    remove_symlink(&path).map_err(InstallError::WritePayload)?;
+                    fs::write(path, &contents).map_err(InstallError::WritePayload)?;
+                }
+                progress.written(size);
+                progress.file_done();
+                Ok(())
+            }
+            OpenedPayload::Archive(mut zip) => {
+                let extraction = Extraction {
+                    root,
+                    templates,
+                    config_files: Mutex::new(config_files),
+                    progress: Mutex::new(progress),
+                    journal: Mutex::new(journal),
+                    failed: AtomicBool::new(false),
+                    error: Mutex::new(None),
+                };
+                extract_directory(&mut zip, &extraction)
+            }
+        }
+    }
+}
+
+/// State shared by the threads extracting an archive
+struct Extraction<'a> {
+    root: &'a Path,
+    templates: &'a Templates,
+    config_files: Mutex<&'a mut ConfigFiles>,
+    progress: Mutex<&'a mut ProgressReporter>,
+    journal: Mutex<&'a mut Journal>,
+    /// Set once an entry failed, so the other threads stop early
+    failed: AtomicBool,
+    /// The first error that occurred, which is the one reported
+    error: Mutex<Option<InstallError>>,
+}
+
+impl<'a> Extraction<'a> {
+    fn config_files(&self) -> MutexGuard<'_, &'a mut ConfigFiles> {
+        self.config_files
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn progress(&self) -> MutexGuard<'_, &'a mut ProgressReporter> {
+        self.progress.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn journal(&self) -> MutexGuard<'_, &'a mut Journal> {
+        self.journal.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns an error if the extraction should stop, because it was cancelled or another thread failed
+    fn check(&self) -> Result<(), InstallError> {
+        if self.failed.load(Ordering::Relaxed) {
+            return Err(InstallError::Cancelled);
+        }
+        self.progress().check_cancelled()
+    }
+
+    /// Counts written bytes and checks whether the extraction should stop, taking the lock only once
+    fn written(&self, bytes: u64) -> Result<(), InstallError> {
+        if self.failed.load(Ordering::Relaxed) {
+            return Err(InstallError::Cancelled);
+        }
+        let mut progress = self.progress();
+        progress.check_cancelled()?;
+        progress.written(bytes);
+        Ok(())
+    }
+
+    fn fail(&self, error: InstallError) {
+        let mut first = self.error.lock().unwrap_or_else(PoisonError::into_inner);
+        if first.is_none() {
+            *first = Some(error);
+        }
+        self.failed.store(true, Ordering::Relaxed);
+    }
+
+    fn result(&self) -> Result<(), InstallError> {
+        match self
+            .error
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+        {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// An archive entry that gets written to the install directory
+struct ArchiveEntry {
+    index: usize,
+    relative_path: PathBuf,
+    mode: Option<u32>,
+    modified: Option<SystemTime>,
+}
+
+/// Unpacks a zip packed directory into the install directory.
+///
+/// Directories are created first, then files are extracted on as many threads as there are cores, each with its own position in the payload.
+/// Symlinks are created last, as on Windows the kind of link depends on its target.
+/// Unix modes, symlinks, directory entries and modification times stored in the archive are restored.
+fn extract_directory(zip: &mut Archive, extraction: &Extraction) -> Result<(), InstallError> {
+    // Attributes of directories are applied last, as writing their contents would change them again
+    let mut directories = Vec::new();
+    let mut files = Vec::new();
+    let mut symlinks = Vec::new();
+
+    for index in 0..zip.len() {
+        extraction.check()?;
+
+        // The raw entry only gives access to the metadata, without setting up decompression
+        let entry = zip.by_index_raw(index).map_err(InstallError::ZipError)?;
+        let relative_path = entry_path(&entry, extraction.root)?;
+        let path = extraction.root.join(&relative_path);
+        let mode = entry.unix_mode();
+        let modified = entry.last_modified().and_then(to_system_time);
+
+        if entry.is_dir() {
+            extraction
+                .journal()
+                .create_dir_all(&path)
+                .map_err(InstallError::WritePayload)?;
+            directories.push(DirectoryAttributes {
+                path,
+                mode,
+                modified,
+            });
+            continue;
+        }
+
+        // The parents of symlinks are created once they are extracted, as an earlier symlink may take their place
+        let entry_kind = if entry.is_symlink() {
+            &mut symlinks
+        } else {
+            if let Some(parent) = path.parent() {
+                extraction
+                    .journal()
+                    .create_dir_all(parent)
+                    .map_err(InstallError::WritePayload)?;
+            }
+            &mut files
+        };
+        entry_kind.push(ArchiveEntry {
+            index,
+            relative_path,
+            mode,
+            modified,
+        });
+    }
+
+    let threads = thread::available_parallelism()
+        .map_or(1, NonZero::get)
+        .min(files.len());
+    // Clones share the parsed central directory and the payload reader
+    let mut archives = (1..threads).map(|_| zip.clone()).collect::<Vec<_>>();
+
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for archive in &mut archives {
+            scope.spawn(|| extract_files(archive, &files, &next, extraction));
+        }
+        extract_files(zip, &files, &next, extraction);
+    });
+    extraction.result()?;
+
+    for entry in symlinks {
+        extraction.check()?;
+        extract_symlink(zip, &entry, extraction)?;
+    }
+
+    for directory in directories.into_iter().rev() {
+        directory.apply().map_err(InstallError::WritePayload)?;
+    }
+
+    Ok(())
+}
+
+/// Takes files from the shared list until all are extracted or extraction stops
+fn extract_files(
+    zip: &mut Archive,
+    files: &[ArchiveEntry],
+    next: &AtomicUsize,
+    extraction: &Extraction,
+) {
+    while let Some(entry) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+        if let Err(err) = extraction
+            .check()
+            .and_then(|()| extract_file(zip, entry, extraction))
+        {
+            extraction.fail(err);
+            return;
+        }
+    }
+}
+
+/// Writes a regular file.
+///
+/// Templates and configuration files are read into memory, as they have to be processed before they are written.
+fn extract_file(
+    zip: &mut Archive,
+    entry: &ArchiveEntry,
+    extraction: &Extraction,
+) -> Result<(), InstallError> {
+    let mut reader = zip.by_index(entry.index).map_err(InstallError::ZipError)?;
+
+    let target = EntryTarget::new(
+        &entry.relative_path,
+        extraction.templates,
+        &extraction.config_files(),
+    );
+    let relative_path = &target.relative_path;
+
+    extraction.progress().start_file(relative_path);
+
+    let contents = if target.is_read {
+        let (contents, read) = target.read(&mut reader, extraction.templates)?;
+        extraction.written(read)?;
+        Some(contents)
+    } else {
+        None
+    };
+
+    let path = match &contents {
+        Some(contents) => {
+            let mut config_files = extraction.config_files();
+            let destination = destination(
+                extraction.root,
+                relative_path,
+                contents,
+                &mut config_files,
+                &mut extraction.journal(),
+            )?;
+            match destination {
+                Some(path) => path,
+                None => {
+                    extraction.progress().file_done();
+                    return Ok(());
+                }
+            }
+        }
+        None => extraction.root.join(relative_path),
+    };
+
+    extraction.journal().track_file(&path);
+    remove_symlink(&path).map_err(InstallError::WritePayload)?;
+    let mut file = fs::File::create(&path).map_err(InstallError::WritePayload)?;
+
+    match &contents {
+        Some(contents) => file
+            .write_all(contents)
+            .map_err(InstallError::WritePayload)?,
+        None => copy_contents(&mut reader, &mut file, extraction)?,
+    }
+
+    if let Some(modified) = entry.modified {
+        file.set_modified(modified)
+            .map_err(InstallError::WritePayload)?;
+    }
+    drop(file);
+
+    set_mode(&path, entry.mode).map_err(InstallError::WritePayload)?;
+    extraction.progress().file_done();
+    Ok(())
+}
+
+fn extract_symlink(
+    zip: &mut Archive,
+    entry: &ArchiveEntry,
+    extraction: &Extraction,
+) -> Result<(), InstallError> {
+    let mut reader = zip.by_index(entry.index).map_err(InstallError::ZipError)?;
+    let path = extraction.root.join(&entry.relative_path);
+
+    // An earlier entry of the archive may have turned a parent into a symlink
+    if symlink_on_path(extraction.root, entry.relative_path.parent()) {
+        return Err(InstallError::UnsafePayloadPath(reader.name().to_string()));
+    }
+    if let Some(parent) = path.parent() {
+        extraction
+            .journal()
+            .create_dir_all(parent)
+            .map_err(InstallError::WritePayload)?;
+    }
+
+    extraction.progress().start_file(&entry.relative_path);
+    extraction.journal().track_file(&path);
+
+    let mut target = String::new();
+    reader
+        .read_to_string(&mut target)
+        .map_err(InstallError::WritePayload)?;
+    if !symlink_stays_within_root(extraction.root, &path, Path::new(&target)) {
+        return Err(InstallError::UnsafeSymlink {
+            link: reader.name().to_string(),
+            target,
+        });
+    }
+    create_symlink(Path::new(&target), &path).map_err(InstallError::WritePayload)?;
+    extraction.progress().file_done();
+    Ok(())
+}
+
+/// Adds the files a payload would write to the plan, or its download for remote payloads, without writing anything.
+///
+/// Paths and contents are decided like extracting does, templates and configuration files are read to know their final size
+/// and whether the user modified them. Symlink targets are only checked when extracting, as they are resolved against
+/// what the archive created before them.
+pub(super) fn plan_payload(
+    payload: FilePayload,
+    root: &Path,
+    templates: &Templates,
+    config_files: &ConfigFiles,
+    plan: &mut InstallPlan,
+) -> Result<(), InstallError> {
+    match payload {
+        FilePayload::File { name, contents } => {
+            let (relative_path, contents) = file_target(root, &name, contents, templates)?;
+            plan_file(root, &relative_path, &contents, config_files, plan)
+        }
+        FilePayload::Directory { reader, .. } => {
+            let mut zip = ZipArchive::new(reader).map_err(InstallError::ZipError)?;
+            for index in 0..zip.len() {
+                let mut entry = zip.by_index(index).map_err(InstallError::ZipError)?;
+                let relative_path = entry_path(&entry, root)?;
+                if entry.is_dir() {
+                    continue;
+                }
+
+                let target = EntryTarget::new(&relative_path, templates, config_files);
+                if target.is_read {
+                    let (contents, _) = target.read(&mut entry, templates)?;
+                    plan_file(root, &target.relative_path, &contents, config_files, plan)?;
+                } else {
+                    let path = root.join(&target.relative_path);
+                    let action = FileAction::for_path(&path);
+                    plan.files.push(PlannedFile {
+                        path,
+                        size: Some(entry.size()),
+                        action,
+                    });
+                }
+            }
+            Ok(())
+        }
+        FilePayload::Remote { url, sha256, size } => {
+            plan.downloads.push(PlannedDownload {
+                url: url.into_owned(),
+                sha256: sha256.into_owned(),
+                size,
+            });
+            Ok(())
+        }
+        FilePayload::Conditional { condition, payload } => {
+            if condition.is_met() {
+                plan_payload(*payload, root, templates, config_files, plan)
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Plans a file whose contents are known, configuration files the user modified are decided by [`ConfigFiles::conflict`] like when installing
+fn plan_file(
+    root: &Path,
+    relative_path: &Path,
+    contents: &[u8],
+    config_files: &ConfigFiles,
+    plan: &mut InstallPlan,
+) -> Result<(), InstallError> {
+    let path = root.join(relative_path);
+    let size = Some(contents.len() as u64);
+    let conflict = if config_files.contains(relative_path) {
+        config_files
+            .conflict(root, relative_path, contents)
+            .map_err(InstallError::WritePayload)?
+    } else {
+        None
+    };
+
+    let mut add = |path: PathBuf, size, action| {
+        plan.files.push(PlannedFile { path, size, action });
+    };
+    let Some(conflict) = conflict else {
+        let action = FileAction::for_path(&path);
+        add(path, size, action);
+        return Ok(());
+    };
+    if let ConfigFileAction::BackedUp(backup) = &conflict {
+        let backup_size = fs::metadata(&path).ok().map(|metadata| metadata.len());
+        add(root.join(backup), backup_size, FileAction::Backup);
+    }
+    match conflict.destination(root, relative_path) {
+        Some(destination) if destination == path => add(path, size, FileAction::Overwrite),
+        Some(destination) => {
+            let action = FileAction::for_path(&destination);
+            add(path, None, FileAction::Keep);
+            add(destination, size, action);
+        }
+        None => add(path, None, FileAction::Keep),
+    }
+    Ok(())
+}
+
+/// Returns the path a file should be written to, or `None` if a modified configuration file should be kept as it is
+fn destination(
+    root: &Path,
+    relative_path: &Path,
+    contents: &[u8],
+    config_files: &mut ConfigFiles,
+    journal: &mut Journal,
+) -> Result<Option<PathBuf>, InstallError> {
+    if config_files.contains(relative_path) {
+        config_files
+            .destination(root, relative_path, contents, journal)
+            .map_err(InstallError::WritePayload)
+    } else {
+        Ok(Some(root.join(relative_path)))
+    }
+}
+
+fn copy_contents(
+    reader: &mut impl Read,
+    file: &mut fs::File,
+    extraction: &Extraction,
+) -> Result<(), InstallError> {
+    let mut buf = [0; 8192];
+
+    loop {
+        let n = reader.read(&mut buf).map_err(InstallError::WritePayload)?;
+        if n == 0 {
+            return Ok(());
+        }
+
+        file.write_all(&buf[..n])
+            .map_err(InstallError::WritePayload)?;
+
+        extraction.written(n as u64)?;
+    }
+}
+
+/// Where a single file payload is written below `root` and its contents, templates are rendered to their target.
+///
+/// Paths that would leave `root`, also through symlinks already there, are refused.
+fn file_target<'a>(
+    root: &Path,
+    name: &str,
+    contents: Cow<'a, [u8]>,
+    templates: &Templates,
+) -> Result<(PathBuf, Cow<'a, [u8]>), InstallError> {
+    let unsafe_path = || InstallError::UnsafePayloadPath(name.to_string());
+    let relative_path = contained_path(Path::new(name)).ok_or_else(unsafe_path)?;
+    let (relative_path, contents) = match templates.target(&relative_path) {
+        Some(target) => (target, Cow::Owned(templates.render(&contents))),
+        None => (relative_path, contents),
+    };
+    if symlink_on_path(root, relative_path.parent()) {
+        return Err(unsafe_path());
+    }
+    Ok((relative_path, contents))
+}
+
+/// Path of an archive entry relative to `root`, refusing entries that would be written outside of it, also through symlinks already there
+fn entry_path<R: Read>(entry: &ZipFile<'_, R>, root: &Path) -> Result<PathBuf, InstallError> {
+    let unsafe_path = || InstallError::UnsafePayloadPath(entry.name().to_string());
+    let relative_path = entry
+        .enclosed_name()
+        .and_then(|name| contained_path(&name))
+        .ok_or_else(unsafe_path)?;
+    // Directories get their attributes set, so they must not be symlinks themselves either
+    let checked = if entry.is_dir() {
+        Some(relative_path.as_path())
+    } else {
+        relative_path.parent()
+    };
+    if symlink_on_path(root, checked) {
+        return Err(unsafe_path());
+    }
+    Ok(relative_path)
+}
+
+/// Where a file of an archive is written and how
+struct EntryTarget {
+    /// The target of templates, else the path in the archive
+    relative_path: PathBuf,
+    is_template: bool,
+    /// Templates and configuration files are read into memory, to render them or compare them with the installed version
+    is_read: bool,
+}
+
+impl EntryTarget {
+    fn new(relative_path: &Path, templates: &Templates, config_files: &ConfigFiles) -> Self {
+        let template_target = templates.target(relative_path);
+        let is_template = template_target.is_some();
+        let relative_path = template_target.unwrap_or_else(|| relative_path.to_path_buf());
+        Self {
+            is_read: is_template || config_files.contains(&relative_path),
+            relative_path,
+            is_template,
+        }
+    }
+
+    /// Reads the contents and renders templates, also returns the number of bytes read
+    fn read(
+        &self,
+        reader: &mut impl Read,
+        templates: &Templates,
+    ) -> Result<(Vec<u8>, u64), InstallError> {
+        let mut contents = Vec::new();
+        reader
+            .read_to_end(&mut contents)
+            .map_err(InstallError::WritePayload)?;
+        let read = contents.len() as u64;
+        if self.is_template {
+            contents = templates.render(&contents);
+        }
+        Ok((contents, read))
+    }
+}
+
+/// Returns the path if it only consists of plain components, so joining it onto the install directory can't leave it.
+fn contained_path(path: &Path) -> Option<PathBuf> {
+    let mut contained = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => contained.push(part),
+            Component::CurDir => (),
+            Component::Prefix(_) | Component::RootDir | Component::ParentDir => return None,
+        }
+    }
+    (!contained.as_os_str().is_empty()).then_some(contained)
+}
+
+/// Returns whether `relative_path` or one of its parents is a symlink below `root`.
+///
+/// Writing through such a symlink could end up outside the install directory, e.g. if an earlier entry or payload created it.
+fn symlink_on_path(root: &Path, relative_path: Option<&Path>) -> bool {
+    let mut path = root.to_path_buf();
+    relative_path
+        .into_iter()
+        .flat_map(Path::components)
+        .any(|component| {
+            path.push(component);
+            fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.is_symlink())
+        })
+}
+
+/// Checks whether a symlink at `link` pointing to `target` resolves to a location inside `root`.
+///
+/// The target is resolved against what is already extracted, so it can't leave the install directory through symlinks created before it.
+/// A `..` following a part that doesn't exist yet is refused, as a later entry could still turn that part into a symlink.
+fn symlink_stays_within_root(root: &Path, link: &Path, target: &Path) -> bool {
+    let (Ok(root), Some(Ok(mut resolved))) =
+        (root.canonicalize(), link.parent().map(Path::canonicalize))
+    else {
+        return false;
+    };
+
+    let mut exists = true;
+    for component in target.components() {
+        match component {
+            Component::Normal(part) => {
+                resolved.push(part);
+                if !exists {
+                    continue;
+                }
+                match fs::symlink_metadata(&resolved) {
+                    // Dangling symlinks can't be resolved, so they are refused
+                    Ok(metadata) if metadata.is_symlink() => match resolved.canonicalize() {
+                        Ok(canonical) => resolved = canonical,
+                        Err(_) => return false,
+                    },
+                    Ok(_) => (),
+                    Err(_) => exists = false,
+                }
+            }
+            Component::CurDir => (),
+            Component::ParentDir => {
+                if !exists || !resolved.pop() {
+                    return false;
+                }
+            }
+            Component::Prefix(_) | Component::RootDir => return false,
+        }
+    }
+    resolved.starts_with(&root)
+}
+
+struct DirectoryAttributes {
+    path: PathBuf,
+    mode: Option<u32>,
+    modified: Option<SystemTime>,
+}
+
+impl DirectoryAttributes {
+    fn apply(self) -> io::Result<()> {
+        // Windows can't open directories as files without extra flags, so their times are left alone there
+        #[cfg(unix)]
+        if let Some(modified) = self.modified {
+            fs::File::open(&self.path)?.set_modified(modified)?;
+        }
+
+        set_mode(&self.path, self.mode)
+    }
+}
+
+fn to_system_time(datetime: zip::DateTime) -> Option<SystemTime> {
+    OffsetDateTime::try_from(datetime)
+        .ok()
+        .map(SystemTime::from)
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: Option<u32>) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if let Some(mode) = mode {
+        fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o7777))?;
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn set_mode(_path: &Path, _mode: Option<u32>) -> io::Result<()> {
+    Ok(())
+}
+
+/// Removes a symlink a previous installation left where a file is written now, as the file would be written through it
+fn remove_symlink(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_symlink()) {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    // A previous installation might have left a file or link at this location
+    if fs::symlink_metadata(link).is_ok() {
+        fs::remove_file(link)?;
+    }
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(target, link)
+    }
+
+    #[cfg(windows)]
+    {
+        let resolved = link.parent().map(|parent| parent.join(target));
+        if resolved.is_some_and(|resolved| resolved.is_dir()) {
+            std::os::windows::fs::symlink_dir(target, link)
+        } else {
+            std::os::windows::fs::symlink_file(target, link)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, io::Cursor};
+
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+    use crate::test_util::{TempDir, install_config, manifest};
+
+    enum Entry {
+        Directory(&'static str),
+        File(&'static str, &'static str),
+        Symlink(&'static str, &'static str),
+    }
+
+    fn archive(entries: &[Entry]) -> OpenedPayload {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default();
+        for entry in entries {
+            match entry {
+                Entry::Directory(name) => writer.add_directory(*name, options).unwrap(),
+                Entry::File(name, contents) => {
+                    writer.start_file(*name, options).unwrap();
+                    writer.write_all(contents.as_bytes()).unwrap();
+                }
+                Entry::Symlink(name, target) => {
+                    writer.add_symlink(*name, *target, options).unwrap()
+                }
+            }
+        }
+        OpenedPayload::open_archive(Box::new(writer.finish().unwrap())).unwrap()
+    }
+
+    fn install(root: &Path, payload: OpenedPayload) -> Result<(), InstallError> {
+        let manifest = manifest();
+        let templates = Templates::new(&manifest, &install_config(root));
+        let mut config_files = ConfigFiles::new(&manifest, BTreeMap::new());
+        let (mut progress, _receiver) = ProgressReporter::for_tests();
+        payload.install(
+            root,
+            &templates,
+            &mut config_files,
+            &mut progress,
+            &mut Journal::default(),
+        )
+    }
+
+    #[test]
+    fn extracts_files_directories_and_symlinks() {
+        let dir = TempDir::new();
+        let root = dir.path().join("app");
+        fs::create_dir(&root).unwrap();
+
+        install(
+            &root,
+            archive(&[
+                Entry::Directory("share/"),
+                Entry::File("bin/app", "binary"),
+                Entry::Symlink("bin/current", "app"),
+                Entry::Symlink("data", "share/../bin"),
+            ]),
+        )
+        .unwrap();
+
+        assert!(root.join("share").is_dir());
+        assert_eq!(fs::read_to_string(root.join("bin/app")).unwrap(), "binary");
+        assert_eq!(
+            fs::read_link(root.join("bin/current")).unwrap(),
+            Path::new("app")
+        );
+        assert_eq!(fs::read_to_string(root.join("data/app")).unwrap(), "binary");
+    }
+
+    #[test]
+    fn refuses_symlinks_leaving_the_root() {
+        for target in ["..", "../app/../..", "/etc", "bin/../../outside"] {
+            let dir = TempDir::new();
+            let root = dir.path().join("app");
+            fs::create_dir(&root).unwrap();
+
+            let result = install(
+                &root,
+                archive(&[Entry::Directory("bin/"), Entry::Symlink("link", target)]),
+            );
+
+            assert!(
+                matches!(result, Err(InstallError::UnsafeSymlink { .. })),
+                "{target}"
+            );
+            assert!(fs::symlink_metadata(root.join("link")).is_err(), "{target}");
+        }
+    }
+
+    #[test]
+    fn refuses_symlinks_chained_through_earlier_symlinks() {
+        let dir = TempDir::new();
+        let root = dir.path().join("app");
+        fs::create_dir_all(root.join("sub")).unwrap();
+
+        // `up/..` looks like it stays inside, but `up` resolves to the root already
+        let result = install(
+            &root,
+            archive(&[
+                Entry::Symlink("sub/up", ".."),
+                Entry::Symlink("sub/escape", "up/.."),
+            ]),
+        );
+        assert!(matches!(result, Err(InstallError::UnsafeSymlink { .. })));
+        assert!(fs::symlink_metadata(root.join("sub/escape")).is_err());
+    }
+
+    #[test]
+    fn refuses_symlinks_below_earlier_symlinks() {
+        let dir = TempDir::new();
+        let root = dir.path().join("app");
+        fs::create_dir(&root).unwrap();
+
+        // `a/b` lies in the root itself, so `../..` would point to the parent of the root's parent
+        let result = install(
+            &root,
+            archive(&[Entry::Symlink("a", "."), Entry::Symlink("a/b", "../..")]),
+        );
+        assert!(matches!(result, Err(InstallError::UnsafePayloadPath(_))));
+        assert!(fs::symlink_metadata(root.join("b")).is_err());
+    }
+
+    #[test]
+    fn refuses_parent_directories_after_missing_parts() {
+        let dir = TempDir::new();
+        let root = dir.path().join("app");
+        fs::create_dir(&root).unwrap();
+
+        // `later` could still become a symlink to anywhere inside the root
+        let result = install(&root, archive(&[Entry::Symlink("link", "later/../x")]));
+        assert!(matches!(result, Err(InstallError::UnsafeSymlink { .. })));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn refuses_files_written_through_symlinks() {
+        let dir = TempDir::new();
+        let root = dir.path().join("app");
+        let outside = dir.path().join("outside");
+        fs::create_dir(&root).unwrap();
+        fs::create_dir(&outside).unwrap();
+
+        install(
+            &root,
+            archive(&[Entry::Directory("lib/"), Entry::Symlink("libs", "lib")]),
+        )
+        .unwrap();
+        let result = install(&root, archive(&[Entry::File("libs/file", "contents")]));
+        assert!(matches!(result, Err(InstallError::UnsafePayloadPath(_))));
+        assert!(!root.join("lib/file").exists());
+
+        // Left behind by something else, pointing outside of the root
+        std::os::unix::fs::symlink(&outside, root.join("escape")).unwrap();
+        for payload in [
+            archive(&[Entry::File("escape/file", "contents")]),
+            archive(&[Entry::Directory("escape/")]),
+            OpenedPayload::File {
+                name: "escape/file".into(),
+                contents: b"contents".as_slice().into(),
+            },
+        ] {
+            let result = install(&root, payload);
+            assert!(matches!(result, Err(InstallError::UnsafePayloadPath(_))));
+        }
+        assert_eq!(fs::read_dir(&outside).unwrap().count(), 0);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn replaces_symlinks_instead_of_writing_through_them() {
+        let dir = TempDir::new();
+        let root = dir.path().join("app");
+        let outside = dir.path().join("outside");
+        fs::create_dir(&root).unwrap();
+        fs::write(&outside, "untouched").unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("file")).unwrap();
+
+        install(&root, archive(&[Entry::File("file", "contents")])).unwrap();
+
+        assert!(
+            !fs::symlink_metadata(root.join("file"))
+                .unwrap()
+                .is_symlink()
+        );
+        assert_eq!(fs::read_to_string(root.join("file")).unwrap(), "contents");
+        assert_eq!(fs::read_to_string(&outside).unwrap(), "untouched");
+    }
+
+    #[test]
+    fn extracts_from_readers_that_cant_be_cloned() {
+        let dir = TempDir::new();
+        let root = dir.path().join("app");
+        let mut writer = ZipWriter::new(fs::File::create(dir.path().join("payload.zip")).unwrap());
+        for index in 0..32 {
+            writer
+                .start_file(format!("file{index}"), SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(&[index as u8; 10_000]).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let reader = fs::File::open(dir.path().join("payload.zip")).unwrap();
+        install(
+            &root,
+            OpenedPayload::open_archive(Box::new(reader)).unwrap(),
+        )
+        .unwrap();
+
+        for index in 0..32 {
+            let contents = fs::read(root.join(format!("file{index}"))).unwrap();
+            assert_eq!(contents, [index as u8; 10_000]);
+        }
+    }
+
+    #[test]
+    fn shared_readers_keep_their_own_position() {
+        let mut first = SharedReader::new(Box::new(Cursor::new(b"0123456789".to_vec())));
+        let mut second = first.clone();
+        let mut buf = [0; 3];
+
+        first.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"012");
+        assert_eq!(second.seek(SeekFrom::End(-4)).unwrap(), 6);
+        second.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"678");
+        first.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"345");
+        assert_eq!(first.seek(SeekFrom::Current(-5)).unwrap(), 1);
+        assert!(first.seek(SeekFrom::Current(-2)).is_err());
+    }
+}
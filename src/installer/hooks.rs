@@ -0,0 +1,254 @@
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::{HookFailurePolicy, HookStage, InstallHook},
+    installer::{
+        InstallError,
+        progress::{Phase, ProgressReporter},
+    },
+};
+
+/// How often a running command is checked for completion, timeouts and cancellation
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long the output is waited for once a command stopped, as processes it started might keep the pipes open
+const OUTPUT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Runs all hooks of the given stage in the order they were declared
+pub(super) fn run_hooks(
+    hooks: &[InstallHook],
+    stage: HookStage,
+    install_path: &Path,
+    progress: &mut ProgressReporter,
+) -> Result<(), InstallError> {
+    let hooks = hooks
+        .iter()
+        .filter(|hook| hook.stage == stage)
+        .collect::<Vec<_>>();
+    if hooks.is_empty() {
+        return Ok(());
+    }
+
+    progress.phase(Phase::RunningHooks);
+
+    for hook in hooks {
+        progress.check_cancelled()?;
+        progress.start_task(hook.description.clone().unwrap_or_else(|| hook.to_string()));
+
+        match run_hook(hook, install_path, progress) {
+            Ok(()) => (),
+            Err(InstallError::Cancelled) => return Err(InstallError::Cancelled),
+            Err(err) if hook.on_failure == HookFailurePolicy::Continue => {
+                progress
+                    .log()
+                    .warn("task", format!("Failure is ignored:\n{err}"));
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
+fn run_hook(
+    hook: &InstallHook,
+    install_path: &Path,
+    progress: &mut ProgressReporter,
+) -> Result<(), InstallError> {
+    let hook_error = |error| InstallError::HookError {
+        command: hook.to_string(),
+        error,
+    };
+
+    let working_dir = match &hook.working_dir {
+        Some(working_dir) => install_path.join(working_dir),
+        // Pre-install hooks may run before the install directory exists
+        None if install_path.is_dir() => install_path.to_path_buf(),
+        None => std::env::current_dir().map_err(hook_error)?,
+    };
+
+    let mut child = Command::new(resolve_program(&hook.program, install_path))
+        .args(&hook.args)
+        .current_dir(working_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(hook_error)?;
+
+    // The output is read on separate threads, so a chatty command can't block on a full pipe
+    let stdout = collect_output(child.stdout.take());
+    let stderr = collect_output(child.stderr.take());
+
+    let timeout = hook.timeout_secs.map(Duration::from_secs);
+    let status = wait(&mut child, timeout, progress).map_err(hook_error)?;
+
+    let deadline = Instant::now() + OUTPUT_TIMEOUT;
+    let stdout = join_output(stdout, deadline);
+    let stderr = join_output(stderr, deadline);
+    log_output(progress, "stdout", stdout.as_deref());
+    log_output(progress, "stderr", stderr.as_deref());
+
+    match status {
+        WaitResult::Exited(status) if status.success() => Ok(()),
+        WaitResult::Exited(status) => Err(InstallError::HookFailed {
+            command: hook.to_string(),
+            code: status.code(),
+            output: stdout.unwrap_or_default() + &stderr.unwrap_or_default(),
+        }),
+        WaitResult::TimedOut => Err(InstallError::HookTimeout {
+            command: hook.to_string(),
+            timeout_secs: hook.timeout_secs.unwrap_or_default(),
+        }),
+        WaitResult::Cancelled => Err(InstallError::Cancelled),
+    }
+}
+
+enum WaitResult {
+    Exited(ExitStatus),
+    TimedOut,
+    Cancelled,
+}
+
+fn wait(
+    child: &mut Child,
+    timeout: Option<Duration>,
+    progress: &ProgressReporter,
+) -> std::io::Result<WaitResult> {
+    let started = Instant::now();
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(WaitResult::Exited(status));
+        }
+
+        let result = if progress.check_cancelled().is_err() {
+            WaitResult::Cancelled
+        } else if timeout.is_some_and(|timeout| started.elapsed() >= timeout) {
+            WaitResult::TimedOut
+        } else {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+
+        child.kill()?;
+        child.wait()?;
+        return Ok(result);
+    }
+}
+
+fn collect_output(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut output);
+        }
+        String::from_utf8_lossy(&output).into_owned()
+    })
+}
+
+/// Returns the output once the pipe is closed, `None` if it is still open at the deadline
+fn join_output(output: JoinHandle<String>, deadline: Instant) -> Option<String> {
+    while !output.is_finished() && Instant::now() < deadline {
+        thread::sleep(POLL_INTERVAL);
+    }
+    if output.is_finished() {
+        output.join().ok()
+    } else {
+        None
+    }
+}
+
+fn log_output(progress: &mut ProgressReporter, pipe: &str, output: Option<&str>) {
+    match output.map(str::trim_end) {
+        Some("") => (),
+        Some(output) => progress.log().info(pipe, output),
+        None => progress.log().warn(
+            pipe,
+            "Not captured, a process started by the task keeps it open",
+        ),
+    }
+}
+
+fn resolve_program(program: &str, install_path: &Path) -> PathBuf {
+    let program = Path::new(program);
+    if program.is_relative() {
+        let installed = install_path.join(program);
+        if installed.is_file() {
+            return installed;
+        }
+    }
+    program.to_path_buf()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::{
+        installer::{CancelHandle, log::InstallLog},
+        test_util::{TempDir, install_config, manifest},
+    };
+
+    /// Runs a shell script as post-install hook and returns the result with the written log
+    fn run_script(script: &str) -> (Result<(), InstallError>, String) {
+        let dir = TempDir::new();
+        let log_file = dir.path().join("install.log");
+        let mut config = install_config(dir.path());
+        config.log_file = Some(log_file.clone());
+        let (sender, _receiver) = mpsc::channel(1024);
+        let mut progress = ProgressReporter::new(
+            sender,
+            CancelHandle::new(),
+            0,
+            InstallLog::create(&config, &manifest()),
+        );
+
+        let hook = InstallHook::post_install("sh").args(["-c", script]);
+        let result = run_hooks(&[hook], HookStage::PostInstall, dir.path(), &mut progress);
+        (result, fs::read_to_string(log_file).unwrap())
+    }
+
+    #[test]
+    fn logs_output_of_successful_tasks() {
+        let (result, log) = run_script("echo first; echo second; echo problem >&2");
+
+        result.unwrap();
+        assert!(log.contains("INFO  stdout   first\n    second\n"), "{log}");
+        assert!(log.contains("INFO  stderr   problem\n"), "{log}");
+    }
+
+    #[test]
+    fn logs_and_reports_output_of_failed_tasks() {
+        let (result, log) = run_script("echo out; echo err >&2; exit 3");
+
+        match result {
+            Err(InstallError::HookFailed { code, output, .. }) => {
+                assert_eq!(code, Some(3));
+                assert_eq!(output, "out\nerr\n");
+            }
+            other => panic!("unexpected result {other:?}"),
+        }
+        assert!(log.contains("INFO  stdout   out\n"), "{log}");
+        assert!(log.contains("INFO  stderr   err\n"), "{log}");
+    }
+
+    #[test]
+    fn does_not_wait_for_processes_keeping_the_output_open() {
+        let started = Instant::now();
+        let (result, log) = run_script("sleep 30 &");
+
+        result.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(20));
+        assert!(log.contains("WARN  stdout   Not captured"), "{log}");
+    }
+}
@@ -0,0 +1,187 @@
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::{io, thread, time::Duration};
+
+#[cfg(unix)]
+use crate::installer::private_dir;
+use crate::{
+    config::{AppManifest, SecondInstancePolicy},
+    installer::{CancelHandle, InstallError, uninstall_entry::product_guid},
+};
+
+/// How often a waiting installer checks whether the other one is done
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Takes the lock of the product, so no other installer of it writes the same files at the same time.
+///
+/// If another installer holds it, the manifest's [`SecondInstancePolicy`] decides whether that is an error
+/// or the lock is waited for. `on_wait` is called once before waiting, which can be cancelled.
+pub(super) fn lock_instance(
+    manifest: &AppManifest,
+    cancel: &CancelHandle,
+    mut on_wait: impl FnMut(),
+) -> Result<InstanceLock, InstallError> {
+    let mut waiting = false;
+    loop {
+        if let Some(lock) =
+            InstanceLock::try_acquire(manifest).map_err(InstallError::InstanceLock)?
+        {
+            return Ok(lock);
+        }
+        if manifest.second_instance == SecondInstancePolicy::Exit {
+            return Err(InstallError::AlreadyRunning(manifest.friendly_name.clone()));
+        }
+        if !waiting {
+            waiting = true;
+            on_wait();
+        }
+        if cancel.is_cancelled() {
+            return Err(InstallError::Cancelled);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Held while an installer of a product runs.
+///
+/// On Unix this is an advisory lock on a file in a directory private to the user, on Windows a named mutex.
+/// Both are released by the operating system when the process exits, also if it crashes.
+pub(super) struct InstanceLock {
+    #[cfg(unix)]
+    _file: std::fs::File,
+    #[cfg(windows)]
+    mutex: windows::Win32::Foundation::HANDLE,
+}
+
+impl InstanceLock {
+    /// Takes the lock of the product, `None` if another installer holds it
+    fn try_acquire(manifest: &AppManifest) -> io::Result<Option<Self>> {
+        let name = format!("frost-wizard-{}", product_guid(manifest));
+
+        #[cfg(unix)]
+        {
+            Self::lock_file(&lock_dir().join(format!("{name}.lock")))
+        }
+
+        #[cfg(windows)]
+        {
+            use windows::{
+                Win32::{
+                    Foundation::{CloseHandle, E_ACCESSDENIED, WAIT_ABANDONED, WAIT_OBJECT_0},
+                    System::Threading::{CreateMutexW, WaitForSingleObject},
+                },
+                core::HSTRING,
+            };
+
+            // Global, so installers in other sessions are seen too
+            let mutex = match unsafe {
+                CreateMutexW(None, false, &HSTRING::from(format!("Global\\{name}")))
+            } {
+                Ok(mutex) => mutex,
+                // The mutex of an elevated installer can't be opened by one without administrator rights
+                Err(err) if err.code() == E_ACCESSDENIED => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
+            match unsafe { WaitForSingleObject(mutex, 0) } {
+                // Abandoned means the other installer exited without releasing it
+                WAIT_OBJECT_0 | WAIT_ABANDONED => Ok(Some(Self { mutex })),
+                _ => {
+                    let _ = unsafe { CloseHandle(mutex) };
+                    Ok(None)
+                }
+            }
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        {
+            let _ = name;
+            Ok(Some(Self {}))
+        }
+    }
+
+    /// Takes an advisory lock on a file in a directory only the installing user can write to
+    #[cfg(unix)]
+    fn lock_file(path: &Path) -> io::Result<Option<Self>> {
+        use std::{fs::OpenOptions, os::fd::AsRawFd};
+
+        if let Some(dir) = path.parent() {
+            private_dir::create_dir(dir)?;
+        }
+        let file =
+            private_dir::open_file(path, OpenOptions::new().read(true).write(true).create(true))?;
+
+        loop {
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+                return Ok(Some(Self { _file: file }));
+            }
+            let err = io::Error::last_os_error();
+            match err.kind() {
+                io::ErrorKind::Interrupted => continue,
+                io::ErrorKind::WouldBlock => return Ok(None),
+                _ => return Err(err),
+            }
+        }
+    }
+}
+
+/// Directory the lock files are kept in, so other users can neither take nor replace them.
+///
+/// It's per user, which separates the scopes as well: only root can install for all users.
+#[cfg(unix)]
+fn lock_dir() -> PathBuf {
+    let base = if unsafe { libc::geteuid() } == 0 {
+        PathBuf::from("/var/run")
+    } else {
+        std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .unwrap_or_else(|| std::env::home_dir().unwrap_or_default().join(".cache"))
+    };
+    base.join("frost-wizard")
+}
+
+#[cfg(windows)]
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        use windows::Win32::{Foundation::CloseHandle, System::Threading::ReleaseMutex};
+
+        let _ = unsafe { ReleaseMutex(self.mutex) };
+        let _ = unsafe { CloseHandle(self.mutex) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::manifest;
+
+    // A Windows mutex can be taken again by the thread that owns it
+    #[cfg(unix)]
+    #[test]
+    fn second_lock_is_refused_until_first_is_dropped() {
+        let manifest = manifest();
+
+        let first = InstanceLock::try_acquire(&manifest).unwrap();
+        assert!(first.is_some());
+        assert!(InstanceLock::try_acquire(&manifest).unwrap().is_none());
+
+        drop(first);
+        assert!(InstanceLock::try_acquire(&manifest).unwrap().is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_linked_lock_file() {
+        let dir = crate::test_util::TempDir::new();
+        let target = dir.path().join("target");
+        std::fs::write(&target, b"unrelated").unwrap();
+        let path = dir.path().join("locks").join("test.lock");
+        private_dir::create_dir(path.parent().unwrap()).unwrap();
+        std::os::unix::fs::symlink(&target, &path).unwrap();
+
+        let err = InstanceLock::lock_file(&path).err().unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(std::fs::read(&target).unwrap(), b"unrelated");
+    }
+}
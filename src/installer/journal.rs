@@ -0,0 +1,80 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::InstallScope,
+    installer::services::{self, CommandRunner},
+};
+
+/// Records the files and directories an installation creates, so they can be removed again if it doesn't complete.
+///
+/// Files that already existed before are overwritten in place and therefore can't be restored.
+/// Services of an earlier installation that were stopped are started again.
+#[derive(Default)]
+pub(super) struct Journal {
+    created: Vec<Created>,
+    /// With the scope they are installed in
+    stopped_services: Option<(InstallScope, Vec<String>)>,
+}
+
+enum Created {
+    File(PathBuf),
+    Directory(PathBuf),
+}
+
+impl Journal {
+    /// Like [`fs::create_dir_all`], but remembers every directory that had to be created
+    pub fn create_dir_all(&mut self, path: &Path) -> io::Result<()> {
+        let missing = path
+            .ancestors()
+            .take_while(|ancestor| !ancestor.as_os_str().is_empty() && !ancestor.exists())
+            .map(Path::to_path_buf)
+            .collect::<Vec<_>>();
+
+        for directory in missing.into_iter().rev() {
+            match fs::create_dir(&directory) {
+                Ok(()) => self.created.push(Created::Directory(directory)),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => (),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Has to be called before a file or symlink is written to `path`
+    pub fn track_file(&mut self, path: &Path) {
+        if fs::symlink_metadata(path).is_err() {
+            self.created.push(Created::File(path.to_path_buf()));
+        }
+    }
+
+    /// Has to be called with the services that were stopped, before anything is written
+    pub fn track_stopped_services(&mut self, scope: InstallScope, names: Vec<String>) {
+        self.stopped_services = Some((scope, names));
+    }
+
+    /// Everything recorded so far, oldest first
+    pub fn created(&self) -> impl Iterator<Item = &Path> {
+        self.created.iter().map(|created| match created {
+            Created::File(path) | Created::Directory(path) => path.as_path(),
+        })
+    }
+
+    /// Removes everything recorded, newest first, then starts the stopped services again.
+    ///
+    /// Errors are ignored, so as much as possible gets cleaned up.
+    pub fn rollback(self, runner: &mut impl CommandRunner) {
+        for created in self.created.into_iter().rev() {
+            let _ = match created {
+                Created::File(path) => fs::remove_file(path),
+                Created::Directory(path) => fs::remove_dir(path),
+            };
+        }
+        if let Some((scope, names)) = self.stopped_services {
+            services::start_services(scope, &names, runner);
+        }
+    }
+}
@@ -0,0 +1,79 @@
+#[cfg(unix)]
+use std::{
+    ffi::CStr,
+    os::unix::process::CommandExt,
+    process::{Command, Stdio},
+};
+use std::{io, path::Path};
+
+use crate::config::AppManifest;
+
+/// Starts the installed application, detached from the installer.
+///
+/// It runs with the rights of the user who started the installer, even if the installer itself was elevated.
+pub(crate) fn launch_application(manifest: &AppManifest, install_path: &Path) -> io::Result<()> {
+    let program = install_path.join(&manifest.bin_name);
+
+    #[cfg(windows)]
+    return crate::windows::shell_open(&program);
+
+    #[cfg(unix)]
+    {
+        let mut command = Command::new(program);
+        command.current_dir(install_path);
+        spawn_unprivileged(command)
+    }
+}
+
+/// Opens a payload file like a README with the default application for its type
+pub(crate) fn open_file(install_path: &Path, file: &str) -> io::Result<()> {
+    open_path(&install_path.join(file))
+}
+
+/// Opens any file, like the install log, with the default application for its type
+pub(crate) fn open_path(path: &Path) -> io::Result<()> {
+    #[cfg(windows)]
+    return crate::windows::shell_open(path);
+
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let mut command = Command::new("xdg-open");
+    #[cfg(unix)]
+    {
+        command.arg(path);
+        spawn_unprivileged(command)
+    }
+}
+
+/// Spawns a command as the user who ran the installer through `sudo` or `pkexec`, root only installs
+#[cfg(unix)]
+fn spawn_unprivileged(mut command: Command) -> io::Result<()> {
+    let invoking_uid = ["SUDO_UID", "PKEXEC_UID"]
+        .into_iter()
+        .find_map(|name| std::env::var(name).ok()?.parse::<libc::uid_t>().ok());
+    if let Some(uid) = invoking_uid
+        && unsafe { libc::geteuid() } == 0
+    {
+        let passwd = unsafe { libc::getpwuid(uid) };
+        if passwd.is_null() {
+            return Err(io::Error::other(format!("Unknown user id {uid}")));
+        }
+        let passwd = unsafe { &*passwd };
+        let name = unsafe { CStr::from_ptr(passwd.pw_name) }.to_string_lossy();
+        let home = unsafe { CStr::from_ptr(passwd.pw_dir) }.to_string_lossy();
+        command
+            .uid(uid)
+            .gid(passwd.pw_gid)
+            .env("HOME", home.as_ref())
+            .env("USER", name.as_ref())
+            .env("LOGNAME", name.as_ref());
+    }
+
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
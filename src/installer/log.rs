@@ -0,0 +1,280 @@
+use std::{
+    fmt::Display,
+    fs::{self, File, OpenOptions},
+    io::{self, LineWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use time::OffsetDateTime;
+
+use crate::config::{AppManifest, InstallConfig, TargetSystem};
+
+/// Name of the log file inside the install directory, next to the receipt
+const LOG_FILE_NAME: &str = ".install-log";
+
+/// Where the log of an installation is written while it runs.
+///
+/// By default that's the temporary directory, the name contains the process id so concurrent installations don't share a log.
+pub(crate) fn log_path(config: &InstallConfig, manifest: &AppManifest) -> PathBuf {
+    config.log_file.clone().unwrap_or_else(|| {
+        std::env::temp_dir().join(format!(
+            "{}-install-{}.log",
+            manifest.bin_name,
+            std::process::id()
+        ))
+    })
+}
+
+/// Where a successful installation keeps its log
+pub(super) fn kept_log_path(install_path: &Path) -> PathBuf {
+    install_path.join(LOG_FILE_NAME)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Level::Info => write!(f, "INFO "),
+            Level::Warning => write!(f, "WARN "),
+            Level::Error => write!(f, "ERROR"),
+        }
+    }
+}
+
+/// A plain text log of an installation, one timestamped event per line like
+/// `2025-01-31 12:00:00.000Z INFO  file     bin/app`.
+///
+/// The log must never stop an installation, so if it can't be written it is silently skipped.
+pub(super) struct InstallLog {
+    path: PathBuf,
+    writer: Option<LineWriter<File>>,
+    /// Whether the log is in the default location, which is cleaned up once it is kept elsewhere
+    temporary: bool,
+}
+
+impl InstallLog {
+    /// Starts the log of an installation, replacing the one of an earlier attempt
+    pub fn create(config: &InstallConfig, manifest: &AppManifest) -> Self {
+        let path = log_path(config, manifest);
+        let temporary = config.log_file.is_none();
+        let file = if temporary {
+            create_temporary(&path)
+        } else {
+            fs::create_dir_all(path.parent().unwrap_or(Path::new(".")))
+                .and_then(|()| File::create(&path))
+        };
+        let mut log = Self {
+            path,
+            writer: file.map(LineWriter::new).ok(),
+            temporary,
+        };
+        log.header(config, manifest);
+        log
+    }
+
+    /// A log that isn't written anywhere
+    #[cfg(test)]
+    pub fn disabled() -> Self {
+        Self {
+            path: PathBuf::new(),
+            writer: None,
+            temporary: false,
+        }
+    }
+
+    /// Records what is installed where, so the log stands on its own
+    fn header(&mut self, config: &InstallConfig, manifest: &AppManifest) {
+        let system = TargetSystem::current();
+        self.info(
+            "start",
+            format!("{} {}", manifest.friendly_name, manifest.version),
+        );
+        self.info("system", format!("{} {}", system.os, system.arch));
+        self.info("target", config.install_path.display());
+        self.info("scope", format!("{:?}", config.scope));
+        if !config.components.is_empty() {
+            let selected = config
+                .selected_components
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            self.info("selected", selected.join(","));
+        }
+    }
+
+    pub fn info(&mut self, event: &str, message: impl Display) {
+        self.write(Level::Info, event, message);
+    }
+
+    pub fn warn(&mut self, event: &str, message: impl Display) {
+        self.write(Level::Warning, event, message);
+    }
+
+    pub fn error(&mut self, event: &str, message: impl Display) {
+        self.write(Level::Error, event, message);
+    }
+
+    pub fn write(&mut self, level: Level, event: &str, message: impl Display) {
+        let Some(writer) = self.writer.as_mut() else {
+            return;
+        };
+        let now = OffsetDateTime::now_utc();
+        // Multi-line messages like hook output are indented, so every event starts a line
+        let message = message.to_string().replace('\n', "\n    ");
+        let line = format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}Z {level} {event:<8} {message}\n",
+            now.year(),
+            u8::from(now.month()),
+            now.day(),
+            now.hour(),
+            now.minute(),
+            now.second(),
+            now.millisecond(),
+        );
+        if writer.write_all(line.as_bytes()).is_err() {
+            self.writer = None;
+        }
+    }
+
+    /// Copies the log into the install directory, where it stays with the installation.
+    /// Later events are written to the copy, a log in the default location is removed.
+    ///
+    /// The copy isn't part of the journal, a rollback must not take the only log with it. Uninstalling removes it.
+    pub fn keep_in(&mut self, install_path: &Path) -> io::Result<()> {
+        let Some(writer) = self.writer.as_mut() else {
+            return Ok(());
+        };
+        writer.flush()?;
+
+        let destination = kept_log_path(install_path);
+        // Copied rather than renamed, the temporary directory is often on another file system
+        fs::copy(&self.path, &destination)?;
+        if self.temporary {
+            let _ = fs::remove_file(&self.path);
+        }
+        self.writer = Some(LineWriter::new(
+            OpenOptions::new().append(true).open(&destination)?,
+        ));
+        self.path = destination;
+        Ok(())
+    }
+}
+
+/// Creates a log in the temporary directory, which other users can write to as well.
+///
+/// The file is always created anew, so a link placed at its path can't make an elevated installer overwrite another file.
+/// A log an earlier attempt of the same process left behind is replaced.
+fn create_temporary(path: &Path) -> io::Result<File> {
+    let create = || OpenOptions::new().write(true).create_new(true).open(path);
+    match create() {
+        Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+            // Removes a link itself, not what it points to
+            fs::remove_file(path)?;
+            create()
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        installer::{journal::Journal, services::SystemCommandRunner},
+        test_util::{TempDir, install_config, manifest},
+    };
+
+    #[test]
+    fn default_log_is_unique_per_process() {
+        let config = install_config(Path::new("/opt/test-app"));
+
+        let path = log_path(&config, &manifest());
+        assert_eq!(path.parent(), Some(std::env::temp_dir().as_path()));
+        assert_eq!(
+            path.file_name().unwrap().to_string_lossy(),
+            format!("test-app-install-{}.log", std::process::id())
+        );
+    }
+
+    #[test]
+    fn replaces_log_of_earlier_attempt() {
+        let dir = TempDir::new();
+        let path = dir.path().join("install.log");
+        fs::write(&path, "earlier attempt").unwrap();
+
+        create_temporary(&path)
+            .unwrap()
+            .write_all(b"new attempt")
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new attempt");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn doesnt_write_through_links() {
+        let dir = TempDir::new();
+        let target = dir.path().join("target");
+        fs::write(&target, "someone else's file").unwrap();
+        let path = dir.path().join("install.log");
+        std::os::unix::fs::symlink(&target, &path).unwrap();
+
+        create_temporary(&path).unwrap().write_all(b"log").unwrap();
+
+        assert_eq!(fs::read_to_string(&target).unwrap(), "someone else's file");
+        assert!(!fs::symlink_metadata(&path).unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "log");
+    }
+
+    #[test]
+    fn kept_log_survives_rollback() {
+        let dir = TempDir::new();
+        let install_path = dir.path().join("app");
+        let mut journal = Journal::default();
+        journal.create_dir_all(&install_path).unwrap();
+        let mut config = install_config(&install_path);
+        config.log_file = Some(dir.path().join("install.log"));
+        let mut log = InstallLog::create(&config, &manifest());
+
+        log.keep_in(&install_path).unwrap();
+        log.error("error", "Installation failed");
+        journal.rollback(&mut SystemCommandRunner);
+
+        let kept = fs::read_to_string(kept_log_path(&install_path)).unwrap();
+        assert!(kept.contains("INFO  start    Test App 1.0.0\n"));
+        assert!(kept.contains("ERROR error    Installation failed\n"));
+        // A log the user asked for stays where it is, it just isn't written anymore
+        assert!(
+            !fs::read_to_string(dir.path().join("install.log"))
+                .unwrap()
+                .contains("Installation failed")
+        );
+    }
+
+    #[test]
+    fn temporary_log_is_removed_once_kept() {
+        let dir = TempDir::new();
+        let path = dir.path().join("install.log");
+        let mut log = InstallLog {
+            path: path.clone(),
+            writer: Some(LineWriter::new(create_temporary(&path).unwrap())),
+            temporary: true,
+        };
+        log.info("start", "Test App 1.0.0");
+
+        log.keep_in(dir.path()).unwrap();
+
+        assert!(!path.exists());
+        assert!(
+            fs::read_to_string(kept_log_path(dir.path()))
+                .unwrap()
+                .contains("start    Test App 1.0.0")
+        );
+    }
+}
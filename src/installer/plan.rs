@@ -0,0 +1,585 @@
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+#[cfg(target_os = "linux")]
+use crate::installer::desktop;
+#[cfg(unix)]
+use crate::installer::env_path;
+use crate::{
+    config::{AppManifest, HookFailurePolicy, HookStage, InstallConfig, InstallScope},
+    disk_space::available_space,
+    installer::{
+        InstallError, config_files::ConfigFiles, extract, log, receipt::Receipt, services,
+        template::Templates,
+    },
+    registry::RegistryChange,
+    ui::format_size,
+};
+
+/// How a plan is printed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum PlanFormat {
+    /// Readable by humans
+    #[default]
+    Text,
+    /// A JSON object, for tools that review changes before they are rolled out
+    Json,
+}
+
+/// Everything an installation would do, determined without changing anything on the system
+#[derive(Debug, Clone, Serialize)]
+pub struct InstallPlan {
+    pub application: String,
+    pub version: String,
+    pub install_path: PathBuf,
+    pub scope: InstallScope,
+    /// Ids of the selected components
+    pub components: Vec<String>,
+    /// Bytes the installed payloads take up
+    pub required_space: u64,
+    /// Free bytes at the install path, if they could be determined
+    pub available_space: Option<u64>,
+    /// Prerequisites that aren't met, the installation wouldn't start
+    pub unmet_requirements: Vec<String>,
+    pub files: Vec<PlannedFile>,
+    /// Remote payloads, which files they contain is only known once they are downloaded
+    pub downloads: Vec<PlannedDownload>,
+    /// Setup tasks in the order they run
+    pub hooks: Vec<PlannedHook>,
+    /// Menu entries and desktop shortcuts, they are listed as files too
+    pub shortcuts: Vec<PathBuf>,
+    pub registry: Vec<RegistryChange>,
+    pub services: Vec<PlannedService>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedFile {
+    pub path: PathBuf,
+    /// Size in bytes, if it is known before installing
+    pub size: Option<u64>,
+    pub action: FileAction,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileAction {
+    Create,
+    Overwrite,
+    /// A configuration file the user modified stays as it is
+    Keep,
+    /// The existing file is copied here before it is overwritten
+    Backup,
+}
+
+impl FileAction {
+    /// Creates a file that doesn't exist yet, anything else is overwritten
+    pub(super) fn for_path(path: &Path) -> Self {
+        if fs::symlink_metadata(path).is_ok() {
+            FileAction::Overwrite
+        } else {
+            FileAction::Create
+        }
+    }
+}
+
+impl Display for FileAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileAction::Create => write!(f, "create"),
+            FileAction::Overwrite => write!(f, "overwrite"),
+            FileAction::Keep => write!(f, "keep"),
+            FileAction::Backup => write!(f, "backup"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedDownload {
+    pub url: String,
+    pub sha256: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedHook {
+    pub stage: HookStage,
+    pub command: String,
+    pub description: Option<String>,
+    pub on_failure: HookFailurePolicy,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedService {
+    pub name: String,
+    pub action: ServiceAction,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ServiceAction {
+    Install,
+    /// A service of an earlier installation is reconfigured and restarted
+    Update,
+    /// A service of an earlier installation that this version doesn't have anymore
+    Remove,
+}
+
+impl Display for ServiceAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServiceAction::Install => write!(f, "install"),
+            ServiceAction::Update => write!(f, "update"),
+            ServiceAction::Remove => write!(f, "remove"),
+        }
+    }
+}
+
+impl InstallPlan {
+    fn add_file(&mut self, path: PathBuf, size: Option<u64>) {
+        let action = FileAction::for_path(&path);
+        self.files.push(PlannedFile { path, size, action });
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Plans only contain serializable values")
+    }
+}
+
+impl Display for InstallPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let scope = self
+            .scope
+            .to_possible_value()
+            .expect("No variant is skipped");
+        writeln!(f, "Installing {} {}", self.application, self.version)?;
+        writeln!(f, "Install path: {}", self.install_path.display())?;
+        writeln!(f, "Scope: {}", scope.get_name())?;
+        if !self.components.is_empty() {
+            writeln!(f, "Components: {}", self.components.join(", "))?;
+        }
+        write!(
+            f,
+            "Disk space: {} required",
+            format_size(self.required_space)
+        )?;
+        match self.available_space {
+            Some(available) => writeln!(f, ", {} available", format_size(available))?,
+            None => writeln!(f)?,
+        }
+
+        if !self.unmet_requirements.is_empty() {
+            writeln!(f, "\nUnmet requirements:")?;
+            for requirement in &self.unmet_requirements {
+                writeln!(f, "  {requirement}")?;
+            }
+        }
+
+        writeln!(f, "\nFiles:")?;
+        for file in &self.files {
+            let size = file.size.map(format_size).unwrap_or_default();
+            writeln!(
+                f,
+                "  {:<9} {size:>10}  {}",
+                file.action,
+                file.path.display()
+            )?;
+        }
+
+        if !self.downloads.is_empty() {
+            writeln!(f, "\nDownloads:")?;
+            for download in &self.downloads {
+                writeln!(
+                    f,
+                    "  {:>10}  {} (SHA-256 {})",
+                    format_size(download.size),
+                    download.url,
+                    download.sha256
+                )?;
+            }
+        }
+
+        if !self.hooks.is_empty() {
+            writeln!(f, "\nSetup tasks:")?;
+            for hook in &self.hooks {
+                let stage = match hook.stage {
+                    HookStage::PreInstall => "before",
+                    HookStage::PostInstall => "after",
+                };
+                write!(f, "  {stage:<6} {}", hook.command)?;
+                if hook.on_failure == HookFailurePolicy::Continue {
+                    write!(f, " (failures are ignored)")?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        if !self.shortcuts.is_empty() {
+            writeln!(f, "\nShortcuts:")?;
+            for shortcut in &self.shortcuts {
+                writeln!(f, "  {}", shortcut.display())?;
+            }
+        }
+
+        if !self.registry.is_empty() {
+            writeln!(f, "\nRegistry:")?;
+            for change in &self.registry {
+                writeln!(f, "  {change}")?;
+            }
+        }
+
+        if !self.services.is_empty() {
+            writeln!(f, "\nServices:")?;
+            for service in &self.services {
+                writeln!(f, "  {:<9} {}", service.action, service.name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Determines what installing `config` would do, reading the system but never changing it.
+///
+/// Errors that the installation would run into before changing anything, like unsafe payload paths, are returned the same way.
+/// Files of a staged installation are listed where they are written, below the staging root.
+pub(super) fn plan(
+    mut config: InstallConfig,
+    manifest: &AppManifest,
+) -> Result<InstallPlan, InstallError> {
+    let install_dir = config.staged(&config.install_path);
+    // Setup tasks and services act on the running system, which a staged installation leaves alone
+    let staged = config.root.is_some();
+    let previous = Receipt::load(&install_dir)
+        .map_err(InstallError::ReadReceipt)?
+        .unwrap_or_default();
+    let hooks = [HookStage::PreInstall, HookStage::PostInstall]
+        .into_iter()
+        .flat_map(|stage| {
+            manifest
+                .hooks
+                .iter()
+                .filter(move |hook| hook.stage == stage)
+        })
+        .map(|hook| PlannedHook {
+            stage: hook.stage,
+            command: hook.to_string(),
+            description: hook.description.clone(),
+            on_failure: hook.on_failure,
+        })
+        .collect();
+    let mut plan = InstallPlan {
+        application: manifest.friendly_name.clone(),
+        version: manifest.version.clone(),
+        install_path: config.install_path.clone(),
+        scope: config.scope,
+        components: config.selected_components.iter().cloned().collect(),
+        required_space: config.required_space(),
+        available_space: available_space(&install_dir).ok(),
+        unmet_requirements: Vec::new(),
+        files: Vec::new(),
+        downloads: Vec::new(),
+        hooks: if staged { Vec::new() } else { hooks },
+        shortcuts: Vec::new(),
+        registry: Vec::new(),
+        services: if staged {
+            Vec::new()
+        } else {
+            services::planned_services(manifest, &previous.services)
+        },
+    };
+
+    let templates = Templates::new(manifest, &config);
+    let config_files = ConfigFiles::new(manifest, previous.config_files.clone());
+    for payload in config.take_payloads() {
+        extract::plan_payload(payload, &install_dir, &templates, &config_files, &mut plan)?;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // Icons of file types are payload files, they are only known if an earlier installation left them
+        let read_installed = |path: &Path| Ok(fs::read(config.staged(path)).ok());
+        let desktop_files = desktop::desktop_files(manifest, &config, read_installed)
+            .map_err(InstallError::DesktopIntegration)?;
+        for file in desktop_files.map(|(_, files)| files).unwrap_or_default() {
+            let path = config.staged(&file.path);
+            if file.is_shortcut {
+                plan.shortcuts.push(path.clone());
+            }
+            plan.add_file(path, Some(file.contents.len() as u64));
+        }
+    }
+
+    #[cfg(unix)]
+    if config.add_to_path {
+        let link = config.staged(&env_path::path_link(manifest, &config));
+        env_path::existing_link(&link, &config.install_path).map_err(InstallError::AddToPath)?;
+        plan.add_file(link, None);
+    }
+
+    #[cfg(windows)]
+    plan_windows(manifest, &config, &previous, &mut plan)?;
+
+    #[cfg(target_os = "linux")]
+    for (path, contents) in services::unit_files(manifest, &config) {
+        plan.add_file(config.staged(&path), Some(contents.len() as u64));
+    }
+
+    plan.add_file(Receipt::path(&install_dir), None);
+    plan.add_file(log::kept_log_path(&install_dir), None);
+
+    Ok(plan)
+}
+
+/// Shortcuts, the uninstaller and everything written to the registry
+#[cfg(windows)]
+fn plan_windows(
+    manifest: &AppManifest,
+    config: &InstallConfig,
+    previous: &Receipt,
+    plan: &mut InstallPlan,
+) -> Result<(), InstallError> {
+    use crate::{
+        installer::{associations, env_path, shortcuts, uninstall_entry},
+        registry::{Hive, RecordingRegistry, SystemRegistry},
+    };
+
+    let install_path = config.install_path.to_string_lossy();
+    let hive = Hive::for_scope(config.scope);
+    let mut registry = RecordingRegistry::new(&SystemRegistry);
+
+    let shortcut_size = shortcuts::shortcut_bytes(manifest, &install_path).len() as u64;
+    for path in
+        shortcuts::shortcut_paths(manifest, config).map_err(InstallError::CreateShortcuts)?
+    {
+        let path = config.staged(&path);
+        plan.shortcuts.push(path.clone());
+        plan.add_file(path, Some(shortcut_size));
+    }
+
+    let uninstaller = uninstall_entry::uninstaller_path(&config.install_path);
+    let installer_size = std::env::current_exe()
+        .and_then(fs::metadata)
+        .ok()
+        .map(|metadata| metadata.len());
+    plan.add_file(config.staged(&uninstaller), installer_size);
+
+    // Staged installations leave the registry alone
+    if config.root.is_some() {
+        return Ok(());
+    }
+
+    if config.add_to_path {
+        env_path::add_path_entry(&mut registry, hive, &install_path)
+            .map_err(InstallError::AddToPath)?;
+    }
+
+    associations::unregister(&mut registry, hive, &previous.associations)
+        .map_err(InstallError::RegisterAssociations)?;
+    associations::register(&mut registry, hive, manifest, &install_path)
+        .map_err(InstallError::RegisterAssociations)?;
+
+    let entry = uninstall_entry::UninstallEntry {
+        manifest,
+        scope: config.scope,
+        install_path: &config.install_path,
+        uninstaller: &uninstaller,
+        size: plan.required_space,
+        install_date: time::OffsetDateTime::now_utc().date(),
+    };
+    entry
+        .write(&mut registry)
+        .map_err(InstallError::RegistryError)?;
+
+    plan.registry = registry.into_changes();
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        io::{Cursor, Write},
+    };
+
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+    use crate::{
+        config::{ConfigFilePolicy, FilePayload},
+        installer::{install_blocking, journal::Journal, progress::ProgressReporter},
+        test_util::{TempDir, install_config, manifest},
+    };
+
+    fn app_manifest() -> AppManifest {
+        manifest()
+            .config_file("app.toml")
+            .config_file_policy(ConfigFilePolicy::Backup)
+    }
+
+    /// A staged installation of a binary, a template and a configuration file, with a menu entry and a link on the `PATH`
+    fn staged_config(root: &Path) -> InstallConfig {
+        let mut config = install_config(Path::new("/opt/test-app"));
+        config.root = Some(root.to_path_buf());
+        config.scope = InstallScope::Machine;
+        config.create_start_menu_shortcut = true;
+        config.add_to_path = true;
+
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in [
+            ("share/readme.txt", "Read me"),
+            ("share/about.txt.tmpl", "{friendly_name} {version}"),
+            ("app.toml", "port = 80\n"),
+        ] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        config.payloads = vec![
+            FilePayload::File {
+                name: "test-app".into(),
+                contents: b"binary".as_slice().into(),
+            },
+            FilePayload::Directory {
+                unpacked_size: 0,
+                reader: Box::new(zip.finish().unwrap()),
+            },
+        ];
+        config
+    }
+
+    fn install(root: &Path) {
+        let (mut progress, _receiver) = ProgressReporter::for_tests();
+        let mut journal = Journal::default();
+        install_blocking(
+            staged_config(root),
+            &app_manifest(),
+            &mut progress,
+            &mut journal,
+        )
+        .unwrap();
+    }
+
+    /// Files and symlinks below `dir` with their sizes
+    fn written_files(dir: &Path) -> BTreeMap<PathBuf, u64> {
+        let mut files = BTreeMap::new();
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(current) = pending.pop() {
+            for entry in fs::read_dir(current).unwrap() {
+                let path = entry.unwrap().path();
+                let metadata = fs::symlink_metadata(&path).unwrap();
+                if metadata.is_dir() {
+                    pending.push(path);
+                } else {
+                    files.insert(path, metadata.len());
+                }
+            }
+        }
+        files
+    }
+
+    /// Checks that the plan lists exactly the files the installation wrote, in the size it wrote them
+    fn assert_plan_written(plan: &InstallPlan, root: &Path) {
+        let written = written_files(root);
+        // Tests don't write a log that could be kept
+        let kept_log = log::kept_log_path(&root.join("opt/test-app"));
+        let planned = plan
+            .files
+            .iter()
+            .filter(|file| file.path != kept_log)
+            .collect::<Vec<_>>();
+
+        let planned_paths = planned.iter().map(|file| &file.path).collect::<Vec<_>>();
+        assert_eq!(planned_paths, written.keys().collect::<Vec<_>>());
+        for file in planned {
+            if let Some(size) = file.size {
+                assert_eq!(size, written[&file.path], "{}", file.path.display());
+            }
+        }
+    }
+
+    fn sorted(mut plan: InstallPlan) -> InstallPlan {
+        plan.files.sort_by(|a, b| a.path.cmp(&b.path));
+        plan
+    }
+
+    #[test]
+    fn plan_lists_what_staged_installation_writes() {
+        let root = TempDir::new();
+
+        let plan = sorted(plan(staged_config(root.path()), &app_manifest()).unwrap());
+        install(root.path());
+
+        assert_plan_written(&plan, root.path());
+        assert!(
+            plan.files
+                .iter()
+                .all(|file| file.action == FileAction::Create)
+        );
+        let about = plan
+            .files
+            .iter()
+            .find(|file| file.path.ends_with("share/about.txt"))
+            .unwrap();
+        assert_eq!(about.size, Some("Test App 1.0.0".len() as u64));
+
+        let text = plan.to_string();
+        let json = serde_json::from_str::<serde_json::Value>(&plan.to_json()).unwrap();
+        let json_files = json["files"].as_array().unwrap();
+        assert_eq!(json_files.len(), plan.files.len());
+        for (file, json_file) in plan.files.iter().zip(json_files) {
+            let path = file.path.display().to_string();
+            assert!(
+                text.lines()
+                    .any(|line| line.starts_with("  create") && line.ends_with(&path)),
+                "{path} is missing in:\n{text}"
+            );
+            assert_eq!(json_file["path"], path.as_str());
+            assert_eq!(json_file["action"], "create");
+        }
+    }
+
+    #[test]
+    fn plan_lists_backup_of_modified_configuration_file() {
+        let root = TempDir::new();
+        install(root.path());
+        let install_dir = root.path().join("opt/test-app");
+        fs::write(install_dir.join("app.toml"), "port = 8080\n").unwrap();
+
+        let plan = sorted(plan(staged_config(root.path()), &app_manifest()).unwrap());
+        install(root.path());
+
+        assert_plan_written(&plan, root.path());
+        let action = |name: &str| {
+            let path = install_dir.join(name);
+            plan.files
+                .iter()
+                .find(|file| file.path == path)
+                .map(|file| (file.action, file.size))
+        };
+        assert_eq!(action("app.toml.bak"), Some((FileAction::Backup, Some(12))));
+        assert_eq!(action("app.toml"), Some((FileAction::Overwrite, Some(10))));
+        assert_eq!(
+            fs::read_to_string(install_dir.join("app.toml.bak")).unwrap(),
+            "port = 8080\n"
+        );
+    }
+
+    #[test]
+    fn plan_refuses_payload_paths_through_symlinks() {
+        let root = TempDir::new();
+        let outside = TempDir::new();
+        let install_dir = root.path().join("opt/test-app");
+        fs::create_dir_all(&install_dir).unwrap();
+        std::os::unix::fs::symlink(outside.path(), install_dir.join("share")).unwrap();
+
+        let result = plan(staged_config(root.path()), &app_manifest());
+
+        assert!(
+            matches!(result, Err(InstallError::UnsafePayloadPath(path)) if path == "share/readme.txt")
+        );
+    }
+}
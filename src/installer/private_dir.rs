@@ -0,0 +1,85 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    path::Path,
+};
+
+/// Creates a directory only the installing user can access, for state kept outside of the installation.
+///
+/// An existing directory is used if it isn't a link and belongs to the user, its permissions are tightened.
+pub(super) fn create_dir(dir: &Path) -> io::Result<()> {
+    if let Some(parent) = dir.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+
+        builder.mode(0o700);
+    }
+    match builder.create(dir) {
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => (),
+        result => result?,
+    }
+
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || !is_own(&metadata) {
+        return Err(not_private(dir));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Created by an older version or with a different umask
+        if metadata.permissions().mode() & 0o077 != 0 {
+            fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+        }
+    }
+    Ok(())
+}
+
+/// Opens a file in a private directory, refusing links and files of other users
+pub(super) fn open_file(path: &Path, options: &mut OpenOptions) -> io::Result<File> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        options.mode(0o600).custom_flags(libc::O_NOFOLLOW);
+    }
+    let file = options.open(path).map_err(|err| {
+        // What O_NOFOLLOW reports for links
+        #[cfg(unix)]
+        if err.raw_os_error() == Some(libc::ELOOP) {
+            return not_private(path);
+        }
+        err
+    })?;
+    let metadata = file.metadata()?;
+    if !metadata.is_file() || !is_own(&metadata) {
+        return Err(not_private(path));
+    }
+    Ok(file)
+}
+
+/// Whether the installing user owns a file, others could change it otherwise
+pub(super) fn is_own(metadata: &fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        metadata.uid() == unsafe { libc::geteuid() }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        true
+    }
+}
+
+fn not_private(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!("{path:?} is a link or belongs to another user"),
+    )
+}
@@ -0,0 +1,277 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::mpsc;
+
+use crate::installer::{CancelHandle, InstallError, log::InstallLog};
+
+/// Minimum time between two progress updates, so receivers aren't flooded with messages
+const MIN_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Another installer of the product is running
+    Waiting,
+    Preparing,
+    Downloading,
+    Extracting,
+    RunningHooks,
+    Finalizing,
+    RollingBack,
+    Done,
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Phase::Waiting => write!(f, "Waiting for another installer to finish"),
+            Phase::Preparing => write!(f, "Preparing installation"),
+            Phase::Downloading => write!(f, "Downloading files"),
+            Phase::Extracting => write!(f, "Extracting files"),
+            Phase::RunningHooks => write!(f, "Running setup tasks"),
+            Phase::Finalizing => write!(f, "Finalizing installation"),
+            Phase::RollingBack => write!(f, "Removing partially installed files"),
+            Phase::Done => write!(f, "Done"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Progress {
+    pub phase: Phase,
+    /// Path of the file currently being written, relative to the install directory
+    pub current_file: Option<PathBuf>,
+    /// Description of the setup task currently running
+    pub current_task: Option<String>,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub files_done: u64,
+    pub files_total: u64,
+}
+
+impl Progress {
+    /// Progress at the start of a phase, before anything is done
+    pub(super) fn new(phase: Phase, bytes_total: u64) -> Self {
+        Self {
+            phase,
+            current_file: None,
+            current_task: None,
+            bytes_done: 0,
+            bytes_total,
+            files_done: 0,
+            files_total: 0,
+        }
+    }
+
+    /// Overall progress between 0.0 and 1.0
+    pub fn fraction(&self) -> f32 {
+        if self.phase == Phase::Done {
+            1.0
+        } else if self.bytes_total == 0 {
+            0.0
+        } else {
+            (self.bytes_done as f64 / self.bytes_total as f64).min(1.0) as f32
+        }
+    }
+}
+
+/// Tracks the progress of an installation and forwards it to the UI at a limited rate.
+///
+/// Phase changes are always sent immediately. Every phase, file and task is also written to the install log.
+/// If nobody is listening for progress anymore, e.g. because the window was closed, the installation is cancelled.
+pub(super) struct ProgressReporter {
+    sender: mpsc::Sender<Progress>,
+    cancel: CancelHandle,
+    progress: Progress,
+    last_sent: Option<Instant>,
+    log: InstallLog,
+}
+
+impl ProgressReporter {
+    pub fn new(
+        sender: mpsc::Sender<Progress>,
+        cancel: CancelHandle,
+        bytes_total: u64,
+        log: InstallLog,
+    ) -> Self {
+        Self {
+            sender,
+            cancel,
+            progress: Progress::new(Phase::Preparing, bytes_total),
+            last_sent: None,
+            log,
+        }
+    }
+
+    pub fn log(&mut self) -> &mut InstallLog {
+        &mut self.log
+    }
+
+    /// Returns an error if the installation should stop
+    pub fn check_cancelled(&self) -> Result<(), InstallError> {
+        if self.cancel.is_cancelled() {
+            Err(InstallError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn set_files_total(&mut self, files_total: u64) {
+        self.progress.files_total = files_total;
+    }
+
+    pub fn phase(&mut self, phase: Phase) {
+        self.log.info("phase", phase);
+        self.progress.phase = phase;
+        self.progress.current_file = None;
+        self.progress.current_task = None;
+        self.send();
+    }
+
+    pub fn start_file(&mut self, path: &Path) {
+        self.log.info("file", path.display());
+        self.progress.current_file = Some(path.to_path_buf());
+        self.maybe_send();
+    }
+
+    pub fn start_task(&mut self, task: String) {
+        self.log.info("task", &task);
+        self.progress.current_task = Some(task);
+        self.send();
+    }
+
+    pub fn written(&mut self, bytes: u64) {
+        self.progress.bytes_done += bytes;
+        self.maybe_send();
+    }
+
+    pub fn file_done(&mut self) {
+        self.progress.files_done += 1;
+        self.maybe_send();
+    }
+
+    /// A reporter without a log, the receiver has to be kept alive as the installation is cancelled once it is dropped
+    #[cfg(test)]
+    pub fn for_tests() -> (Self, mpsc::Receiver<Progress>) {
+        let (sender, receiver) = mpsc::channel(1024);
+        let reporter = Self::new(sender, CancelHandle::new(), 0, InstallLog::disabled());
+        (reporter, receiver)
+    }
+
+    fn maybe_send(&mut self) {
+        if self
+            .last_sent
+            .is_none_or(|last_sent| last_sent.elapsed() >= MIN_INTERVAL)
+        {
+            self.send();
+        }
+    }
+
+    fn send(&mut self) {
+        self.last_sent = Some(Instant::now());
+        if self.sender.blocking_send(self.progress.clone()).is_err() {
+            self.cancel.cancel();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    fn received(receiver: &mut mpsc::Receiver<Progress>) -> Vec<Progress> {
+        let mut received = Vec::new();
+        while let Ok(progress) = receiver.try_recv() {
+            received.push(progress);
+        }
+        received
+    }
+
+    #[test]
+    fn limits_rate_of_updates() {
+        let (mut progress, mut receiver) = ProgressReporter::for_tests();
+
+        for _ in 0..100 {
+            progress.written(1);
+        }
+        let first = received(&mut receiver);
+        thread::sleep(MIN_INTERVAL);
+        progress.written(1);
+        let second = received(&mut receiver);
+
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].bytes_done, 1);
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].bytes_done, 101);
+    }
+
+    #[test]
+    fn sends_phase_changes_and_tasks_immediately() {
+        let (mut progress, mut receiver) = ProgressReporter::for_tests();
+
+        progress.start_file(Path::new("bin/app"));
+        progress.phase(Phase::RunningHooks);
+        progress.start_task("setup".to_string());
+        progress.phase(Phase::Finalizing);
+
+        let received = received(&mut receiver);
+        assert_eq!(
+            received
+                .iter()
+                .map(|progress| progress.phase)
+                .collect::<Vec<_>>(),
+            [
+                Phase::Preparing,
+                Phase::RunningHooks,
+                Phase::RunningHooks,
+                Phase::Finalizing,
+            ]
+        );
+        assert_eq!(
+            received[0].current_file.as_deref(),
+            Some(Path::new("bin/app"))
+        );
+        // A new phase starts without a current file or task
+        assert_eq!(received[1].current_file, None);
+        assert_eq!(received[2].current_task.as_deref(), Some("setup"));
+        assert_eq!(received[3].current_task, None);
+    }
+
+    #[test]
+    fn counts_bytes_and_files() {
+        let (sender, mut receiver) = mpsc::channel(16);
+        let mut progress =
+            ProgressReporter::new(sender, CancelHandle::new(), 300, InstallLog::disabled());
+
+        progress.set_files_total(2);
+        progress.written(100);
+        progress.file_done();
+        progress.written(200);
+        progress.file_done();
+        progress.phase(Phase::Finalizing);
+
+        let last = received(&mut receiver).pop().unwrap();
+        assert_eq!((last.bytes_done, last.bytes_total), (300, 300));
+        assert_eq!((last.files_done, last.files_total), (2, 2));
+        assert_eq!(last.fraction(), 1.0);
+    }
+
+    #[test]
+    fn fraction_stays_within_bounds() {
+        let mut progress = Progress::new(Phase::Extracting, 200);
+        progress.bytes_done = 50;
+        assert_eq!(progress.fraction(), 0.25);
+
+        // More bytes than expected are written e.g. if a payload is larger than declared
+        progress.bytes_done = 300;
+        assert_eq!(progress.fraction(), 1.0);
+
+        assert_eq!(Progress::new(Phase::Extracting, 0).fraction(), 0.0);
+        assert_eq!(Progress::new(Phase::Done, 0).fraction(), 1.0);
+    }
+}
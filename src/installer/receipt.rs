@@ -0,0 +1,180 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::InstallScope,
+    installer::{associations::Associations, journal::Journal},
+};
+
+/// Name of the receipt file inside the install directory
+const RECEIPT_FILE_NAME: &str = ".install-receipt";
+
+/// Version of the receipt format, which has to be raised when a field changes its meaning.
+/// Adding fields doesn't need a new version, they are missing from older receipts and take their default.
+const RECEIPT_FORMAT: u32 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReceiptError {
+    #[error("{0}")]
+    Io(io::Error),
+    #[error("It is damaged:\n{0}")]
+    Decode(serde_json::Error),
+    #[error("It was written by a newer installer, format version {0}")]
+    NewerFormat(u32),
+}
+
+/// Describes a completed installation, so later installations of the same application can build on it.
+///
+/// It is stored as JSON, along with the format version.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct Receipt {
+    pub version: String,
+    /// SHA-256 hashes of the configuration files as they were shipped, relative to the install directory
+    pub config_files: BTreeMap<PathBuf, [u8; 32]>,
+    /// Files and directories the installations created, oldest first. They are removed newest first when uninstalling.
+    pub created: Vec<PathBuf>,
+    /// Data directory the desktop integration was written to, its caches have to be refreshed after uninstalling
+    pub desktop_data_dir: Option<PathBuf>,
+    pub scope: InstallScope,
+    /// Directory the installation added to the Windows `Path` value
+    pub path_entry: Option<PathBuf>,
+    /// Registry key of the entry in the list of installed programs
+    pub uninstall_key: Option<String>,
+    /// File types and URL schemes registered in the Windows registry
+    pub associations: Associations,
+    /// Names of the installed services
+    pub services: Vec<String>,
+    /// Every component that was offered and whether it was selected
+    pub components: BTreeMap<String, bool>,
+}
+
+/// The version read before the rest of the receipt, so newer receipts are recognized even if their fields don't match anymore
+#[derive(Deserialize)]
+struct Format {
+    #[serde(default)]
+    format: u32,
+}
+
+#[derive(Serialize)]
+struct Versioned<'a> {
+    format: u32,
+    #[serde(flatten)]
+    receipt: &'a Receipt,
+}
+
+impl Receipt {
+    /// Reads the receipt of a previous installation, `None` if there is none
+    pub fn load(install_path: &Path) -> Result<Option<Self>, ReceiptError> {
+        let data = match fs::read(Self::path(install_path)) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(ReceiptError::Io(err)),
+        };
+
+        let Format { format } = serde_json::from_slice(&data).map_err(ReceiptError::Decode)?;
+        if format > RECEIPT_FORMAT {
+            return Err(ReceiptError::NewerFormat(format));
+        }
+        serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(ReceiptError::Decode)
+    }
+
+    pub fn path(install_path: &Path) -> PathBuf {
+        install_path.join(RECEIPT_FILE_NAME)
+    }
+
+    pub fn save(&self, install_path: &Path, journal: &mut Journal) -> io::Result<()> {
+        let versioned = Versioned {
+            format: RECEIPT_FORMAT,
+            receipt: self,
+        };
+        let data = serde_json::to_vec_pretty(&versioned).map_err(io::Error::other)?;
+        let path = Self::path(install_path);
+        journal.track_file(&path);
+        fs::write(path, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn round_trips() {
+        let dir = TempDir::new();
+        let receipt = Receipt {
+            version: "1.2.0".to_string(),
+            config_files: BTreeMap::from([(PathBuf::from("etc/app.conf"), [7; 32])]),
+            created: vec![dir.path().join("bin"), dir.path().join("bin/app")],
+            services: vec!["app".to_string()],
+            components: BTreeMap::from([("docs".to_string(), false)]),
+            ..Receipt::default()
+        };
+        receipt.save(dir.path(), &mut Journal::default()).unwrap();
+
+        let loaded = Receipt::load(dir.path()).unwrap().unwrap();
+        assert_eq!(loaded.version, receipt.version);
+        assert_eq!(loaded.config_files, receipt.config_files);
+        assert_eq!(loaded.created, receipt.created);
+        assert_eq!(loaded.services, receipt.services);
+        assert_eq!(loaded.components, receipt.components);
+    }
+
+    #[test]
+    fn missing_receipt_is_no_installation() {
+        let dir = TempDir::new();
+        assert!(Receipt::load(dir.path()).unwrap().is_none());
+    }
+
+    #[test]
+    fn missing_and_unknown_fields_are_tolerated() {
+        let dir = TempDir::new();
+        fs::write(
+            Receipt::path(dir.path()),
+            r#"{"format": 1, "version": "1.0.0", "added_later": [1, 2]}"#,
+        )
+        .unwrap();
+
+        let loaded = Receipt::load(dir.path()).unwrap().unwrap();
+        assert_eq!(loaded.version, "1.0.0");
+        assert!(loaded.created.is_empty());
+    }
+
+    #[test]
+    fn damaged_receipt_is_an_error() {
+        let dir = TempDir::new();
+        fs::write(Receipt::path(dir.path()), b"\x05\x01garbage").unwrap();
+        assert!(matches!(
+            Receipt::load(dir.path()),
+            Err(ReceiptError::Decode(_))
+        ));
+
+        fs::write(Receipt::path(dir.path()), r#"{"version": 3}"#).unwrap();
+        assert!(matches!(
+            Receipt::load(dir.path()),
+            Err(ReceiptError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn newer_format_is_an_error() {
+        let dir = TempDir::new();
+        fs::write(
+            Receipt::path(dir.path()),
+            r#"{"format": 2, "version": {"major": 2}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            Receipt::load(dir.path()),
+            Err(ReceiptError::NewerFormat(2))
+        ));
+    }
+}
@@ -0,0 +1,758 @@
+use std::{
+    io,
+    process::{Command, Stdio},
+};
+
+#[cfg(any(target_os = "linux", windows))]
+use crate::config::Service;
+use crate::{
+    config::{AppManifest, InstallConfig, InstallScope},
+    installer::{
+        journal::Journal,
+        plan::{PlannedService, ServiceAction},
+    },
+};
+
+#[cfg(any(windows, test))]
+mod scm;
+#[cfg(any(target_os = "linux", test))]
+mod systemd;
+
+/// Runs the tools that manage services, like `systemctl` or `sc.exe`
+pub(super) trait CommandRunner {
+    /// Runs a program to completion, a failed exit status is an error
+    fn run(&mut self, program: &str, args: &[String]) -> io::Result<()>;
+}
+
+/// Runs the commands on the system
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct SystemCommandRunner;
+
+impl CommandRunner for SystemCommandRunner {
+    fn run(&mut self, program: &str, args: &[String]) -> io::Result<()> {
+        let output = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()?;
+        if output.status.success() {
+            return Ok(());
+        }
+
+        let command = std::iter::once(program)
+            .chain(args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+        Err(io::Error::other(format!(
+            "`{command}` failed with {}:\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        )))
+    }
+}
+
+/// Stops the running services of an earlier installation, so their binaries can be replaced.
+///
+/// Services that aren't running are left alone. Returns the stopped ones, which a rollback starts again.
+pub(super) fn stop_services(
+    scope: InstallScope,
+    names: &[String],
+    runner: &mut impl CommandRunner,
+) -> Vec<String> {
+    names
+        .iter()
+        .filter(|name| stop_service(scope, name, runner))
+        .cloned()
+        .collect()
+}
+
+/// Returns whether the service was running and is stopped now
+fn stop_service(scope: InstallScope, name: &str, runner: &mut impl CommandRunner) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        let unit = systemd::unit_name(name);
+        // Stopping also succeeds if the service isn't running
+        runner
+            .run(
+                systemd::SYSTEMCTL,
+                &systemd::systemctl_args(scope, &["is-active", "--quiet", &unit]),
+            )
+            .is_ok()
+            && runner
+                .run(
+                    systemd::SYSTEMCTL,
+                    &systemd::systemctl_args(scope, &["stop", &unit]),
+                )
+                .is_ok()
+    }
+    #[cfg(windows)]
+    {
+        let _ = scope;
+        // Fails if the service isn't running
+        runner.run(scm::NET, &scm::stop_args(name)).is_ok()
+    }
+    #[cfg(not(any(target_os = "linux", windows)))]
+    {
+        let _ = (scope, name, runner);
+        false
+    }
+}
+
+/// Starts services [`stop_services`] stopped again, when the installation is rolled back.
+///
+/// Failures are ignored, so the rest of the rollback still happens.
+pub(super) fn start_services(
+    scope: InstallScope,
+    names: &[String],
+    runner: &mut impl CommandRunner,
+) {
+    for name in names {
+        #[cfg(target_os = "linux")]
+        let _ = runner.run(
+            systemd::SYSTEMCTL,
+            &systemd::systemctl_args(scope, &["start", &systemd::unit_name(name)]),
+        );
+        #[cfg(windows)]
+        let _ = runner.run(scm::NET, &scm::start_args(name));
+        #[cfg(not(any(target_os = "linux", windows)))]
+        let _ = (scope, name, &mut *runner);
+    }
+}
+
+/// Registers the services of the manifest and starts those that start automatically.
+///
+/// Services of the `previous` installation are updated in place, those the manifest doesn't declare anymore are removed.
+/// Staged installations only write the systemd units, nothing is registered with the running system.
+/// Returns the names of the installed services.
+pub(super) fn install_services(
+    manifest: &AppManifest,
+    config: &InstallConfig,
+    previous: &[String],
+    journal: &mut Journal,
+    runner: &mut impl CommandRunner,
+) -> io::Result<Vec<String>> {
+    let stale = previous
+        .iter()
+        .filter(|name| {
+            !manifest
+                .services
+                .iter()
+                .any(|service| &service.name == *name)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    let staged = config.root.is_some();
+    if !staged {
+        remove_services(config.scope, &stale, runner)?;
+    }
+
+    if manifest.services.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        journal.create_dir_all(&config.staged(&systemd::unit_dir(config.scope)))?;
+        for (path, contents) in unit_files(manifest, config) {
+            let path = config.staged(&path);
+            journal.track_file(&path);
+            std::fs::write(path, contents)?;
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = journal;
+        // Windows services only exist in the service manager
+        if staged {
+            return Ok(Vec::new());
+        }
+        if config.scope != InstallScope::Machine {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Services can only be installed for all users",
+            ));
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", windows)))]
+    {
+        let _ = (journal, staged);
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Services aren't supported on this platform",
+        ));
+    }
+
+    #[cfg(any(target_os = "linux", windows))]
+    if !staged {
+        activate_services(
+            config.scope,
+            &manifest.services,
+            &config.install_path.to_string_lossy(),
+            previous,
+            runner,
+        )?;
+    }
+
+    Ok(manifest
+        .services
+        .iter()
+        .map(|service| service.name.clone())
+        .collect())
+}
+
+/// Registers written services with the service manager and starts those that start automatically.
+///
+/// If a command fails, the services that didn't exist before are removed again, so a failed installation doesn't leave them registered.
+#[cfg(any(target_os = "linux", windows))]
+fn activate_services(
+    scope: InstallScope,
+    services: &[Service],
+    install_path: &str,
+    previous: &[String],
+    runner: &mut impl CommandRunner,
+) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    let commands = {
+        let _ = install_path;
+        systemd::install_commands(scope, services)
+            .into_iter()
+            .map(|args| (systemd::SYSTEMCTL, args))
+            .collect::<Vec<_>>()
+    };
+    #[cfg(windows)]
+    let commands = services
+        .iter()
+        .flat_map(|service| {
+            scm::install_commands(service, install_path, previous.contains(&service.name))
+        })
+        .collect::<Vec<_>>();
+
+    let Err(error) = commands
+        .into_iter()
+        .try_for_each(|(program, args)| runner.run(program, &args))
+    else {
+        return Ok(());
+    };
+
+    let added = services
+        .iter()
+        .map(|service| service.name.clone())
+        .filter(|name| !previous.contains(name))
+        .collect::<Vec<_>>();
+    // The failure of the installation is what gets reported, not one of the cleanup
+    let _ = remove_services(scope, &added, runner);
+    Err(error)
+}
+
+/// The systemd unit files [`install_services`] writes, with their contents
+#[cfg(target_os = "linux")]
+pub(super) fn unit_files(
+    manifest: &AppManifest,
+    config: &InstallConfig,
+) -> Vec<(std::path::PathBuf, String)> {
+    let unit_dir = systemd::unit_dir(config.scope);
+    manifest
+        .services
+        .iter()
+        .map(|service| {
+            (
+                unit_dir.join(systemd::unit_name(&service.name)),
+                systemd::unit_file(service, &config.install_path, config.scope),
+            )
+        })
+        .collect()
+}
+
+/// What [`install_services`] does to each service, given the services of the `previous` installation
+pub(super) fn planned_services(manifest: &AppManifest, previous: &[String]) -> Vec<PlannedService> {
+    let installed = manifest.services.iter().map(|service| PlannedService {
+        name: service.name.clone(),
+        action: if previous.contains(&service.name) {
+            ServiceAction::Update
+        } else {
+            ServiceAction::Install
+        },
+    });
+    let removed = previous
+        .iter()
+        .filter(|name| {
+            !manifest
+                .services
+                .iter()
+                .any(|service| &service.name == *name)
+        })
+        .map(|name| PlannedService {
+            name: name.clone(),
+            action: ServiceAction::Remove,
+        });
+    removed.chain(installed).collect()
+}
+
+/// Stops and unregisters services.
+///
+/// Removal continues past failures and the first one is returned.
+pub(super) fn remove_services(
+    scope: InstallScope,
+    names: &[String],
+    runner: &mut impl CommandRunner,
+) -> io::Result<()> {
+    if names.is_empty() {
+        return Ok(());
+    }
+
+    let mut first_error = None;
+
+    #[cfg(target_os = "linux")]
+    {
+        for name in names {
+            let unit = systemd::unit_name(name);
+            if let Err(error) = runner.run(
+                systemd::SYSTEMCTL,
+                &systemd::systemctl_args(scope, &["disable", "--now", &unit]),
+            ) {
+                first_error.get_or_insert(error);
+            }
+            match std::fs::remove_file(systemd::unit_dir(scope).join(unit)) {
+                Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                    first_error.get_or_insert(error);
+                }
+                _ => (),
+            }
+        }
+        if let Err(error) = runner.run(
+            systemd::SYSTEMCTL,
+            &systemd::systemctl_args(scope, &["daemon-reload"]),
+        ) {
+            first_error.get_or_insert(error);
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = scope;
+        for name in names {
+            // Not running is fine
+            let _ = runner.run(scm::NET, &scm::stop_args(name));
+            if let Err(error) = runner.run(scm::SC, &scm::delete_args(name)) {
+                first_error.get_or_insert(error);
+            }
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", windows)))]
+    let _ = (scope, &mut *runner);
+
+    match first_error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{config::Service, test_util::manifest};
+
+    /// Records the commands instead of running them, failing the one given
+    #[derive(Default)]
+    struct RecordingRunner {
+        commands: Vec<Vec<String>>,
+        fail: Option<Vec<String>>,
+    }
+
+    impl RecordingRunner {
+        fn failing(command: &[&str]) -> Self {
+            Self {
+                commands: Vec::new(),
+                fail: Some(strings(command)),
+            }
+        }
+    }
+
+    impl CommandRunner for RecordingRunner {
+        fn run(&mut self, program: &str, args: &[String]) -> io::Result<()> {
+            let command = std::iter::once(program.to_string())
+                .chain(args.iter().cloned())
+                .collect::<Vec<_>>();
+            self.commands.push(command.clone());
+            if self.fail.as_ref() == Some(&command) {
+                return Err(io::Error::other("failed"));
+            }
+            Ok(())
+        }
+    }
+
+    fn strings(command: &[&str]) -> Vec<String> {
+        command.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn commands(commands: &[&[&str]]) -> Vec<Vec<String>> {
+        commands.iter().map(|command| strings(command)).collect()
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        strings(names)
+    }
+
+    #[test]
+    fn plans_removed_updated_and_new_services() {
+        let manifest = manifest()
+            .service(Service::new("kept", "kept"))
+            .service(Service::new("new", "new"));
+
+        let planned = planned_services(&manifest, &names(&["stale", "kept"]));
+        assert_eq!(
+            planned
+                .iter()
+                .map(|service| (service.name.as_str(), service.action))
+                .collect::<Vec<_>>(),
+            [
+                ("stale", ServiceAction::Remove),
+                ("kept", ServiceAction::Update),
+                ("new", ServiceAction::Install),
+            ]
+        );
+    }
+
+    #[test]
+    fn doesnt_run_commands_for_staged_installations() {
+        let dir = crate::test_util::TempDir::new();
+        let mut config = crate::test_util::install_config(Path::new("/opt/test-app"));
+        config.root = Some(dir.path().to_path_buf());
+        let manifest = manifest().service(Service::new("server", "server"));
+        let mut runner = RecordingRunner::default();
+
+        let result = install_services(
+            &manifest,
+            &config,
+            &names(&["stale"]),
+            &mut Journal::default(),
+            &mut runner,
+        );
+
+        if cfg!(any(target_os = "linux", windows)) {
+            result.unwrap();
+        }
+        assert_eq!(runner.commands, Vec::<Vec<String>>::new());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn enables_and_starts_units() {
+        let services = [
+            Service::new("frost-wizard-test-server", "server"),
+            Service::new("frost-wizard-test-worker", "worker")
+                .start(crate::config::StartType::Manual),
+        ];
+        let mut runner = RecordingRunner::default();
+
+        activate_services(
+            InstallScope::Machine,
+            &services,
+            "/opt/test-app",
+            &[],
+            &mut runner,
+        )
+        .unwrap();
+
+        assert_eq!(
+            runner.commands,
+            commands(&[
+                &["systemctl", "daemon-reload"],
+                &[
+                    "systemctl",
+                    "enable",
+                    "--now",
+                    "frost-wizard-test-server.service"
+                ],
+                &["systemctl", "disable", "frost-wizard-test-worker.service"],
+            ])
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn user_units_use_the_user_manager() {
+        let mut runner = RecordingRunner::default();
+
+        activate_services(
+            InstallScope::User,
+            &[Service::new("frost-wizard-test-server", "server")],
+            "/home/user/.local/share/test-app",
+            &[],
+            &mut runner,
+        )
+        .unwrap();
+
+        assert_eq!(
+            runner.commands,
+            commands(&[
+                &["systemctl", "--user", "daemon-reload"],
+                &[
+                    "systemctl",
+                    "--user",
+                    "enable",
+                    "--now",
+                    "frost-wizard-test-server.service"
+                ],
+            ])
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn removes_new_units_when_starting_fails() {
+        let services = [
+            Service::new("frost-wizard-test-old", "old"),
+            Service::new("frost-wizard-test-new", "new"),
+        ];
+        let mut runner = RecordingRunner::failing(&[
+            "systemctl",
+            "enable",
+            "--now",
+            "frost-wizard-test-new.service",
+        ]);
+
+        let result = activate_services(
+            InstallScope::Machine,
+            &services,
+            "/opt/test-app",
+            &names(&["frost-wizard-test-old"]),
+            &mut runner,
+        );
+
+        assert!(result.is_err());
+        // The unit of the earlier installation stays
+        assert_eq!(
+            runner.commands,
+            commands(&[
+                &["systemctl", "daemon-reload"],
+                &[
+                    "systemctl",
+                    "enable",
+                    "--now",
+                    "frost-wizard-test-old.service"
+                ],
+                &[
+                    "systemctl",
+                    "enable",
+                    "--now",
+                    "frost-wizard-test-new.service"
+                ],
+                &[
+                    "systemctl",
+                    "disable",
+                    "--now",
+                    "frost-wizard-test-new.service"
+                ],
+                &["systemctl", "daemon-reload"],
+            ])
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn removal_continues_after_failures() {
+        let mut runner = RecordingRunner::failing(&[
+            "systemctl",
+            "--user",
+            "disable",
+            "--now",
+            "frost-wizard-test-a.service",
+        ]);
+
+        let result = remove_services(
+            InstallScope::User,
+            &names(&["frost-wizard-test-a", "frost-wizard-test-b"]),
+            &mut runner,
+        );
+
+        assert!(result.is_err());
+        assert_eq!(
+            runner.commands,
+            commands(&[
+                &[
+                    "systemctl",
+                    "--user",
+                    "disable",
+                    "--now",
+                    "frost-wizard-test-a.service"
+                ],
+                &[
+                    "systemctl",
+                    "--user",
+                    "disable",
+                    "--now",
+                    "frost-wizard-test-b.service"
+                ],
+                &["systemctl", "--user", "daemon-reload"],
+            ])
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn stops_only_running_services() {
+        let mut runner =
+            RecordingRunner::failing(&["systemctl", "is-active", "--quiet", "a.service"]);
+
+        let stopped = stop_services(InstallScope::Machine, &names(&["a", "b"]), &mut runner);
+
+        assert_eq!(stopped, names(&["b"]));
+        assert_eq!(
+            runner.commands,
+            commands(&[
+                &["systemctl", "is-active", "--quiet", "a.service"],
+                &["systemctl", "is-active", "--quiet", "b.service"],
+                &["systemctl", "stop", "b.service"],
+            ])
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn rollback_starts_stopped_services_again() {
+        let dir = crate::test_util::TempDir::new();
+        let mut runner = RecordingRunner::failing(&["systemctl", "--user", "stop", "b.service"]);
+        let mut journal = Journal::default();
+        journal.create_dir_all(&dir.path().join("app")).unwrap();
+
+        let stopped = stop_services(InstallScope::User, &names(&["a", "b"]), &mut runner);
+        journal.track_stopped_services(InstallScope::User, stopped);
+        let mut runner = RecordingRunner::default();
+        journal.rollback(&mut runner);
+
+        // The service that couldn't be stopped is still running
+        assert_eq!(
+            runner.commands,
+            commands(&[&["systemctl", "--user", "start", "a.service"]])
+        );
+        assert!(!dir.path().join("app").exists());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn rollback_starts_stopped_services_again() {
+        let mut runner = RecordingRunner::failing(&["net.exe", "stop", "a"]);
+        let mut journal = Journal::default();
+
+        let stopped = stop_services(InstallScope::Machine, &names(&["a", "b"]), &mut runner);
+        journal.track_stopped_services(InstallScope::Machine, stopped);
+        let mut runner = RecordingRunner::default();
+        journal.rollback(&mut runner);
+
+        assert_eq!(runner.commands, commands(&[&["net.exe", "start", "b"]]));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn registers_and_starts_services() {
+        let services = [Service::new("server", "bin/server.exe").arg("--port=80")];
+        let mut runner = RecordingRunner::default();
+
+        activate_services(
+            InstallScope::Machine,
+            &services,
+            "C:\\Program Files\\App",
+            &[],
+            &mut runner,
+        )
+        .unwrap();
+
+        assert_eq!(
+            runner.commands,
+            commands(&[
+                &[
+                    "sc.exe",
+                    "create",
+                    "server",
+                    "binPath=",
+                    "\"C:\\Program Files\\App\\bin\\server.exe\" \"--port=80\"",
+                    "start=",
+                    "auto",
+                    "DisplayName=",
+                    "server",
+                    "obj=",
+                    "LocalSystem",
+                ],
+                &["sc.exe", "description", "server", ""],
+                &[
+                    "sc.exe",
+                    "failure",
+                    "server",
+                    "reset=",
+                    "86400",
+                    "actions=",
+                    "restart/5000/restart/5000/restart/5000",
+                ],
+                &["sc.exe", "failureflag", "server", "1"],
+                &["net.exe", "start", "server"],
+            ])
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn deletes_new_services_when_starting_fails() {
+        let services = [
+            Service::new("old", "old.exe").restart(crate::config::RestartPolicy::Never),
+            Service::new("new", "new.exe").restart(crate::config::RestartPolicy::Never),
+        ];
+        let mut runner = RecordingRunner::failing(&["net.exe", "start", "new"]);
+
+        let result = activate_services(
+            InstallScope::Machine,
+            &services,
+            "C:\\App",
+            &names(&["old"]),
+            &mut runner,
+        );
+
+        assert!(result.is_err());
+        let registered = |verb: &'static str, name: &'static str| -> Vec<&'static str> {
+            let bin_path = if name == "old" {
+                "\"C:\\App\\old.exe\""
+            } else {
+                "\"C:\\App\\new.exe\""
+            };
+            vec![
+                "sc.exe",
+                verb,
+                name,
+                "binPath=",
+                bin_path,
+                "start=",
+                "auto",
+                "DisplayName=",
+                name,
+                "obj=",
+                "LocalSystem",
+            ]
+        };
+        let configured = |name: &'static str| {
+            [
+                vec!["sc.exe", "description", name, ""],
+                vec!["sc.exe", "failure", name, "reset=", "86400", "actions=", ""],
+                vec!["sc.exe", "failureflag", name, "0"],
+                vec!["net.exe", "start", name],
+            ]
+        };
+        let mut expected = vec![registered("config", "old")];
+        expected.extend(configured("old"));
+        expected.push(registered("create", "new"));
+        expected.extend(configured("new"));
+        // The service of the earlier installation stays
+        expected.push(vec!["net.exe", "stop", "new"]);
+        expected.push(vec!["sc.exe", "delete", "new"]);
+        assert_eq!(
+            runner.commands,
+            expected
+                .iter()
+                .map(|command| strings(command))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{
+    config::{RestartPolicy, Service, StartType},
+    installer::uninstall_entry::quote,
+};
+
+/// Configures services in the service control manager
+pub(super) const SC: &str = "sc.exe";
+/// Starts and stops services, unlike `sc.exe` it waits until they are done
+pub(super) const NET: &str = "net.exe";
+
+/// Time after which the failure count of a service is reset, in seconds
+const FAILURE_RESET_SECS: u32 = 24 * 60 * 60;
+/// Delay before a failed service is restarted, in milliseconds
+const RESTART_DELAY_MILLIS: u32 = 5000;
+
+/// The commands that register a service installed in `install_path` and start it if it starts automatically.
+///
+/// A service that `exists` already is reconfigured instead, it has to be stopped beforehand.
+pub(super) fn install_commands(
+    service: &Service,
+    install_path: &str,
+    exists: bool,
+) -> Vec<(&'static str, Vec<String>)> {
+    let install_path = install_path.trim_end_matches('\\');
+    let program = format!("{install_path}\\{}", service.program.replace('/', "\\"));
+    let mut bin_path = quote(&program);
+    for arg in &service.args {
+        bin_path.push(' ');
+        bin_path.push_str(&quote(arg));
+    }
+    let start = match service.start {
+        StartType::Automatic => "auto",
+        StartType::Manual => "demand",
+        StartType::Disabled => "disabled",
+    };
+    let display_name = service.display_name.as_ref().unwrap_or(&service.name);
+
+    // The `option=` and its value are separate arguments
+    let register = vec![
+        if exists { "config" } else { "create" }.to_string(),
+        service.name.clone(),
+        "binPath=".to_string(),
+        bin_path,
+        "start=".to_string(),
+        start.to_string(),
+        "DisplayName=".to_string(),
+        display_name.clone(),
+        "obj=".to_string(),
+        service
+            .user
+            .clone()
+            .unwrap_or_else(|| "LocalSystem".to_string()),
+    ];
+    let mut commands = vec![(SC, register)];
+    commands.push((
+        SC,
+        vec![
+            "description".to_string(),
+            service.name.clone(),
+            service.description.clone().unwrap_or_default(),
+        ],
+    ));
+
+    let actions = match service.restart {
+        RestartPolicy::Never => String::new(),
+        RestartPolicy::OnFailure | RestartPolicy::Always => {
+            vec![format!("restart/{RESTART_DELAY_MILLIS}"); 3].join("/")
+        }
+    };
+    commands.push((
+        SC,
+        vec![
+            "failure".to_string(),
+            service.name.clone(),
+            "reset=".to_string(),
+            FAILURE_RESET_SECS.to_string(),
+            "actions=".to_string(),
+            actions,
+        ],
+    ));
+    // Also counts exiting with an error as failure, not just crashing
+    let failure_flag = if service.restart == RestartPolicy::Never {
+        "0"
+    } else {
+        "1"
+    };
+    commands.push((
+        SC,
+        vec![
+            "failureflag".to_string(),
+            service.name.clone(),
+            failure_flag.to_string(),
+        ],
+    ));
+
+    if service.start == StartType::Automatic {
+        commands.push((NET, start_args(&service.name)));
+    }
+    commands
+}
+
+pub(super) fn start_args(name: &str) -> Vec<String> {
+    vec!["start".to_string(), name.to_string()]
+}
+
+pub(super) fn stop_args(name: &str) -> Vec<String> {
+    vec!["stop".to_string(), name.to_string()]
+}
+
+pub(super) fn delete_args(name: &str) -> Vec<String> {
+    vec!["delete".to_string(), name.to_string()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn creates_and_starts_new_service() {
+        let service = Service::new("frost-server", "bin/server.exe")
+            .display_name("Frost Server")
+            .description("Serves frost")
+            .args(["--data", "C:\\Data\\"])
+            .user("NT AUTHORITY\\LocalService")
+            .restart(RestartPolicy::Always);
+
+        let commands = install_commands(&service, "C:\\Program Files\\Frost\\", false);
+
+        assert_eq!(
+            commands,
+            [
+                (
+                    SC,
+                    strings(&[
+                        "create",
+                        "frost-server",
+                        "binPath=",
+                        "\"C:\\Program Files\\Frost\\bin\\server.exe\" \"--data\" \"C:\\Data\\\\\"",
+                        "start=",
+                        "auto",
+                        "DisplayName=",
+                        "Frost Server",
+                        "obj=",
+                        "NT AUTHORITY\\LocalService",
+                    ])
+                ),
+                (
+                    SC,
+                    strings(&["description", "frost-server", "Serves frost"])
+                ),
+                (
+                    SC,
+                    strings(&[
+                        "failure",
+                        "frost-server",
+                        "reset=",
+                        "86400",
+                        "actions=",
+                        "restart/5000/restart/5000/restart/5000",
+                    ])
+                ),
+                (SC, strings(&["failureflag", "frost-server", "1"])),
+                (NET, strings(&["start", "frost-server"])),
+            ]
+        );
+    }
+
+    #[test]
+    fn reconfigures_existing_service_without_starting_it() {
+        let service = Service::new("frost-server", "server.exe")
+            .restart(RestartPolicy::Never)
+            .start(StartType::Manual);
+
+        let commands = install_commands(&service, "C:\\Frost", true);
+
+        assert_eq!(
+            commands,
+            [
+                (
+                    SC,
+                    strings(&[
+                        "config",
+                        "frost-server",
+                        "binPath=",
+                        "\"C:\\Frost\\server.exe\"",
+                        "start=",
+                        "demand",
+                        "DisplayName=",
+                        "frost-server",
+                        "obj=",
+                        "LocalSystem",
+                    ])
+                ),
+                (SC, strings(&["description", "frost-server", ""])),
+                (
+                    SC,
+                    strings(&["failure", "frost-server", "reset=", "86400", "actions=", "",])
+                ),
+                (SC, strings(&["failureflag", "frost-server", "0"])),
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_and_deletes_services() {
+        assert_eq!(stop_args("frost-server"), ["stop", "frost-server"]);
+        assert_eq!(delete_args("frost-server"), ["delete", "frost-server"]);
+    }
+}
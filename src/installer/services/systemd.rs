@@ -0,0 +1,178 @@
+use std::{fmt::Write, path::Path, path::PathBuf};
+
+use crate::config::{InstallScope, RestartPolicy, Service, StartType};
+
+pub(super) const SYSTEMCTL: &str = "systemctl";
+
+/// Directory the units of a scope are installed into
+pub(super) fn unit_dir(scope: InstallScope) -> PathBuf {
+    match scope {
+        InstallScope::Machine => PathBuf::from("/etc/systemd/system"),
+        InstallScope::User => std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .unwrap_or_else(|| std::env::home_dir().unwrap_or_default().join(".config"))
+            .join("systemd")
+            .join("user"),
+    }
+}
+
+pub(super) fn unit_name(service_name: &str) -> String {
+    format!("{service_name}.service")
+}
+
+/// Arguments of `systemctl`, talking to the user's service manager for per-user installations
+pub(super) fn systemctl_args(scope: InstallScope, args: &[&str]) -> Vec<String> {
+    let user = (scope == InstallScope::User).then_some("--user");
+    user.into_iter()
+        .chain(args.iter().copied())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The `systemctl` calls that activate freshly written units
+pub(super) fn install_commands(scope: InstallScope, services: &[Service]) -> Vec<Vec<String>> {
+    let mut commands = vec![systemctl_args(scope, &["daemon-reload"])];
+    for service in services {
+        let unit = unit_name(&service.name);
+        let args = match service.start {
+            StartType::Automatic => systemctl_args(scope, &["enable", "--now", &unit]),
+            // An earlier version may have enabled the service
+            StartType::Manual | StartType::Disabled => systemctl_args(scope, &["disable", &unit]),
+        };
+        commands.push(args);
+    }
+    commands
+}
+
+/// Generates the unit file of a service installed in `install_path`
+pub(super) fn unit_file(service: &Service, install_path: &Path, scope: InstallScope) -> String {
+    let description = service
+        .description
+        .as_deref()
+        .or(service.display_name.as_deref())
+        .unwrap_or(&service.name);
+    let program = install_path.join(&service.program);
+    let mut exec_start = quote_argument(&program.to_string_lossy());
+    for arg in &service.args {
+        exec_start.push(' ');
+        exec_start.push_str(&quote_argument(arg));
+    }
+    let restart = match service.restart {
+        RestartPolicy::Never => "no",
+        RestartPolicy::OnFailure => "on-failure",
+        RestartPolicy::Always => "always",
+    };
+
+    let mut unit = String::from("[Unit]\n");
+    let _ = writeln!(unit, "Description={}", escape_specifiers(description));
+    unit.push_str("After=network.target\n\n[Service]\nType=simple\n");
+    let _ = writeln!(unit, "ExecStart={exec_start}");
+    let _ = writeln!(
+        unit,
+        "WorkingDirectory={}",
+        escape_specifiers(&install_path.to_string_lossy())
+    );
+    // User units always run as the user who owns them
+    if scope == InstallScope::Machine
+        && let Some(user) = &service.user
+    {
+        let _ = writeln!(unit, "User={}", escape_specifiers(user));
+    }
+    let _ = writeln!(unit, "Restart={restart}");
+    unit.push_str("\n[Install]\n");
+    let target = match scope {
+        InstallScope::Machine => "multi-user.target",
+        InstallScope::User => "default.target",
+    };
+    let _ = writeln!(unit, "WantedBy={target}");
+    unit
+}
+
+/// Quotes an argument of `ExecStart`, which systemd splits like a shell would and expands `%` specifiers and `$` variables in
+fn quote_argument(arg: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in arg.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '%' => quoted.push_str("%%"),
+            '$' => quoted.push_str("$$"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Escapes `%` specifiers in a value, which can't span lines either
+fn escape_specifiers(value: &str) -> String {
+    value.replace(['\n', '\r'], " ").replace('%', "%%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_system_unit() {
+        let service = Service::new("frost-server", "bin/server")
+            .display_name("Frost Server")
+            .args(["--name", "100% \"frost\" $HOME"])
+            .user("frost")
+            .restart(RestartPolicy::Always);
+
+        assert_eq!(
+            unit_file(&service, Path::new("/opt/frost"), InstallScope::Machine),
+            "[Unit]\n\
+             Description=Frost Server\n\
+             After=network.target\n\
+             \n\
+             [Service]\n\
+             Type=simple\n\
+             ExecStart=\"/opt/frost/bin/server\" \"--name\" \"100%% \\\"frost\\\" $$HOME\"\n\
+             WorkingDirectory=/opt/frost\n\
+             User=frost\n\
+             Restart=always\n\
+             \n\
+             [Install]\n\
+             WantedBy=multi-user.target\n"
+        );
+    }
+
+    #[test]
+    fn user_units_run_as_their_owner() {
+        let service = Service::new("frost-server", "server")
+            .description("Serves 100%\nof frost")
+            .user("frost")
+            .restart(RestartPolicy::Never);
+
+        let unit = unit_file(&service, Path::new("/home/me/frost"), InstallScope::User);
+        assert!(unit.contains("Description=Serves 100%% of frost\n"));
+        assert!(!unit.contains("User="));
+        assert!(unit.contains("Restart=no\n"));
+        assert!(unit.ends_with("WantedBy=default.target\n"));
+    }
+
+    #[test]
+    fn disables_units_that_dont_start_automatically() {
+        let services = [
+            Service::new("a", "a"),
+            Service::new("b", "b").start(StartType::Manual),
+            Service::new("c", "c").start(StartType::Disabled),
+        ];
+
+        assert_eq!(
+            install_commands(InstallScope::User, &services),
+            [
+                vec!["--user", "daemon-reload"],
+                vec!["--user", "enable", "--now", "a.service"],
+                vec!["--user", "disable", "b.service"],
+                vec!["--user", "disable", "c.service"],
+            ]
+        );
+    }
+}
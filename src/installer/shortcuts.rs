@@ -0,0 +1,209 @@
+#[cfg(windows)]
+use std::{fs, io};
+
+use lnk_rs::Lnk;
+
+use crate::config::AppManifest;
+#[cfg(windows)]
+use crate::{
+    config::{InstallConfig, InstallScope},
+    installer::journal::Journal,
+};
+
+/// Where a shortcut is placed
+#[cfg(windows)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum ShortcutLocation {
+    StartMenu,
+    Desktop,
+}
+
+/// Creates the shortcuts the user asked for.
+///
+/// Per-machine installations put them into the folders shared by all users, per-user installations into the user's own.
+/// The shortcuts are recorded by the journal, so uninstalling removes them.
+#[cfg(windows)]
+pub(super) fn create_shortcuts(
+    manifest: &AppManifest,
+    config: &InstallConfig,
+    journal: &mut Journal,
+) -> io::Result<()> {
+    let contents = shortcut_bytes(manifest, &config.install_path.to_string_lossy());
+
+    for path in shortcut_paths(manifest, config)? {
+        let path = config.staged(&path);
+        if let Some(dir) = path.parent() {
+            journal.create_dir_all(dir)?;
+        }
+        journal.track_file(&path);
+        fs::write(&path, &contents)?;
+    }
+    Ok(())
+}
+
+/// Where [`create_shortcuts`] puts the shortcuts the user asked for
+#[cfg(windows)]
+pub(super) fn shortcut_paths(
+    manifest: &AppManifest,
+    config: &InstallConfig,
+) -> io::Result<Vec<std::path::PathBuf>> {
+    let locations = [
+        (
+            config.create_start_menu_shortcut,
+            ShortcutLocation::StartMenu,
+        ),
+        (config.create_desktop_shortcut, ShortcutLocation::Desktop),
+    ];
+
+    locations
+        .into_iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, location)| {
+            let dir = crate::windows::known_folder(location.folder_id(config.scope))?;
+            Ok(dir.join(shortcut_file_name(manifest)))
+        })
+        .collect()
+}
+
+#[cfg(windows)]
+impl ShortcutLocation {
+    fn folder_id(self, scope: InstallScope) -> &'static windows::core::GUID {
+        use windows::Win32::UI::Shell::{
+            FOLDERID_CommonPrograms, FOLDERID_Desktop, FOLDERID_Programs, FOLDERID_PublicDesktop,
+        };
+
+        match (self, scope) {
+            (ShortcutLocation::StartMenu, InstallScope::Machine) => &FOLDERID_CommonPrograms,
+            (ShortcutLocation::StartMenu, InstallScope::User) => &FOLDERID_Programs,
+            (ShortcutLocation::Desktop, InstallScope::Machine) => &FOLDERID_PublicDesktop,
+            (ShortcutLocation::Desktop, InstallScope::User) => &FOLDERID_Desktop,
+        }
+    }
+}
+
+/// Contents of a `.lnk` file starting the application installed in `install_path`.
+///
+/// The path is passed as a string, so shortcuts with Windows paths can be generated on any platform.
+pub(super) fn shortcut_bytes(manifest: &AppManifest, install_path: &str) -> Vec<u8> {
+    let install_path = install_path.trim_end_matches('\\');
+    let target = format!("{install_path}\\{}", manifest.bin_name);
+
+    Lnk::new(target.clone())
+        .name(manifest.friendly_name.clone())
+        .working_dir(install_path)
+        .icon(target, 0)
+        .app_user_model_id(app_user_model_id(manifest))
+        .to_bytes()
+        .expect("Shortcuts only contain data lnk-rs can write")
+}
+
+/// File name of the shortcut, the application name without characters Windows doesn't allow in file names
+pub(super) fn shortcut_file_name(manifest: &AppManifest) -> String {
+    let name = manifest
+        .friendly_name
+        .chars()
+        .filter(|c| {
+            !c.is_control() && !matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*')
+        })
+        .collect::<String>();
+    // Windows drops trailing dots and spaces, which would make the name differ from the created file
+    let name = name.trim_end_matches(['.', ' ']);
+    if name.is_empty() {
+        format!("{}.lnk", bin_stem(manifest))
+    } else {
+        format!("{name}.lnk")
+    }
+}
+
+/// Groups the windows of the application with its shortcut on the taskbar, e.g. `Publisher.App`.
+///
+/// IDs consist of dot separated parts without spaces and are at most 128 characters long.
+pub(super) fn app_user_model_id(manifest: &AppManifest) -> String {
+    let part = |value: &str| {
+        value
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect::<String>()
+    };
+
+    let mut id = manifest
+        .publisher
+        .as_deref()
+        .map(part)
+        .filter(|publisher| !publisher.is_empty())
+        .map(|publisher| format!("{publisher}."))
+        .unwrap_or_default();
+    id.push_str(&part(bin_stem(manifest)));
+    id.truncate(128);
+    id
+}
+
+/// `bin_name` without the `.exe` extension
+fn bin_stem(manifest: &AppManifest) -> &str {
+    let bin_name = manifest.bin_name.as_str();
+    bin_name.strip_suffix(".exe").unwrap_or(bin_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::manifest;
+
+    #[test]
+    fn shortcut_starts_application() {
+        let manifest = manifest().bin_name("app.exe").publisher("Frost Corp.");
+
+        let bytes = shortcut_bytes(&manifest, "C:\\Program Files\\Test App\\");
+        let lnk = Lnk::parse(&mut bytes.as_slice()).unwrap();
+        assert_eq!(
+            lnk.link_info.unwrap().local_base_path.as_deref(),
+            Some("C:\\Program Files\\Test App\\app.exe")
+        );
+        assert_eq!(lnk.name.as_deref(), Some("Test App"));
+        assert_eq!(
+            lnk.working_dir.as_deref(),
+            Some("C:\\Program Files\\Test App")
+        );
+        assert_eq!(lnk.arguments, None);
+        assert_eq!(
+            lnk.icon_location.as_deref(),
+            Some("C:\\Program Files\\Test App\\app.exe")
+        );
+        assert_eq!(lnk.icon_index, 0);
+        assert_eq!(
+            lnk.block_data
+                .property_store
+                .app_user_model
+                .unwrap()
+                .id
+                .as_deref(),
+            Some("FrostCorp.app")
+        );
+    }
+
+    #[test]
+    fn file_name_is_valid_on_windows() {
+        assert_eq!(shortcut_file_name(&manifest()), "Test App.lnk");
+        assert_eq!(
+            shortcut_file_name(&manifest().friendly_name("A/B: \"C\"?.. ")),
+            "AB C.lnk"
+        );
+        assert_eq!(
+            shortcut_file_name(&manifest().friendly_name("<*>").bin_name("app.exe")),
+            "app.lnk"
+        );
+    }
+
+    #[test]
+    fn app_user_model_id_has_no_spaces() {
+        assert_eq!(app_user_model_id(&manifest()), "test-app");
+        assert_eq!(
+            app_user_model_id(&manifest().publisher("Frost Corp.").bin_name("my app.exe")),
+            "FrostCorp.myapp"
+        );
+        assert_eq!(
+            app_user_model_id(&manifest().publisher("...").bin_name("a".repeat(200))).len(),
+            128
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use crate::config::{AppManifest, InstallConfig};
+
+/// File extension marking payload files as templates. It is removed when the rendered file is written.
+const TEMPLATE_EXTENSION: &str = "tmpl";
+
+/// Renders payload files marked as templates, replacing `{name}` placeholders with install time values.
+///
+/// Placeholders that don't name a known variable are left untouched, so braces in e.g. JSON files don't need escaping.
+pub(super) struct Templates {
+    marked: Vec<PathBuf>,
+    variables: BTreeMap<String, String>,
+}
+
+impl Templates {
+    pub fn new(manifest: &AppManifest, config: &InstallConfig) -> Self {
+        let mut variables = BTreeMap::new();
+        variables.insert(
+            "install_path".to_string(),
+            config.install_path.display().to_string(),
+        );
+        variables.insert("version".to_string(), manifest.version.clone());
+        variables.insert("bin_name".to_string(), manifest.bin_name.clone());
+        variables.insert("friendly_name".to_string(), manifest.friendly_name.clone());
+        if let Some(publisher) = &manifest.publisher {
+            variables.insert("publisher".to_string(), publisher.clone());
+        }
+        if let Some(user) = current_user() {
+            variables.insert("user".to_string(), user);
+        }
+        // Values collected by the wizard take precedence
+        variables.extend(config.variables.clone());
+
+        Self {
+            marked: manifest.templates.iter().map(PathBuf::from).collect(),
+            variables,
+        }
+    }
+
+    /// Returns the path the rendered file should be written to, if the file at `path` is a template
+    pub fn target(&self, path: &Path) -> Option<PathBuf> {
+        if path.extension() == Some(OsStr::new(TEMPLATE_EXTENSION)) {
+            Some(path.with_extension(""))
+        } else if self.marked.iter().any(|marked| marked == path) {
+            Some(path.to_path_buf())
+        } else {
+            None
+        }
+    }
+
+    pub fn render(&self, input: &[u8]) -> Vec<u8> {
+        let mut output = Vec::with_capacity(input.len());
+        let mut rest = input;
+
+        while let Some(start) = rest.iter().position(|&byte| byte == b'{') {
+            output.extend_from_slice(&rest[..start]);
+            rest = &rest[start..];
+
+            let name_length = rest[1..]
+                .iter()
+                .take_while(|byte| byte.is_ascii_alphanumeric() || **byte == b'_')
+                .count();
+            let value = (rest.get(name_length + 1) == Some(&b'}'))
+                .then(|| std::str::from_utf8(&rest[1..name_length + 1]).ok())
+                .flatten()
+                .and_then(|name| self.variables.get(name));
+
+            match value {
+                Some(value) => {
+                    output.extend_from_slice(value.as_bytes());
+                    rest = &rest[name_length + 2..];
+                }
+                None => {
+                    output.push(b'{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+
+        output.extend_from_slice(rest);
+        output
+    }
+}
+
+fn current_user() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{install_config, manifest};
+
+    fn templates(variables: &[(&str, &str)]) -> Templates {
+        let mut config = install_config(Path::new("/opt/test-app"));
+        config.variables = variables
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Templates::new(&manifest().template("etc/marked.conf"), &config)
+    }
+
+    fn render(templates: &Templates, input: &str) -> String {
+        String::from_utf8(templates.render(input.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn strips_template_extension() {
+        let templates = templates(&[]);
+
+        assert_eq!(
+            templates.target(Path::new("etc/app.conf.tmpl")),
+            Some(PathBuf::from("etc/app.conf"))
+        );
+        // Marked templates keep their name
+        assert_eq!(
+            templates.target(Path::new("etc/marked.conf")),
+            Some(PathBuf::from("etc/marked.conf"))
+        );
+        assert_eq!(templates.target(Path::new("etc/app.conf")), None);
+        assert_eq!(templates.target(Path::new("etc/tmpl")), None);
+    }
+
+    #[test]
+    fn replaces_known_placeholders() {
+        let templates = templates(&[]);
+
+        assert_eq!(
+            render(
+                &templates,
+                "{friendly_name} {version} in {install_path}/{bin_name}"
+            ),
+            "Test App 1.0.0 in /opt/test-app/test-app"
+        );
+    }
+
+    #[test]
+    fn leaves_unknown_placeholders_intact() {
+        let templates = templates(&[]);
+
+        assert_eq!(
+            render(
+                &templates,
+                r#"{"name": "{friendly_name}", "port": {port}, "path": "{install-path}"} {"#
+            ),
+            r#"{"name": "Test App", "port": {port}, "path": "{install-path}"} {"#
+        );
+    }
+
+    #[test]
+    fn wizard_variables_take_precedence() {
+        let templates = templates(&[("version", "2.0-beta"), ("port", "8080")]);
+
+        assert_eq!(
+            render(&templates, "{version} {port} {bin_name}"),
+            "2.0-beta 8080 test-app"
+        );
+    }
+}
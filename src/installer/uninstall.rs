@@ -0,0 +1,150 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::installer::{
+    log::kept_log_path,
+    receipt::{Receipt, ReceiptError},
+    services,
+};
+#[cfg(windows)]
+use crate::registry::RegistryBackend;
+
+#[derive(Debug, thiserror::Error)]
+pub enum UninstallError {
+    #[error("No installation found in {0:?}")]
+    NotInstalled(PathBuf),
+    #[error("Failed to read the install receipt:\n{0}")]
+    ReadReceipt(ReceiptError),
+    #[error("Failed to remove {path:?}:\n{error}")]
+    Remove { path: PathBuf, error: io::Error },
+    #[error("Failed to remove services:\n{0}")]
+    RemoveServices(io::Error),
+    #[cfg(windows)]
+    #[error("Failed to remove the application from PATH:\n{0}")]
+    RemoveFromPath(io::Error),
+    #[cfg(windows)]
+    #[error("Failed to unregister file types and URL schemes:\n{0}")]
+    RemoveAssociations(io::Error),
+    #[cfg(windows)]
+    #[error("Failed to remove the application from the list of installed programs:\n{0}")]
+    RemoveUninstallEntry(io::Error),
+}
+
+/// Removes the installation in `install_path`, using the receipt it left behind.
+///
+/// Only files and directories the installations created are removed, directories only if they are empty afterwards.
+/// Files the user added are therefore kept. Removal continues past failures and the first one is returned.
+pub(super) fn uninstall(install_path: &Path) -> Result<(), UninstallError> {
+    let receipt = Receipt::load(install_path)
+        .map_err(UninstallError::ReadReceipt)?
+        .ok_or_else(|| UninstallError::NotInstalled(install_path.to_path_buf()))?;
+
+    // Stopped first, so their binaries can be deleted
+    let mut first_error = services::remove_services(
+        receipt.scope,
+        &receipt.services,
+        &mut services::SystemCommandRunner,
+    )
+    .err()
+    .map(UninstallError::RemoveServices);
+
+    // A running program can't delete itself on Windows, which is the case when the installed uninstaller runs
+    #[cfg(windows)]
+    let running = std::env::current_exe().and_then(fs::canonicalize).ok();
+    #[cfg(windows)]
+    let mut deferred = Vec::new();
+
+    let receipt_path = Receipt::path(install_path);
+    let log_path = kept_log_path(install_path);
+    let paths = [receipt_path.as_path(), log_path.as_path()]
+        .into_iter()
+        .chain(receipt.created.iter().rev().map(PathBuf::as_path));
+    for path in paths {
+        #[cfg(windows)]
+        if running.is_some() && fs::canonicalize(path).ok() == running {
+            deferred.push(path.to_path_buf());
+            continue;
+        }
+        #[cfg(windows)]
+        if let Some(exe) = deferred.first()
+            && exe.starts_with(path)
+        {
+            deferred.push(path.to_path_buf());
+            continue;
+        }
+
+        if let Err(error) = remove(path)
+            && first_error.is_none()
+        {
+            first_error = Some(UninstallError::Remove {
+                path: path.to_path_buf(),
+                error,
+            });
+        }
+    }
+
+    #[cfg(windows)]
+    if !deferred.is_empty() {
+        crate::windows::delete_after_exit(&deferred);
+    }
+
+    #[cfg(windows)]
+    if let Some(key) = &receipt.uninstall_key
+        && let Err(error) = crate::registry::SystemRegistry
+            .delete_key(crate::registry::Hive::for_scope(receipt.scope), key)
+        && first_error.is_none()
+    {
+        first_error = Some(UninstallError::RemoveUninstallEntry(error));
+    }
+
+    #[cfg(windows)]
+    if receipt.associations != Default::default() {
+        let result = crate::installer::associations::unregister(
+            &mut crate::registry::SystemRegistry,
+            crate::registry::Hive::for_scope(receipt.scope),
+            &receipt.associations,
+        );
+        crate::windows::notify_association_change();
+        if let Err(error) = result
+            && first_error.is_none()
+        {
+            first_error = Some(UninstallError::RemoveAssociations(error));
+        }
+    }
+
+    #[cfg(windows)]
+    if let Some(dir) = &receipt.path_entry
+        && let Err(error) = crate::installer::env_path::remove_from_path(receipt.scope, dir)
+        && first_error.is_none()
+    {
+        first_error = Some(UninstallError::RemoveFromPath(error));
+    }
+
+    #[cfg(target_os = "linux")]
+    if let Some(data_dir) = &receipt.desktop_data_dir {
+        crate::installer::desktop::refresh_caches(data_dir);
+    }
+
+    match first_error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    let result = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir(path),
+        Ok(_) => fs::remove_file(path),
+        // Already removed
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+
+    match result {
+        // The directory still contains files the user created
+        Err(err) if err.kind() == io::ErrorKind::DirectoryNotEmpty => Ok(()),
+        result => result,
+    }
+}
@@ -0,0 +1,333 @@
+#[cfg(windows)]
+use std::{fs, path::PathBuf};
+#[cfg(any(windows, test))]
+use std::{io, path::Path};
+
+#[cfg(any(windows, test))]
+use clap::ValueEnum;
+use sha2::{Digest, Sha256};
+#[cfg(any(windows, test))]
+use time::Date;
+
+use crate::config::AppManifest;
+#[cfg(windows)]
+use crate::installer::journal::Journal;
+#[cfg(any(windows, test))]
+use crate::{
+    config::InstallScope,
+    registry::{Hive, RegistryBackend, RegistryValue},
+};
+
+#[cfg(any(windows, test))]
+/// Parent key of the entries listed in "Apps & features"
+const UNINSTALL_KEY: &str = "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall";
+
+#[cfg(any(windows, test))]
+/// File name of the uninstaller inside the install directory
+const UNINSTALLER_NAME: &str = "uninstall.exe";
+
+#[cfg(any(windows, test))]
+/// The entry of an installation in the list of installed programs
+pub(super) struct UninstallEntry<'a> {
+    pub manifest: &'a AppManifest,
+    pub scope: InstallScope,
+    pub install_path: &'a Path,
+    pub uninstaller: &'a Path,
+    /// Installed size in bytes
+    pub size: u64,
+    pub install_date: Date,
+}
+
+#[cfg(any(windows, test))]
+impl UninstallEntry<'_> {
+    /// Registry key of the entry below its hive
+    pub fn key(&self) -> String {
+        uninstall_key(self.manifest)
+    }
+
+    pub fn values(&self) -> Vec<(&'static str, RegistryValue)> {
+        let manifest = self.manifest;
+        let install_path = self.install_path.to_string_lossy().into_owned();
+        let uninstaller = quote(&self.uninstaller.to_string_lossy());
+        let scope = self
+            .scope
+            .to_possible_value()
+            .expect("No variant is skipped");
+        let uninstall_string = format!(
+            "{uninstaller} --uninstall --scope {} --install-path {}",
+            scope.get_name(),
+            quote(&install_path)
+        );
+
+        let mut values = vec![
+            (
+                "DisplayName",
+                RegistryValue::String(manifest.friendly_name.clone()),
+            ),
+            (
+                "DisplayVersion",
+                RegistryValue::String(manifest.version.clone()),
+            ),
+            (
+                "DisplayIcon",
+                RegistryValue::String(format!(
+                    "{},0",
+                    self.install_path.join(&manifest.bin_name).display()
+                )),
+            ),
+            ("InstallLocation", RegistryValue::String(install_path)),
+            (
+                "InstallDate",
+                RegistryValue::String(format!(
+                    "{:04}{:02}{:02}",
+                    self.install_date.year(),
+                    u8::from(self.install_date.month()),
+                    self.install_date.day()
+                )),
+            ),
+            (
+                "QuietUninstallString",
+                RegistryValue::String(format!("{uninstall_string} --silent")),
+            ),
+            ("UninstallString", RegistryValue::String(uninstall_string)),
+            (
+                "EstimatedSize",
+                RegistryValue::U32((self.size / 1024).try_into().unwrap_or(u32::MAX)),
+            ),
+            // The installer can neither change nor repair an installation
+            ("NoModify", RegistryValue::U32(1)),
+            ("NoRepair", RegistryValue::U32(1)),
+        ];
+        if let Some(publisher) = &manifest.publisher {
+            values.push(("Publisher", RegistryValue::String(publisher.clone())));
+        }
+        if let Some(url) = &manifest.url {
+            values.push(("URLInfoAbout", RegistryValue::String(url.clone())));
+        }
+        values
+    }
+
+    /// Writes the entry, replacing an entry of an earlier version.
+    ///
+    /// Returns the key that was written.
+    pub fn write(&self, registry: &mut impl RegistryBackend) -> io::Result<String> {
+        let hive = Hive::for_scope(self.scope);
+        let key = self.key();
+
+        // Earlier versions of the installer named the key after the application
+        registry.delete_key(hive, &legacy_uninstall_key(self.manifest))?;
+        // Values an older version set shouldn't linger
+        registry.delete_key(hive, &key)?;
+
+        for (name, value) in self.values() {
+            registry.set_value(hive, &key, name, value)?;
+        }
+        Ok(key)
+    }
+}
+
+#[cfg(windows)]
+/// Copies the running installer into the install directory, where the uninstall entry can start it
+pub(super) fn install_uninstaller(install_path: &Path, journal: &mut Journal) -> io::Result<()> {
+    let uninstaller = uninstaller_path(install_path);
+    journal.track_file(&uninstaller);
+    fs::copy(std::env::current_exe()?, &uninstaller)?;
+    Ok(())
+}
+
+#[cfg(windows)]
+/// Where [`install_uninstaller`] puts the uninstaller
+pub(super) fn uninstaller_path(install_path: &Path) -> PathBuf {
+    install_path.join(UNINSTALLER_NAME)
+}
+
+/// The product GUID from the manifest, or one derived from the publisher and `bin_name`
+pub(super) fn product_guid(manifest: &AppManifest) -> String {
+    if let Some(guid) = &manifest.product_guid {
+        let guid = guid.trim_matches(['{', '}']).to_uppercase();
+        return format!("{{{guid}}}");
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(manifest.publisher.as_deref().unwrap_or_default());
+    hasher.update([0]);
+    hasher.update(&manifest.bin_name);
+    let hash = hasher.finalize();
+
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&hash[..16]);
+    // Mark it as a name based UUID, like version 5 UUIDs
+    bytes[6] = (bytes[6] & 0x0f) | 0x50;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex = bytes
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<String>();
+    format!(
+        "{{{}-{}-{}-{}-{}}}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+#[cfg(any(windows, test))]
+pub(super) fn uninstall_key(manifest: &AppManifest) -> String {
+    format!("{UNINSTALL_KEY}\\{}", product_guid(manifest))
+}
+
+#[cfg(any(windows, test))]
+/// Key used before entries were identified by the product GUID
+fn legacy_uninstall_key(manifest: &AppManifest) -> String {
+    let name_for_path = manifest
+        .friendly_name
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect::<String>();
+    format!("{UNINSTALL_KEY}\\{name_for_path}")
+}
+
+/// Quotes an argument for the command line, trailing backslashes are doubled so they don't escape the closing quote
+#[cfg(any(windows, test))]
+pub(super) fn quote(arg: &str) -> String {
+    let trailing_backslashes = arg.len() - arg.trim_end_matches('\\').len();
+    format!("\"{arg}{}\"", "\\".repeat(trailing_backslashes))
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Month;
+
+    use super::*;
+    use crate::{registry::MemoryRegistry, test_util::manifest};
+
+    fn entry_values(
+        registry: &MemoryRegistry,
+        hive: Hive,
+        key: &str,
+    ) -> Vec<(String, RegistryValue)> {
+        registry
+            .values(hive, key)
+            .unwrap()
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn writes_entry() {
+        let mut manifest = manifest();
+        manifest.publisher = Some("Frost".to_string());
+        manifest.url = Some("https://example.com".to_string());
+        let install_path = Path::new("C:\\Program Files\\Test App\\");
+        let uninstaller = install_path.join(UNINSTALLER_NAME);
+        let entry = UninstallEntry {
+            manifest: &manifest,
+            scope: InstallScope::Machine,
+            install_path,
+            uninstaller: &uninstaller,
+            size: 5 * 1024 * 1024 + 100,
+            install_date: Date::from_calendar_date(2025, Month::March, 7).unwrap(),
+        };
+        let mut registry = MemoryRegistry::new();
+
+        let key = entry.write(&mut registry).unwrap();
+
+        assert_eq!(key, uninstall_key(&manifest));
+        assert!(key.starts_with(&format!("{UNINSTALL_KEY}\\{{")));
+        let string = |value: &str| RegistryValue::String(value.to_string());
+        let uninstall = format!(
+            "\"{}\" --uninstall --scope machine --install-path \"C:\\Program Files\\Test App\\\\\"",
+            uninstaller.display()
+        );
+        let mut expected = vec![
+            ("DisplayName".to_string(), string("Test App")),
+            ("DisplayVersion".to_string(), string("1.0.0")),
+            (
+                "DisplayIcon".to_string(),
+                string(&format!("{},0", install_path.join("test-app").display())),
+            ),
+            (
+                "InstallLocation".to_string(),
+                string("C:\\Program Files\\Test App\\"),
+            ),
+            ("InstallDate".to_string(), string("20250307")),
+            (
+                "QuietUninstallString".to_string(),
+                string(&format!("{uninstall} --silent")),
+            ),
+            ("UninstallString".to_string(), string(&uninstall)),
+            ("EstimatedSize".to_string(), RegistryValue::U32(5120)),
+            ("NoModify".to_string(), RegistryValue::U32(1)),
+            ("NoRepair".to_string(), RegistryValue::U32(1)),
+            ("Publisher".to_string(), string("Frost")),
+            ("URLInfoAbout".to_string(), string("https://example.com")),
+        ];
+        expected.sort_by(|(a, _), (b, _)| a.cmp(b));
+        assert_eq!(entry_values(&registry, Hive::LocalMachine, &key), expected);
+        assert_eq!(registry.keys(Hive::CurrentUser).count(), 0);
+    }
+
+    #[test]
+    fn replaces_earlier_entries() {
+        let manifest = manifest();
+        let install_path = Path::new("C:\\Users\\me\\AppData\\Local\\Programs\\test-app");
+        let uninstaller = install_path.join(UNINSTALLER_NAME);
+        let entry = UninstallEntry {
+            manifest: &manifest,
+            scope: InstallScope::User,
+            install_path,
+            uninstaller: &uninstaller,
+            size: u64::MAX,
+            install_date: Date::from_calendar_date(2025, Month::January, 1).unwrap(),
+        };
+        let key = uninstall_key(&manifest);
+        let legacy_key = legacy_uninstall_key(&manifest);
+        let mut registry = MemoryRegistry::new();
+        let stale = RegistryValue::String("stale".to_string());
+        registry
+            .set_value(Hive::CurrentUser, &key, "Comments", stale.clone())
+            .unwrap();
+        registry
+            .set_value(Hive::CurrentUser, &legacy_key, "DisplayName", stale)
+            .unwrap();
+
+        entry.write(&mut registry).unwrap();
+
+        assert_eq!(legacy_key, format!("{UNINSTALL_KEY}\\TestApp"));
+        assert!(registry.values(Hive::CurrentUser, &legacy_key).is_none());
+        let values = registry.values(Hive::CurrentUser, &key).unwrap();
+        assert!(!values.contains_key("Comments"));
+        // Sizes that don't fit are capped
+        assert_eq!(values["EstimatedSize"], RegistryValue::U32(u32::MAX));
+        assert!(!values.contains_key("Publisher"));
+    }
+
+    #[test]
+    fn derives_stable_product_guid() {
+        let mut manifest = manifest();
+        let derived = product_guid(&manifest);
+        assert_eq!(derived.len(), 38);
+        assert_eq!(&derived[15..16], "5");
+        manifest.version = "2.0.0".to_string();
+        manifest.friendly_name = "Renamed".to_string();
+        assert_eq!(product_guid(&manifest), derived);
+        manifest.publisher = Some("Frost".to_string());
+        assert_ne!(product_guid(&manifest), derived);
+
+        manifest.product_guid = Some("8f7a1d2c-0b3e-4f5a-9c6d-1e2f3a4b5c6d".to_string());
+        assert_eq!(
+            product_guid(&manifest),
+            "{8F7A1D2C-0B3E-4F5A-9C6D-1E2F3A4B5C6D}"
+        );
+    }
+
+    #[test]
+    fn quotes_arguments() {
+        assert_eq!(quote("C:\\App"), "\"C:\\App\"");
+        assert_eq!(quote("C:\\App\\"), "\"C:\\App\\\\\"");
+    }
+}
@@ -11,9 +11,15 @@ use std::{
 use clap::Parser;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use zip::{ZipWriter, write::SimpleFileOptions};
+use zip::ZipWriter;
 
-use crate::{config::AppManifest, post_embed::append_data};
+use crate::{
+    config::{
+        AppManifest, FileAssociation, FilePayload, InstallHook, InstallScope, MimeType,
+        PayloadCondition, Service, UrlScheme,
+    },
+    post_embed::append_data,
+};
 
 #[derive(Debug, Error)]
 pub enum CreateInstallerError {
@@ -63,18 +69,140 @@ struct Metadata {
 #[derive(Deserialize, Clone, Debug)]
 struct WizardMetadata {
     friendly_name: Option<String>,
+    #[serde(default)]
+    hooks: Vec<InstallHook>,
+    #[serde(default)]
+    default_scope: InstallScope,
+    #[serde(default)]
+    allow_scope_choice: bool,
+    #[serde(default)]
+    mime_types: Vec<MimeType>,
+    #[serde(default)]
+    add_to_path: bool,
+    product_guid: Option<String>,
+    #[serde(default)]
+    file_associations: Vec<FileAssociation>,
+    #[serde(default)]
+    url_schemes: Vec<UrlScheme>,
+    #[serde(default)]
+    services: Vec<Service>,
+    #[serde(default)]
+    payloads: Vec<PayloadMetadata>,
+}
+
+/// A directory installed alongside the binary, e.g. native libraries for one target
+#[derive(Deserialize, Clone, Debug)]
+struct PayloadMetadata {
+    /// Relative to the directory of Cargo.toml. Its contents are installed into the install directory.
+    dir: PathBuf,
+    /// Only installed on this operating system, named like `std::env::consts::OS`
+    os: Option<String>,
+    /// Only installed on this architecture, named like `std::env::consts::ARCH`
+    arch: Option<String>,
 }
 
 impl Metadata {
     fn friendly_name(&self) -> Option<String> {
         self.frost_wizard.as_ref()?.friendly_name.clone()
     }
+
+    fn hooks(&self) -> Vec<InstallHook> {
+        self.frost_wizard
+            .as_ref()
+            .map(|wizard| wizard.hooks.clone())
+            .unwrap_or_default()
+    }
+
+    fn default_scope(&self) -> InstallScope {
+        self.frost_wizard
+            .as_ref()
+            .map(|wizard| wizard.default_scope)
+            .unwrap_or_default()
+    }
+
+    fn allow_scope_choice(&self) -> bool {
+        self.frost_wizard
+            .as_ref()
+            .is_some_and(|wizard| wizard.allow_scope_choice)
+    }
+
+    fn product_guid(&self) -> Option<String> {
+        self.frost_wizard.as_ref()?.product_guid.clone()
+    }
+
+    fn add_to_path(&self) -> bool {
+        self.frost_wizard
+            .as_ref()
+            .is_some_and(|wizard| wizard.add_to_path)
+    }
+
+    fn mime_types(&self) -> Vec<MimeType> {
+        self.frost_wizard
+            .as_ref()
+            .map(|wizard| wizard.mime_types.clone())
+            .unwrap_or_default()
+    }
+
+    fn file_associations(&self) -> Vec<FileAssociation> {
+        self.frost_wizard
+            .as_ref()
+            .map(|wizard| wizard.file_associations.clone())
+            .unwrap_or_default()
+    }
+
+    fn url_schemes(&self) -> Vec<UrlScheme> {
+        self.frost_wizard
+            .as_ref()
+            .map(|wizard| wizard.url_schemes.clone())
+            .unwrap_or_default()
+    }
+
+    fn services(&self) -> Vec<Service> {
+        self.frost_wizard
+            .as_ref()
+            .map(|wizard| wizard.services.clone())
+            .unwrap_or_default()
+    }
+
+    fn payloads(&self) -> Vec<PayloadMetadata> {
+        self.frost_wizard
+            .as_ref()
+            .map(|wizard| wizard.payloads.clone())
+            .unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EmbeddedConfig {
     pub manifest: AppManifest,
     pub unpacked_size: u64,
+    /// Payload directories, embedded in this order before the binary. Each archive is preceded by its length.
+    pub payloads: Vec<EmbeddedPayload>,
+}
+
+/// A zipped payload directory and the target it is installed on
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmbeddedPayload {
+    pub os: Option<String>,
+    pub arch: Option<String>,
+    pub unpacked_size: u64,
+}
+
+impl EmbeddedPayload {
+    /// Restricts the payload to the target it was declared for
+    pub fn restrict(&self, payload: FilePayload) -> FilePayload {
+        let conditions = self
+            .os
+            .iter()
+            .map(PayloadCondition::os)
+            .chain(self.arch.iter().map(PayloadCondition::arch))
+            .collect::<Vec<_>>();
+        if conditions.is_empty() {
+            payload
+        } else {
+            payload.when(PayloadCondition::All(conditions))
+        }
+    }
 }
 
 pub fn create_installer() -> Result<(), CreateInstallerError> {
@@ -149,6 +277,17 @@ pub fn create_installer() -> Result<(), CreateInstallerError> {
                 .map(Metadata::friendly_name)
                 .flatten()
                 .unwrap_or(bin_name.clone());
+            let hooks = metadata.as_ref().map(Metadata::hooks).unwrap_or_default();
+            let default_scope = metadata
+                .as_ref()
+                .map(Metadata::default_scope)
+                .unwrap_or_default();
+            let allow_scope_choice = metadata.as_ref().is_some_and(Metadata::allow_scope_choice);
+            let add_to_path = metadata.as_ref().is_some_and(Metadata::add_to_path);
+            let mime_types = metadata
+                .as_ref()
+                .map(Metadata::mime_types)
+                .unwrap_or_default();
 
             let mut search_path = cargo_manifest_path
                 .parent()
@@ -157,6 +296,24 @@ pub fn create_installer() -> Result<(), CreateInstallerError> {
                 search_path = Path::new(".");
             }
 
+            let mut payloads = Vec::new();
+            let mut payload_archives = Vec::new();
+            for payload in metadata
+                .as_ref()
+                .map(Metadata::payloads)
+                .unwrap_or_default()
+            {
+                let dir = search_path.join(&payload.dir);
+                println!("Zipping payload directory {}", dir.display());
+                let (archive, unpacked_size) = zip_dir::zip_directory(&dir)?;
+                payload_archives.push(archive);
+                payloads.push(EmbeddedPayload {
+                    os: payload.os,
+                    arch: payload.arch,
+                    unpacked_size,
+                });
+            }
+
             println!("Building installer for:\n{}\n{}", friendly_name, version);
 
             let mut bin_path = None;
@@ -184,12 +341,34 @@ pub fn create_installer() -> Result<(), CreateInstallerError> {
             #[cfg(windows)]
             let bin_size = bin_file.metadata()?.file_size();
 
+            let mut manifest = AppManifest::build()
+                .friendly_name(friendly_name)
+                .bin_name(bin_name.clone())
+                .version(version)
+                .default_scope(default_scope)
+                .allow_scope_choice(allow_scope_choice)
+                .add_to_path(add_to_path);
+            manifest.hooks = hooks;
+            manifest.mime_types = mime_types;
+            manifest.file_associations = metadata
+                .as_ref()
+                .map(Metadata::file_associations)
+                .unwrap_or_default();
+            manifest.url_schemes = metadata
+                .as_ref()
+                .map(Metadata::url_schemes)
+                .unwrap_or_default();
+            manifest.services = metadata
+                .as_ref()
+                .map(Metadata::services)
+                .unwrap_or_default();
+            manifest.product_guid = metadata.as_ref().and_then(Metadata::product_guid);
+            manifest.url = cargo_manifest.package().homepage().map(ToString::to_string);
+
             let embedded_config = EmbeddedConfig {
-                manifest: AppManifest::build()
-                    .friendly_name(friendly_name)
-                    .bin_name(bin_name.clone())
-                    .version(version),
+                manifest,
                 unpacked_size: bin_size,
+                payloads,
             };
 
             let config_bytes = postcard::to_stdvec(&embedded_config)?;
@@ -200,15 +379,19 @@ pub fn create_installer() -> Result<(), CreateInstallerError> {
             let length_bytes = (config_bytes.len() as u64).to_le_bytes();
             append_writer.write_all(&length_bytes)?;
             append_writer.write_all(&config_bytes)?;
+            for archive in payload_archives {
+                append_writer.write_all(&(archive.len() as u64).to_le_bytes())?;
+                append_writer.write_all(&archive)?;
+            }
             append_writer.move_start_to_current()?;
 
             println!("Zipping and embedding files");
 
             let mut zip = ZipWriter::new(append_writer);
-            let options = SimpleFileOptions::default()
-                .compression_method(zip::CompressionMethod::Xz)
-                .compression_level(Some(6i64));
-            zip.start_file(bin_name.clone(), options)?;
+            zip.start_file(
+                bin_name.clone(),
+                zip_dir::file_options(&bin_file.metadata()?),
+            )?;
             let mut bin_reader = BufReader::new(bin_file);
             std::io::copy(&mut bin_reader, &mut zip)?;
 
@@ -223,3 +406,80 @@ pub fn create_installer() -> Result<(), CreateInstallerError> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TargetSystem;
+
+    fn embedded(os: Option<&str>, arch: Option<&str>) -> EmbeddedPayload {
+        EmbeddedPayload {
+            os: os.map(str::to_string),
+            arch: arch.map(str::to_string),
+            unpacked_size: 4,
+        }
+    }
+
+    fn file() -> FilePayload {
+        FilePayload::File {
+            name: "data".into(),
+            contents: b"data".as_slice().into(),
+        }
+    }
+
+    /// Whether the restricted payload is installed on the system
+    fn installed_on(payload: &FilePayload, os: &'static str, arch: &str) -> bool {
+        let system = TargetSystem {
+            os,
+            arch: arch.to_string(),
+        };
+        match payload {
+            FilePayload::Conditional { condition, .. } => condition.is_met_on(&system),
+            _ => true,
+        }
+    }
+
+    #[test]
+    fn restricts_payload_to_declared_target() {
+        let payload = embedded(Some("linux"), Some("x86_64")).restrict(file());
+        assert!(installed_on(&payload, "linux", "x86_64"));
+        assert!(!installed_on(&payload, "linux", "aarch64"));
+        assert!(!installed_on(&payload, "windows", "x86_64"));
+
+        let payload = embedded(Some("windows"), None).restrict(file());
+        assert!(installed_on(&payload, "windows", "aarch64"));
+        assert!(!installed_on(&payload, "linux", "x86_64"));
+    }
+
+    #[test]
+    fn payload_without_target_is_unrestricted() {
+        let payload = embedded(None, None).restrict(file());
+        assert!(matches!(payload, FilePayload::File { .. }));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn zipped_payload_keeps_symlinks() {
+        let dir = crate::test_util::TempDir::new();
+        std::fs::create_dir(dir.path().join("bin")).unwrap();
+        std::fs::write(dir.path().join("bin/app"), b"binary").unwrap();
+        std::os::unix::fs::symlink("bin/app", dir.path().join("app")).unwrap();
+        // Would never end if links were followed
+        std::os::unix::fs::symlink("..", dir.path().join("bin/parent")).unwrap();
+
+        let (archive, unpacked_size) = zip_dir::zip_directory(dir.path()).unwrap();
+
+        assert_eq!(unpacked_size, 6);
+        let mut zip = zip::ZipArchive::new(std::io::Cursor::new(archive)).unwrap();
+        let mut names = zip.file_names().collect::<Vec<_>>();
+        names.sort_unstable();
+        assert_eq!(names, ["app", "bin/", "bin/app", "bin/parent"]);
+        for (name, target) in [("app", "bin/app"), ("bin/parent", "..")] {
+            let mut entry = zip.by_name(name).unwrap();
+            assert!(entry.is_symlink());
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents).unwrap();
+            assert_eq!(contents, target);
+        }
+    }
+}
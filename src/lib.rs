@@ -1,8 +1,14 @@
 pub mod config;
+pub mod disk_space;
 mod installer;
 pub mod installer_creator;
 pub mod post_embed;
+pub mod registry;
+pub mod requirement;
 mod ui;
 #[cfg(windows)]
 pub mod windows;
 pub mod wizard;
+
+#[cfg(test)]
+mod test_util;
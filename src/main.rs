@@ -1,6 +1,5 @@
 #![windows_subsystem = "windows"]
 use std::io::Read;
-use std::path::PathBuf;
 use thiserror::Error;
 
 #[cfg(windows)]
@@ -16,8 +15,6 @@ fn main() {
     if let Some(embedded_reader) =
         search_for_embedded_data().expect("Error while checking for embedded data")
     {
-        #[cfg(windows)]
-        attach_and_ensure_admin();
         if let Err(err) = start_installer_from_embedded_data(embedded_reader) {
             eprintln!("Error while running installer: {}", err);
             std::process::exit(1);
@@ -54,23 +51,42 @@ fn start_installer_from_embedded_data(
     reader.read_exact(&mut config_bytes)?;
     let config: EmbeddedConfig = postcard::from_bytes(&config_bytes)?;
 
+    let mut payloads = Vec::new();
+    for payload in &config.payloads {
+        reader.read_exact(&mut len_bytes)?;
+        let archive = reader.split_off(u64::from_le_bytes(len_bytes))?;
+        payloads.push(payload.restrict(FilePayload::Directory {
+            unpacked_size: payload.unpacked_size,
+            reader: Box::new(archive),
+        }));
+    }
+
     reader.move_start_to_current();
 
+    // If the scope can still be chosen in the wizard, it restarts elevated itself once that is needed
     #[cfg(windows)]
-    let mut default_install_path = PathBuf::from("C:\\Program Files");
-    #[cfg(unix)]
-    let mut default_install_path = PathBuf::from("/opt");
-    default_install_path.push(&config.manifest.bin_name);
+    if BasicWizard::requires_elevation(&config.manifest) {
+        attach_and_ensure_admin();
+    } else {
+        let _ = attach();
+    }
 
-    BasicWizard::builder()
+    let default_install_path = config
+        .manifest
+        .default_scope
+        .default_install_path(&config.manifest.bin_name);
+
+    let mut builder = BasicWizard::builder()
         .manifest(config.manifest)
         .default_install_path(default_install_path)
         .add_payload(FilePayload::Directory {
             unpacked_size: config.unpacked_size,
             reader: Box::new(reader),
-        })
-        .to_installer()
-        .run()?;
+        });
+    for payload in payloads {
+        builder = builder.add_payload(payload);
+    }
+    builder.to_installer().run()?;
 
     Ok(())
 }
@@ -67,20 +67,30 @@ impl EmbeddedReader {
     pub fn move_start_to_current(&mut self) {
         self.start = self.position;
     }
+
+    /// Takes the next `length` bytes as a reader of their own and moves past them
+    pub fn split_off(&mut self, length: u64) -> io::Result<EmbeddedReader> {
+        let end = self.position.saturating_add(length);
+        if end > self.end {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Embedded data is truncated",
+            ));
+        }
+        let part = EmbeddedReader::new(self.file.try_clone()?, self.position, length);
+        self.position = end;
+        Ok(part)
+    }
 }
 
 impl Read for EmbeddedReader {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        if self.position == self.end {
+        let max = std::cmp::min(buf.len() as u64, self.end - self.position) as usize;
+        if max == 0 {
             return Ok(0);
         }
 
-        let max = std::cmp::min(buf.len() as u64, self.end - self.position) as usize;
-        let n = self.file.read(&mut buf[..max])?;
-        assert!(
-            n as u64 <= self.end - self.position,
-            "number of read bytes exceeds limit"
-        );
+        let n = read_at(&self.file, &mut buf[..max], self.position)?;
         self.position += n as u64;
         Ok(n)
     }
@@ -89,35 +99,29 @@ impl Read for EmbeddedReader {
 impl Seek for EmbeddedReader {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
         let new_pos = match pos {
-            SeekFrom::Start(pos) => {
-                let max = std::cmp::min(pos + self.start, self.end);
-                self.file.seek(SeekFrom::Start(max))?
-            }
-            SeekFrom::End(pos) => {
-                let pos = (self.end as i64 - pos) as u64;
-                let max = std::cmp::min(pos, self.end);
-                let clamped = std::cmp::max(max, self.start);
-                self.file.seek(SeekFrom::Start(clamped))?
-            }
-            SeekFrom::Current(pos) => {
-                if self.position as i64 + pos > self.end as i64 {
-                    self.file.seek(SeekFrom::Start(self.end))?
-                } else if self.position as i64 + pos < self.start as i64 {
-                    self.file.seek(SeekFrom::Start(self.start))?
-                } else {
-                    self.file.seek(SeekFrom::Current(pos))?
-                }
-            }
+            SeekFrom::Start(pos) => self.start as i128 + pos as i128,
+            SeekFrom::End(pos) => self.end as i128 + pos as i128,
+            SeekFrom::Current(pos) => self.position as i128 + pos as i128,
         };
 
-        self.position = new_pos;
-
-        let corrected_position = new_pos - self.start;
+        self.position = new_pos.clamp(self.start as i128, self.end as i128) as u64;
 
-        Ok(corrected_position)
+        Ok(self.position - self.start)
     }
 }
 
+/// Reads at an absolute offset without moving the file cursor other readers of the same file rely on
+#[cfg(unix)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    std::os::unix::fs::FileExt::read_at(file, buf, offset)
+}
+
+/// Reads at an absolute offset. The cursor moves, but it isn't used by any reader.
+#[cfg(windows)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    std::os::windows::fs::FileExt::seek_read(file, buf, offset)
+}
+
 pub fn append_data(new_executable: &Path) -> Result<AppendDataWriter, std::io::Error> {
     let source = current_exe().unwrap();
     std::fs::copy(source, new_executable)?;
@@ -0,0 +1,465 @@
+use std::{collections::BTreeMap, io};
+
+use serde::Serialize;
+
+use crate::config::InstallScope;
+
+/// Root of the registry keys an installation writes to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum Hive {
+    /// `HKEY_LOCAL_MACHINE`, requires administrator rights to write
+    LocalMachine,
+    /// `HKEY_CURRENT_USER`
+    CurrentUser,
+}
+
+impl Hive {
+    /// Per-user installations only write to the user's own hive
+    pub fn for_scope(scope: InstallScope) -> Self {
+        match scope {
+            InstallScope::Machine => Hive::LocalMachine,
+            InstallScope::User => Hive::CurrentUser,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum RegistryValue {
+    String(String),
+    /// A string that may contain `%VARIABLE%` references, like the `Path` environment variable
+    ExpandString(String),
+    U32(u32),
+}
+
+/// Access to the Windows registry.
+///
+/// Keys are paths below a [`Hive`] separated by backslashes, e.g. `SOFTWARE\Microsoft`.
+/// Missing keys are created when a value is set, and deleting something that doesn't exist succeeds.
+pub trait RegistryBackend {
+    fn get_value(&self, hive: Hive, key: &str, name: &str) -> io::Result<Option<RegistryValue>>;
+    fn set_value(
+        &mut self,
+        hive: Hive,
+        key: &str,
+        name: &str,
+        value: RegistryValue,
+    ) -> io::Result<()>;
+    fn delete_value(&mut self, hive: Hive, key: &str, name: &str) -> io::Result<()>;
+    /// Deletes the key with all of its values and subkeys
+    fn delete_key(&mut self, hive: Hive, key: &str) -> io::Result<()>;
+}
+
+/// The registry of the running system
+#[cfg(windows)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemRegistry;
+
+#[cfg(windows)]
+impl SystemRegistry {
+    fn root(hive: Hive) -> &'static windows_registry::Key {
+        match hive {
+            Hive::LocalMachine => windows_registry::LOCAL_MACHINE,
+            Hive::CurrentUser => windows_registry::CURRENT_USER,
+        }
+    }
+}
+
+#[cfg(windows)]
+fn is_not_found(error: &windows_result::Error) -> bool {
+    error.code() == windows::Win32::Foundation::ERROR_FILE_NOT_FOUND.to_hresult()
+}
+
+#[cfg(windows)]
+impl RegistryBackend for SystemRegistry {
+    fn get_value(&self, hive: Hive, key: &str, name: &str) -> io::Result<Option<RegistryValue>> {
+        let key = match Self::root(hive).open(key) {
+            Ok(key) => key,
+            Err(err) if is_not_found(&err) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let value = match key.get_type(name) {
+            Ok(windows_registry::Type::String) => RegistryValue::String(key.get_string(name)?),
+            Ok(windows_registry::Type::ExpandString) => {
+                RegistryValue::ExpandString(key.get_string(name)?)
+            }
+            Ok(windows_registry::Type::U32) => RegistryValue::U32(key.get_u32(name)?),
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Registry value {name} has an unsupported type"),
+                ));
+            }
+            Err(err) if is_not_found(&err) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Some(value))
+    }
+
+    fn set_value(
+        &mut self,
+        hive: Hive,
+        key: &str,
+        name: &str,
+        value: RegistryValue,
+    ) -> io::Result<()> {
+        let key = Self::root(hive).create(key)?;
+        match value {
+            RegistryValue::String(value) => key.set_string(name, value)?,
+            RegistryValue::ExpandString(value) => key.set_expand_string(name, value)?,
+            RegistryValue::U32(value) => key.set_u32(name, value)?,
+        }
+        Ok(())
+    }
+
+    fn delete_value(&mut self, hive: Hive, key: &str, name: &str) -> io::Result<()> {
+        let result = Self::root(hive)
+            .open(key)
+            .and_then(|key| key.remove_value(name));
+        match result {
+            Err(err) if !is_not_found(&err) => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn delete_key(&mut self, hive: Hive, key: &str) -> io::Result<()> {
+        match Self::root(hive).remove_tree(key) {
+            Err(err) if !is_not_found(&err) => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A registry that only lives in memory, to see what an installation would write without touching the system.
+///
+/// Unlike the real registry, key paths and value names are case-sensitive.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryRegistry {
+    keys: BTreeMap<(Hive, String), BTreeMap<String, RegistryValue>>,
+}
+
+impl MemoryRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All values of a key, if it exists
+    pub fn values(&self, hive: Hive, key: &str) -> Option<&BTreeMap<String, RegistryValue>> {
+        self.keys.get(&(hive, key.to_string()))
+    }
+
+    /// Paths of all keys that exist in a hive
+    pub fn keys(&self, hive: Hive) -> impl Iterator<Item = &str> {
+        self.keys
+            .keys()
+            .filter(move |(key_hive, _)| *key_hive == hive)
+            .map(|(_, key)| key.as_str())
+    }
+}
+
+impl RegistryBackend for MemoryRegistry {
+    fn get_value(&self, hive: Hive, key: &str, name: &str) -> io::Result<Option<RegistryValue>> {
+        Ok(self
+            .values(hive, key)
+            .and_then(|values| values.get(name))
+            .cloned())
+    }
+
+    fn set_value(
+        &mut self,
+        hive: Hive,
+        key: &str,
+        name: &str,
+        value: RegistryValue,
+    ) -> io::Result<()> {
+        self.keys
+            .entry((hive, key.to_string()))
+            .or_default()
+            .insert(name.to_string(), value);
+        Ok(())
+    }
+
+    fn delete_value(&mut self, hive: Hive, key: &str, name: &str) -> io::Result<()> {
+        if let Some(values) = self.keys.get_mut(&(hive, key.to_string())) {
+            values.remove(name);
+        }
+        Ok(())
+    }
+
+    fn delete_key(&mut self, hive: Hive, key: &str) -> io::Result<()> {
+        let subkey_prefix = format!("{key}\\");
+        self.keys.retain(|(key_hive, existing), _| {
+            *key_hive != hive || (existing != key && !existing.starts_with(&subkey_prefix))
+        });
+        Ok(())
+    }
+}
+
+/// A change to the registry, as recorded by [`RecordingRegistry`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum RegistryChange {
+    SetValue {
+        hive: Hive,
+        key: String,
+        name: String,
+        value: RegistryValue,
+    },
+    DeleteValue {
+        hive: Hive,
+        key: String,
+        name: String,
+    },
+    DeleteKey {
+        hive: Hive,
+        key: String,
+    },
+}
+
+impl std::fmt::Display for RegistryChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let root = |hive: &Hive| match hive {
+            Hive::LocalMachine => "HKLM",
+            Hive::CurrentUser => "HKCU",
+        };
+        match self {
+            RegistryChange::SetValue {
+                hive,
+                key,
+                name,
+                value,
+            } => {
+                let name = if name.is_empty() { "(Default)" } else { name };
+                match value {
+                    RegistryValue::String(value) | RegistryValue::ExpandString(value) => {
+                        write!(f, "set {}\\{key} {name} = {value:?}", root(hive))
+                    }
+                    RegistryValue::U32(value) => {
+                        write!(f, "set {}\\{key} {name} = {value}", root(hive))
+                    }
+                }
+            }
+            RegistryChange::DeleteValue { hive, key, name } => {
+                write!(f, "delete {}\\{key} {name}", root(hive))
+            }
+            RegistryChange::DeleteKey { hive, key } => {
+                write!(f, "delete {}\\{key}", root(hive))
+            }
+        }
+    }
+}
+
+/// Reads from another registry, but only records changes instead of making them.
+///
+/// Values it recorded are read back, so code that builds on its own changes sees them.
+pub struct RecordingRegistry<'a, R> {
+    base: &'a R,
+    changed: MemoryRegistry,
+    /// Keys and values deleted from the base, a deleted value has a name
+    deleted: Vec<(Hive, String, Option<String>)>,
+    changes: Vec<RegistryChange>,
+}
+
+impl<'a, R: RegistryBackend> RecordingRegistry<'a, R> {
+    pub fn new(base: &'a R) -> Self {
+        Self {
+            base,
+            changed: MemoryRegistry::new(),
+            deleted: Vec::new(),
+            changes: Vec::new(),
+        }
+    }
+
+    fn is_deleted(&self, hive: Hive, key: &str, name: &str) -> bool {
+        self.deleted
+            .iter()
+            .any(|(deleted_hive, deleted_key, deleted_name)| {
+                *deleted_hive == hive
+                    && match deleted_name {
+                        Some(deleted_name) => deleted_key == key && deleted_name == name,
+                        None => {
+                            deleted_key == key
+                                || key
+                                    .strip_prefix(deleted_key.as_str())
+                                    .is_some_and(|rest| rest.starts_with('\\'))
+                        }
+                    }
+            })
+    }
+
+    /// The recorded changes, in the order they were made
+    pub fn into_changes(self) -> Vec<RegistryChange> {
+        self.changes
+    }
+}
+
+impl<R: RegistryBackend> RegistryBackend for RecordingRegistry<'_, R> {
+    fn get_value(&self, hive: Hive, key: &str, name: &str) -> io::Result<Option<RegistryValue>> {
+        match self.changed.get_value(hive, key, name)? {
+            Some(value) => Ok(Some(value)),
+            None if self.is_deleted(hive, key, name) => Ok(None),
+            None => self.base.get_value(hive, key, name),
+        }
+    }
+
+    fn set_value(
+        &mut self,
+        hive: Hive,
+        key: &str,
+        name: &str,
+        value: RegistryValue,
+    ) -> io::Result<()> {
+        self.changed.set_value(hive, key, name, value.clone())?;
+        self.changes.push(RegistryChange::SetValue {
+            hive,
+            key: key.to_string(),
+            name: name.to_string(),
+            value,
+        });
+        Ok(())
+    }
+
+    fn delete_value(&mut self, hive: Hive, key: &str, name: &str) -> io::Result<()> {
+        self.changed.delete_value(hive, key, name)?;
+        self.deleted
+            .push((hive, key.to_string(), Some(name.to_string())));
+        self.changes.push(RegistryChange::DeleteValue {
+            hive,
+            key: key.to_string(),
+            name: name.to_string(),
+        });
+        Ok(())
+    }
+
+    fn delete_key(&mut self, hive: Hive, key: &str) -> io::Result<()> {
+        self.changed.delete_key(hive, key)?;
+        self.deleted.push((hive, key.to_string(), None));
+        self.changes.push(RegistryChange::DeleteKey {
+            hive,
+            key: key.to_string(),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(value: &str) -> RegistryValue {
+        RegistryValue::String(value.to_string())
+    }
+
+    #[test]
+    fn memory_registry_deletes_keys_with_subkeys() {
+        let mut registry = MemoryRegistry::new();
+        for key in ["Software\\App", "Software\\App\\Sub", "Software\\Apple"] {
+            registry
+                .set_value(Hive::CurrentUser, key, "", string(key))
+                .unwrap();
+        }
+        registry
+            .set_value(Hive::LocalMachine, "Software\\App", "", string("machine"))
+            .unwrap();
+
+        registry
+            .delete_key(Hive::CurrentUser, "Software\\App")
+            .unwrap();
+        registry
+            .delete_value(Hive::LocalMachine, "Software\\App", "")
+            .unwrap();
+
+        assert_eq!(
+            registry.keys(Hive::CurrentUser).collect::<Vec<_>>(),
+            ["Software\\Apple"]
+        );
+        assert_eq!(
+            registry.values(Hive::LocalMachine, "Software\\App"),
+            Some(&BTreeMap::new())
+        );
+    }
+
+    #[test]
+    fn recording_registry_leaves_base_alone() {
+        let mut base = MemoryRegistry::new();
+        base.set_value(Hive::CurrentUser, "Software\\App", "Kept", string("base"))
+            .unwrap();
+        base.set_value(
+            Hive::CurrentUser,
+            "Software\\App\\Sub",
+            "Gone",
+            string("base"),
+        )
+        .unwrap();
+        base.set_value(
+            Hive::CurrentUser,
+            "Software\\App",
+            "Deleted",
+            string("base"),
+        )
+        .unwrap();
+        let mut recording = RecordingRegistry::new(&base);
+
+        recording
+            .delete_key(Hive::CurrentUser, "Software\\App\\Sub")
+            .unwrap();
+        recording
+            .delete_value(Hive::CurrentUser, "Software\\App", "Deleted")
+            .unwrap();
+        recording
+            .set_value(
+                Hive::CurrentUser,
+                "Software\\App\\Sub",
+                "New",
+                string("recorded"),
+            )
+            .unwrap();
+
+        // Its own changes are read back, on top of the base
+        let get =
+            |name: &str, key: &str| recording.get_value(Hive::CurrentUser, key, name).unwrap();
+        assert_eq!(get("Kept", "Software\\App"), Some(string("base")));
+        assert_eq!(get("Deleted", "Software\\App"), None);
+        assert_eq!(get("Gone", "Software\\App\\Sub"), None);
+        assert_eq!(get("New", "Software\\App\\Sub"), Some(string("recorded")));
+
+        assert_eq!(
+            recording.into_changes(),
+            [
+                RegistryChange::DeleteKey {
+                    hive: Hive::CurrentUser,
+                    key: "Software\\App\\Sub".to_string(),
+                },
+                RegistryChange::DeleteValue {
+                    hive: Hive::CurrentUser,
+                    key: "Software\\App".to_string(),
+                    name: "Deleted".to_string(),
+                },
+                RegistryChange::SetValue {
+                    hive: Hive::CurrentUser,
+                    key: "Software\\App\\Sub".to_string(),
+                    name: "New".to_string(),
+                    value: string("recorded"),
+                },
+            ]
+        );
+        assert_eq!(
+            base.get_value(Hive::CurrentUser, "Software\\App\\Sub", "Gone")
+                .unwrap(),
+            Some(string("base"))
+        );
+    }
+
+    #[test]
+    fn displays_changes() {
+        let change = RegistryChange::SetValue {
+            hive: Hive::LocalMachine,
+            key: "Software\\App".to_string(),
+            name: String::new(),
+            value: string("C:\\App"),
+        };
+        assert_eq!(
+            change.to_string(),
+            "set HKLM\\Software\\App (Default) = \"C:\\\\App\""
+        );
+    }
+}
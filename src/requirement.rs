@@ -0,0 +1,453 @@
+//! Prerequisites that have to be met before an application can be installed.
+//!
+//! Requirements are checked before the wizard is shown or the unattended installation starts.
+
+use std::path::PathBuf;
+
+/// A condition the target system has to fulfill for the installation to start
+pub trait Requirement: Send + Sync {
+    /// Short description of what is required, e.g. "Windows 10 or newer"
+    fn description(&self) -> String;
+    /// Returns the reason why the requirement isn't met
+    fn check(&self) -> Result<(), String>;
+}
+
+/// A requirement that isn't met, as shown to the user
+#[derive(Debug, Clone)]
+pub struct UnmetRequirement {
+    pub description: String,
+    pub reason: String,
+}
+
+/// Checks all requirements and returns those that aren't met
+pub fn check_requirements(requirements: &[Box<dyn Requirement>]) -> Vec<UnmetRequirement> {
+    requirements
+        .iter()
+        .filter_map(|requirement| {
+            requirement.check().err().map(|reason| UnmetRequirement {
+                description: requirement.description(),
+                reason,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Os {
+    Windows,
+    Linux,
+}
+
+/// Requires a minimum version of the operating system.
+///
+/// On Windows the version is compared as major, minor and build number, e.g. `10.0.17763`.
+/// On Linux the kernel version is used. The requirement is always met on other operating systems.
+pub struct MinOsVersion {
+    os: Os,
+    version: [u32; 3],
+}
+
+impl MinOsVersion {
+    pub fn windows(major: u32, minor: u32, build: u32) -> Self {
+        Self {
+            os: Os::Windows,
+            version: [major, minor, build],
+        }
+    }
+
+    pub fn linux_kernel(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            os: Os::Linux,
+            version: [major, minor, patch],
+        }
+    }
+}
+
+impl Requirement for MinOsVersion {
+    fn description(&self) -> String {
+        let [major, minor, patch] = self.version;
+        match self.os {
+            Os::Windows => format!("Windows {major}.{minor}.{patch} or newer"),
+            Os::Linux => format!("Linux kernel {major}.{minor}.{patch} or newer"),
+        }
+    }
+
+    fn check(&self) -> Result<(), String> {
+        let current_os = if cfg!(windows) {
+            Os::Windows
+        } else if cfg!(target_os = "linux") {
+            Os::Linux
+        } else {
+            return Ok(());
+        };
+        if current_os != self.os {
+            return Ok(());
+        }
+
+        let current = os_version().map_err(|err| format!("Couldn't determine version: {err}"))?;
+        if current >= self.version {
+            Ok(())
+        } else {
+            let [major, minor, patch] = current;
+            Err(format!("Found version {major}.{minor}.{patch}"))
+        }
+    }
+}
+
+#[cfg(windows)]
+fn os_version() -> Result<[u32; 3], String> {
+    let key = windows_registry::LOCAL_MACHINE
+        .open("SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion")
+        .map_err(|err| err.to_string())?;
+    let major = key
+        .get_u32("CurrentMajorVersionNumber")
+        .map_err(|err| err.to_string())?;
+    let minor = key
+        .get_u32("CurrentMinorVersionNumber")
+        .map_err(|err| err.to_string())?;
+    let build = key
+        .get_string("CurrentBuildNumber")
+        .map_err(|err| err.to_string())?;
+    let build = build
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid build number {build}"))?;
+    Ok([major, minor, build])
+}
+
+#[cfg(unix)]
+fn os_version() -> Result<[u32; 3], String> {
+    use std::{ffi::CStr, mem::MaybeUninit};
+
+    let mut uname = MaybeUninit::<libc::utsname>::uninit();
+    if unsafe { libc::uname(uname.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+    let uname = unsafe { uname.assume_init() };
+    let release = unsafe { CStr::from_ptr(uname.release.as_ptr()) }.to_string_lossy();
+
+    Ok(parse_version(&release))
+}
+
+/// Parses the leading numeric parts of a version string like `6.1.12-arch1`
+#[cfg(unix)]
+fn parse_version(version: &str) -> [u32; 3] {
+    let mut parsed = [0; 3];
+    let parts = version
+        .split(|c: char| !c.is_ascii_digit())
+        .take_while(|part| !part.is_empty());
+    for (slot, part) in parsed.iter_mut().zip(parts) {
+        *slot = part.parse().unwrap_or_default();
+    }
+    parsed
+}
+
+/// Requires a shared library where the dynamic linker finds it, e.g. `libssl.so.3` or `vcruntime140.dll`
+pub struct SharedLibrary {
+    name: String,
+}
+
+impl SharedLibrary {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+impl Requirement for SharedLibrary {
+    fn description(&self) -> String {
+        format!("Shared library {}", self.name)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if library_available(&self.name) {
+            Ok(())
+        } else {
+            Err(format!("{} couldn't be found", self.name))
+        }
+    }
+}
+
+/// Looks the library up where the dynamic linker would, without loading it, as loading runs its initialization code.
+///
+/// On macOS system libraries that only exist in the dyld shared cache aren't found.
+#[cfg(unix)]
+fn library_available(name: &str) -> bool {
+    if name.contains('/') {
+        return std::path::Path::new(name).is_file();
+    }
+
+    let configured = std::env::var_os(LIBRARY_PATH_VARIABLE).unwrap_or_default();
+    let search_dirs = std::env::split_paths(&configured)
+        .chain(DEFAULT_LIBRARY_DIRS.iter().map(PathBuf::from))
+        .collect::<Vec<_>>();
+    if find_library(name, &search_dirs).is_some() {
+        return true;
+    }
+
+    // Only `ldconfig` knows all directories configured in /etc/ld.so.conf, it is often not on the `PATH` of regular users
+    ["ldconfig", "/sbin/ldconfig"].into_iter().any(|ldconfig| {
+        std::process::Command::new(ldconfig)
+            .arg("-p")
+            .output()
+            .is_ok_and(|output| ldconfig_lists(&String::from_utf8_lossy(&output.stdout), name))
+    })
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+const LIBRARY_PATH_VARIABLE: &str = "LD_LIBRARY_PATH";
+#[cfg(target_os = "macos")]
+const LIBRARY_PATH_VARIABLE: &str = "DYLD_LIBRARY_PATH";
+
+/// Directories the dynamic linker searches even if they aren't configured
+#[cfg(unix)]
+const DEFAULT_LIBRARY_DIRS: &[&str] = &[
+    "/lib",
+    "/lib64",
+    "/usr/lib",
+    "/usr/lib64",
+    "/usr/local/lib",
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    "/usr/lib/x86_64-linux-gnu",
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    "/lib/x86_64-linux-gnu",
+    #[cfg(all(target_arch = "aarch64", target_os = "linux"))]
+    "/usr/lib/aarch64-linux-gnu",
+    #[cfg(all(target_arch = "aarch64", target_os = "linux"))]
+    "/lib/aarch64-linux-gnu",
+];
+
+#[cfg(unix)]
+fn find_library(name: &str, search_dirs: &[PathBuf]) -> Option<PathBuf> {
+    search_dirs
+        .iter()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Checks whether the output of `ldconfig -p` has an entry for the library.
+///
+/// Entries look like `\tlibssl.so.3 (libc6,x86-64) => /lib/x86_64-linux-gnu/libssl.so.3`.
+#[cfg(unix)]
+fn ldconfig_lists(output: &str, name: &str) -> bool {
+    output
+        .lines()
+        .filter_map(|line| line.trim_start().split_once(" => "))
+        .any(|(entry, _)| entry.split(' ').next() == Some(name))
+}
+
+#[cfg(windows)]
+fn library_available(name: &str) -> bool {
+    use windows::{
+        Win32::{
+            Foundation::FreeLibrary,
+            System::LibraryLoader::{LOAD_LIBRARY_AS_DATAFILE, LoadLibraryExW},
+        },
+        core::HSTRING,
+    };
+
+    // Loading as data file doesn't run any code from the library
+    match unsafe { LoadLibraryExW(&HSTRING::from(name), None, LOAD_LIBRARY_AS_DATAFILE) } {
+        Ok(module) => {
+            let _ = unsafe { FreeLibrary(module) };
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Requires a file or directory to exist, e.g. the install location of a dependency
+pub struct PathExists {
+    path: PathBuf,
+}
+
+impl PathExists {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Requirement for PathExists {
+    fn description(&self) -> String {
+        format!("{} has to exist", self.path.display())
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if self.path.exists() {
+            Ok(())
+        } else {
+            Err(format!("{} couldn't be found", self.path.display()))
+        }
+    }
+}
+
+/// Requires that no process with the given executable name is running, e.g. the application being installed
+pub struct ProcessNotRunning {
+    name: String,
+}
+
+impl ProcessNotRunning {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+impl Requirement for ProcessNotRunning {
+    fn description(&self) -> String {
+        format!("{} must not be running", self.name)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if process_running(&self.name) {
+            Err(format!("Please close {} and try again", self.name))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(unix)]
+fn process_running(name: &str) -> bool {
+    let Ok(processes) = std::fs::read_dir("/proc") else {
+        return false;
+    };
+
+    let own_pid = std::process::id().to_string();
+
+    processes.flatten().any(|process| {
+        if process.file_name() == own_pid.as_str() {
+            return false;
+        }
+        let path = process.path();
+        if let Ok(exe) = std::fs::read_link(path.join("exe")) {
+            exe.file_name().is_some_and(|exe| exe == name)
+        } else {
+            // The kernel truncates the command name to 15 bytes
+            let truncated = name.get(..15).unwrap_or(name);
+            std::fs::read_to_string(path.join("comm"))
+                .is_ok_and(|comm| comm.trim_end_matches('\n') == truncated)
+        }
+    })
+}
+
+#[cfg(windows)]
+fn process_running(name: &str) -> bool {
+    use windows::Win32::{
+        Foundation::CloseHandle,
+        System::Diagnostics::ToolHelp::{
+            CreateToolhelp32Snapshot, PROCESSENTRY32W, Process32FirstW, Process32NextW,
+            TH32CS_SNAPPROCESS,
+        },
+    };
+
+    let Ok(snapshot) = (unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) }) else {
+        return false;
+    };
+
+    let mut entry = PROCESSENTRY32W {
+        dwSize: size_of::<PROCESSENTRY32W>() as u32,
+        ..Default::default()
+    };
+
+    let mut found = false;
+    let mut next = unsafe { Process32FirstW(snapshot, &mut entry) };
+    while next.is_ok() {
+        let length = entry
+            .szExeFile
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(entry.szExeFile.len());
+        let exe = String::from_utf16_lossy(&entry.szExeFile[..length]);
+        if entry.th32ProcessID != std::process::id() && exe.eq_ignore_ascii_case(name) {
+            found = true;
+            break;
+        }
+        next = unsafe { Process32NextW(snapshot, &mut entry) };
+    }
+
+    let _ = unsafe { CloseHandle(snapshot) };
+    found
+}
+
+/// A requirement backed by a closure, for checks that aren't covered by the built-in requirements
+pub struct CustomRequirement<F> {
+    description: String,
+    check: F,
+}
+
+impl<F> CustomRequirement<F>
+where
+    F: Fn() -> Result<(), String> + Send + Sync,
+{
+    pub fn new(description: impl Into<String>, check: F) -> Self {
+        Self {
+            description: description.into(),
+            check,
+        }
+    }
+}
+
+impl<F> Requirement for CustomRequirement<F>
+where
+    F: Fn() -> Result<(), String> + Send + Sync,
+{
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+
+    fn check(&self) -> Result<(), String> {
+        (self.check)()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn finds_libraries_in_search_dirs() {
+        let dir = TempDir::new();
+        let libs = dir.path().join("lib");
+        fs::create_dir(&libs).unwrap();
+        fs::write(libs.join("libexample.so.1"), "").unwrap();
+        let search_dirs = [PathBuf::new(), dir.path().to_path_buf(), libs.clone()];
+
+        assert_eq!(
+            find_library("libexample.so.1", &search_dirs),
+            Some(libs.join("libexample.so.1"))
+        );
+        assert_eq!(find_library("libexample.so.2", &search_dirs), None);
+        assert_eq!(find_library("lib", &search_dirs), None);
+    }
+
+    #[test]
+    fn parses_ldconfig_output() {
+        let output = "\
+1234 libs found in cache `/etc/ld.so.cache'
+\tlibssl.so.3 (libc6,x86-64) => /lib/x86_64-linux-gnu/libssl.so.3
+\tlibssl.so (libc6,x86-64) => /lib/x86_64-linux-gnu/libssl.so
+\tlibz.so.1 (libc6,x86-64, OS ABI: Linux 3.2.0) => /lib/x86_64-linux-gnu/libz.so.1
+Cache generated by: ldconfig (GNU libc) stable release version 2.39
+";
+
+        assert!(ldconfig_lists(output, "libssl.so.3"));
+        assert!(ldconfig_lists(output, "libz.so.1"));
+        assert!(!ldconfig_lists(output, "libssl.so.1.1"));
+        assert!(!ldconfig_lists(output, "libc6,x86-64)"));
+        assert!(!ldconfig_lists(output, "libs"));
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    fn finds_installed_libraries() {
+        assert!(SharedLibrary::new("libc.so.6").check().is_ok());
+        assert!(
+            SharedLibrary::new("libdoes-not-exist.so.42")
+                .check()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::config::{AppManifest, InstallConfig, InstallScope};
+
+/// A fresh directory below the temporary directory, removed with everything in it when dropped
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "frost-wizard-test-{}-{}",
+            process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        // Left over by an earlier run with the same process id
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        // Symlink checks compare against the canonical path, so the directory is handed out canonical as well
+        Self(path.canonicalize().unwrap())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+pub(crate) fn manifest() -> AppManifest {
+    AppManifest::build()
+        .friendly_name("Test App")
+        .version("1.0.0")
+        .bin_name("test-app")
+}
+
+pub(crate) fn install_config(install_path: &Path) -> InstallConfig {
+    InstallConfig {
+        install_path: install_path.to_path_buf(),
+        scope: InstallScope::default(),
+        payloads: Vec::new(),
+        components: Vec::new(),
+        selected_components: BTreeSet::new(),
+        create_start_menu_shortcut: false,
+        create_desktop_shortcut: false,
+        add_to_path: false,
+        variables: BTreeMap::new(),
+        launch: false,
+        log_file: None,
+        root: None,
+    }
+}
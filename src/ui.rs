@@ -1,15 +1,20 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc, time::Instant};
 
 use iced::{
-    Element, Task, exit,
-    widget::{horizontal_space, progress_bar, row, text},
+    Element, Subscription, Task, exit,
+    widget::{button, checkbox, column, horizontal_space, progress_bar, row, text},
+    window,
 };
 use sipper::Sipper;
 use zip::result::ZipError;
 
 use crate::{
     config::{AppManifest, InstallConfig},
-    installer::InstallError,
+    installer::{
+        CancelHandle, InstallError, InstallReport, Progress, launch_application, log_path,
+        open_file, open_path,
+    },
+    requirement::{Requirement, UnmetRequirement, check_requirements},
     ui::scaffold::Scaffold,
     wizard::WizardAction,
 };
@@ -18,6 +23,8 @@ pub mod scaffold;
 
 #[derive(PartialEq)]
 pub enum InstallerStep {
+    /// Shown instead of the introduction if prerequisites of the installation aren't met
+    Requirements,
     Introduction,
     Wizard,
     Installing,
@@ -28,9 +35,14 @@ pub enum InstallerStep {
 pub enum Message<WizardMessage> {
     Next,
     Wizard(WizardMessage),
-    Progress(f32),
-    InstallDone,
+    Progress(Progress),
+    InstallDone(InstallReport),
     InstallError(Arc<InstallError>),
+    Cancel,
+    CloseRequested,
+    Launch(bool),
+    OpenReadme(bool),
+    OpenLog,
     Finish,
 }
 
@@ -44,23 +56,63 @@ pub struct InstallerUi<Wizard> {
     step: InstallerStep,
     wizard: Wizard,
     manifest: AppManifest,
-    progress: f32,
+    requirements: Vec<Box<dyn Requirement>>,
+    unmet_requirements: Vec<UnmetRequirement>,
+    progress: Option<Progress>,
+    install_started: Option<Instant>,
     finished: bool,
+    report: Option<InstallReport>,
     error: Option<Arc<InstallError>>,
+    cancel: CancelHandle,
+    /// Set when the window was closed during the installation, so the UI exits once it has stopped
+    exit_when_stopped: bool,
+    /// Where the application is installed, once the installation started
+    install_path: Option<PathBuf>,
+    /// Starts the application when the completed installation is finished
+    launch: bool,
+    /// Opens the manifest's README when the completed installation is finished
+    open_readme: bool,
+    /// Why the application or its README couldn't be opened, finishing again just exits
+    launch_error: Option<String>,
+    /// Where the running or failed installation writes its log
+    log_path: Option<PathBuf>,
+    /// Why the log couldn't be opened
+    log_error: Option<String>,
 }
 
 impl<Wizard> InstallerUi<Wizard>
 where
     Wizard: crate::wizard::Wizard,
 {
-    pub fn start(wizard: Wizard, manifest: AppManifest) -> (Self, Task<Message<Wizard::Message>>) {
+    pub fn start(
+        wizard: Wizard,
+        manifest: AppManifest,
+        requirements: Vec<Box<dyn Requirement>>,
+    ) -> (Self, Task<Message<Wizard::Message>>) {
+        let unmet_requirements = check_requirements(&requirements);
         let ui = Self {
-            step: InstallerStep::Introduction,
+            step: if unmet_requirements.is_empty() {
+                InstallerStep::Introduction
+            } else {
+                InstallerStep::Requirements
+            },
             wizard,
             manifest,
-            progress: 0.0,
+            requirements,
+            unmet_requirements,
+            progress: None,
+            install_started: None,
             finished: false,
+            report: None,
             error: None,
+            cancel: CancelHandle::new(),
+            exit_when_stopped: false,
+            install_path: None,
+            launch: false,
+            open_readme: false,
+            launch_error: None,
+            log_path: None,
+            log_error: None,
         };
         (ui, Task::none())
     }
@@ -76,6 +128,13 @@ where
                 }
             }
             Message::Next => match &mut self.step {
+                InstallerStep::Requirements => {
+                    self.unmet_requirements = check_requirements(&self.requirements);
+                    if self.unmet_requirements.is_empty() {
+                        self.step = InstallerStep::Introduction;
+                    }
+                    Task::none()
+                }
                 InstallerStep::Introduction => {
                     self.step = InstallerStep::Wizard;
                     let action = self.wizard.start();
@@ -89,19 +148,65 @@ where
                 InstallerStep::Completed => Task::none(),
             },
             Message::Progress(progress) => {
-                self.progress = progress;
+                self.progress = Some(progress);
                 Task::none()
             }
-            Message::InstallDone => {
+            Message::InstallDone(report) => {
                 self.finished = true;
-                self.progress = 1.0;
-                Task::none()
+                self.report = Some(report);
+                if self.exit_when_stopped {
+                    exit()
+                } else {
+                    Task::none()
+                }
             }
             Message::InstallError(error) => {
                 self.error = Some(error);
+                if self.exit_when_stopped {
+                    exit()
+                } else {
+                    Task::none()
+                }
+            }
+            Message::Cancel => {
+                self.cancel.cancel();
+                Task::none()
+            }
+            Message::CloseRequested => {
+                if self.is_installing() {
+                    self.cancel.cancel();
+                    self.exit_when_stopped = true;
+                    Task::none()
+                } else {
+                    exit()
+                }
+            }
+            Message::Launch(launch) => {
+                self.launch = launch;
+                Task::none()
+            }
+            Message::OpenReadme(open_readme) => {
+                self.open_readme = open_readme;
                 Task::none()
             }
-            Message::Finish => exit(),
+            Message::OpenLog => {
+                if let Some(log_path) = &self.log_path {
+                    self.log_error = open_path(log_path)
+                        .err()
+                        .map(|error| format!("Failed to open the log:\n{error}"));
+                }
+                Task::none()
+            }
+            Message::Finish => {
+                if self.step == InstallerStep::Completed
+                    && self.launch_error.is_none()
+                    && let Err(error) = self.open_selected()
+                {
+                    self.launch_error = Some(error);
+                    return Task::none();
+                }
+                exit()
+            }
         }
     }
 
@@ -118,7 +223,15 @@ where
             }
             crate::wizard::WizardAction::Install(config) => {
                 self.step = InstallerStep::Installing;
-                self.progress = 0.0;
+                self.progress = None;
+                self.install_started = Some(Instant::now());
+                self.cancel = CancelHandle::new();
+                // A staged installation is only complete once it is deployed, its files can still be opened
+                self.install_path = Some(config.staged(&config.install_path));
+                self.log_path = Some(log_path(&config, &self.manifest));
+                self.log_error = None;
+                self.launch = config.launch && config.root.is_none();
+                self.open_readme = self.manifest.readme.is_some();
                 self.install(config)
             }
         }
@@ -126,6 +239,22 @@ where
 
     pub fn view<'a>(&'a self) -> Element<'a, Message<Wizard::Message>> {
         if let Some(error) = &self.error {
+            if let InstallError::Cancelled = error.as_ref() {
+                return Scaffold::new()
+                    .title(row![
+                        text(&self.manifest.friendly_name).size(24),
+                        horizontal_space(),
+                        text(&self.manifest.version).size(24)
+                    ])
+                    .control(text("Installation cancelled!"))
+                    .control(text(
+                        "Files and folders the installation added have been removed again. \
+                         Files of an earlier installation it already replaced can't be restored.",
+                    ))
+                    .on_finish(Message::Finish)
+                    .into();
+            }
+
             let error_message = if let InstallError::ZipError(ZipError::Io(io)) = error.as_ref() {
                 format!("{error}:\n{io}")
             } else {
@@ -140,11 +269,34 @@ where
                 ])
                 .control(text("Error during installation!"))
                 .control(text(error_message))
+                .control_maybe(self.log_path.as_ref().map(|log_path| {
+                    row![
+                        text(format!("Details are in the log at {}", log_path.display())),
+                        horizontal_space(),
+                        button(text("Open log")).on_press(Message::OpenLog),
+                    ]
+                    .spacing(10)
+                    .align_y(iced::Alignment::Center)
+                }))
+                .control_maybe(self.log_error.as_deref().map(text))
                 .on_finish(Message::Finish)
                 .into();
         }
 
         match &self.step {
+            InstallerStep::Requirements => Scaffold::new()
+                .title(row![text(&self.manifest.friendly_name).size(24), horizontal_space(), text(&self.manifest.version).size(24)])
+                .control(text(format!(
+                    "{} can't be installed on this system yet.",
+                    self.manifest.friendly_name
+                )))
+                .control(column(self.unmet_requirements.iter().map(|requirement| {
+                    text(format!("{}: {}", requirement.description, requirement.reason)).into()
+                })).spacing(10))
+                .control(text("Resolve the issues above and press Next to check again."))
+                .on_cancel(Message::Finish)
+                .on_next(Message::Next)
+                .into(),
             InstallerStep::Introduction => Scaffold::new()
                 .title(row![text(&self.manifest.friendly_name).size(24), horizontal_space(), text(&self.manifest.version).size(24)])
                 .control(text(format!(
@@ -164,8 +316,11 @@ where
                                     "Installing {}!",
                                     self.manifest.friendly_name
                                 )))
-                                .control(progress_bar(0.0..=1.0, self.progress))
-                                .control(text(format!("{:.0}%", self.progress * 100.0)))
+                                .control(progress_bar(0.0..=1.0, self.fraction()))
+                                .control(text(format!("{:.0}%", self.fraction() * 100.0)))
+                                .control_maybe(self.progress_details().map(text))
+                                .control_maybe(self.cancel.is_cancelled().then(|| text("Cancelling...")))
+                                .on_cancel_maybe((!self.finished && !self.cancel.is_cancelled()).then_some(Message::Cancel))
                                 .on_next_maybe(self.finished.then_some(Message::Next))
                                 .into()
             },
@@ -176,25 +331,139 @@ where
                                 "{} installed successfully!",
                                 self.manifest.friendly_name
                             )))
+                            .control_maybe(self.config_file_notes().map(text))
+                            .control(
+                                checkbox(format!("Run {} now", self.manifest.friendly_name), self.launch)
+                                    .on_toggle_maybe(self.launch_error.is_none().then_some(Message::Launch)),
+                            )
+                            .control_maybe(self.manifest.readme.as_ref().map(|readme| {
+                                checkbox(format!("Open {readme}"), self.open_readme)
+                                    .on_toggle_maybe(self.launch_error.is_none().then_some(Message::OpenReadme))
+                            }))
+                            .control_maybe(self.launch_error.as_deref().map(text))
                             .on_finish(Message::Finish)
                             .into()
 ,
         }
     }
 
+    pub fn subscription(&self) -> Subscription<Message<Wizard::Message>> {
+        window::close_requests().map(|_| Message::CloseRequested)
+    }
+
+    fn is_installing(&self) -> bool {
+        self.step == InstallerStep::Installing && !self.finished && self.error.is_none()
+    }
+
+    fn fraction(&self) -> f32 {
+        if self.finished {
+            1.0
+        } else {
+            self.progress.as_ref().map_or(0.0, Progress::fraction)
+        }
+    }
+
+    /// Describes the current phase, file, amount written, throughput and remaining time
+    fn progress_details(&self) -> Option<String> {
+        let progress = self.progress.as_ref()?;
+
+        let mut details = progress.phase.to_string();
+        if let Some(file) = &progress.current_file {
+            details.push_str(&format!(": {}", file.display()));
+        } else if let Some(task) = &progress.current_task {
+            details.push_str(&format!(": {task}"));
+        }
+        details.push_str(&format!(
+            "\n{} of {} ({} of {} files)",
+            format_size(progress.bytes_done),
+            format_size(progress.bytes_total),
+            progress.files_done,
+            progress.files_total
+        ));
+
+        let elapsed = self.install_started?.elapsed().as_secs_f64();
+        if elapsed > 0.0 && progress.bytes_done > 0 && !self.finished {
+            let rate = progress.bytes_done as f64 / elapsed;
+            let remaining = progress.bytes_total.saturating_sub(progress.bytes_done) as f64 / rate;
+            details.push_str(&format!(
+                "\n{}/s, about {}s remaining",
+                format_size(rate as u64),
+                remaining.ceil() as u64
+            ));
+        }
+
+        Some(details)
+    }
+
+    /// Lists modified configuration files that weren't simply replaced
+    fn config_file_notes(&self) -> Option<String> {
+        let outcomes = &self.report.as_ref()?.config_files;
+        if outcomes.is_empty() {
+            return None;
+        }
+
+        let mut notes =
+            "Some configuration files were changed since they were installed:".to_string();
+        for outcome in outcomes {
+            notes.push_str(&format!("\n{outcome}"));
+        }
+        Some(notes)
+    }
+
+    /// Starts the application and opens the README, as selected on the completion page
+    fn open_selected(&self) -> Result<(), String> {
+        let Some(install_path) = &self.install_path else {
+            return Ok(());
+        };
+        if self.launch {
+            launch_application(&self.manifest, install_path).map_err(|error| {
+                format!("Failed to start {}:\n{error}", self.manifest.friendly_name)
+            })?;
+        }
+        if self.open_readme
+            && let Some(readme) = &self.manifest.readme
+        {
+            open_file(install_path, readme)
+                .map_err(|error| format!("Failed to open {readme}:\n{error}"))?;
+        }
+        Ok(())
+    }
+
     pub fn title(&self) -> String {
         // TODO: configurable title
         "Iced Installer".to_string()
     }
 
     fn install(&self, config: InstallConfig) -> Task<Message<Wizard::Message>> {
-        let sipper =
-            crate::installer::install(config, self.manifest.clone(), |result| match result {
-                Ok(()) => Message::InstallDone,
+        let sipper = crate::installer::install(
+            config,
+            self.manifest.clone(),
+            self.cancel.clone(),
+            |result| match result {
+                Ok(report) => Message::InstallDone(report),
                 Err(error) => Message::InstallError(Arc::new(error)),
-            })
-            .with(|message| Message::Progress(message));
+            },
+        )
+        .with(|message| Message::Progress(message));
 
         Task::stream(sipper::stream(sipper))
     }
 }
+
+/// Formats a number of bytes for humans, e.g. `1.5 GB`
+pub(crate) fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} {}", UNITS[0])
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
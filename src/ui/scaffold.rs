@@ -11,6 +11,8 @@ pub struct Scaffold<'a, Message> {
     controls: Vec<Element<'a, Message>>,
     on_next: Option<Message>,
     on_back: Option<Message>,
+    on_cancel: Option<Message>,
+    show_cancel: bool,
     is_finished: bool,
 }
 
@@ -21,6 +23,8 @@ impl<'a, Message> Scaffold<'a, Message> {
             controls: Vec::new(),
             on_next: None,
             on_back: None,
+            on_cancel: None,
+            show_cancel: false,
             is_finished: false,
         }
     }
@@ -35,6 +39,13 @@ impl<'a, Message> Scaffold<'a, Message> {
         self
     }
 
+    pub fn control_maybe(mut self, control: Option<impl Into<Element<'a, Message>>>) -> Self {
+        if let Some(control) = control {
+            self.controls.push(control.into());
+        }
+        self
+    }
+
     pub fn on_next(mut self, message: Message) -> Self {
         self.is_finished = false;
         self.on_next = Some(message);
@@ -57,6 +68,18 @@ impl<'a, Message> Scaffold<'a, Message> {
         self
     }
 
+    pub fn on_cancel(mut self, message: Message) -> Self {
+        self.show_cancel = true;
+        self.on_cancel = Some(message);
+        self
+    }
+
+    pub fn on_cancel_maybe(mut self, message: Option<Message>) -> Self {
+        self.show_cancel = true;
+        self.on_cancel = message;
+        self
+    }
+
     pub fn on_finish(mut self, message: Message) -> Self {
         self.is_finished = true;
         self.on_next = Some(message);
@@ -87,21 +110,29 @@ where
             .push(vertical_space())
             .push(horizontal_rule(2))
             .push(
-                row![
-                    horizontal_space(),
-                    button(text("Back"))
-                        .padding([8, 30])
-                        .on_press_maybe(scaffold.on_back),
-                    button(text(if scaffold.is_finished {
-                        "Finish"
-                    } else {
-                        "Next"
+                row![]
+                    .push_maybe(scaffold.show_cancel.then(|| {
+                        button(text("Cancel"))
+                            .padding([8, 30])
+                            .on_press_maybe(scaffold.on_cancel)
                     }))
-                    .padding([8, 30])
-                    .on_press_maybe(scaffold.on_next)
-                ]
-                .spacing(20)
-                .padding(10),
+                    .push(horizontal_space())
+                    .push(
+                        button(text("Back"))
+                            .padding([8, 30])
+                            .on_press_maybe(scaffold.on_back),
+                    )
+                    .push(
+                        button(text(if scaffold.is_finished {
+                            "Finish"
+                        } else {
+                            "Next"
+                        }))
+                        .padding([8, 30])
+                        .on_press_maybe(scaffold.on_next),
+                    )
+                    .spacing(20)
+                    .padding(10),
             )
             .spacing(10)
             .padding(20)
@@ -1,14 +1,35 @@
-use std::{env::current_exe, ffi::CString};
+use std::{
+    env::current_exe,
+    ffi::OsStr,
+    fmt::Write,
+    iter::repeat_n,
+    os::windows::process::CommandExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
-use windows::Win32::{
-    Foundation::HANDLE,
-    Security::{
-        GetTokenInformation, TOKEN_ELEVATION, TOKEN_QUERY, TokenElevation,
+use windows::{
+    Win32::{
+        Foundation::{HANDLE, LPARAM, WPARAM},
+        Security::{GetTokenInformation, TOKEN_ELEVATION, TOKEN_QUERY, TokenElevation},
+        System::Com::CoTaskMemFree,
+        System::Console::{ATTACH_PARENT_PROCESS, AttachConsole},
+        System::SystemInformation::{
+            IMAGE_FILE_MACHINE, IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_MACHINE_ARM64,
+            IMAGE_FILE_MACHINE_ARMNT, IMAGE_FILE_MACHINE_I386,
+        },
+        System::Threading::{
+            CREATE_NO_WINDOW, GetCurrentProcess, IsWow64Process2, OpenProcessToken,
+        },
+        UI::Shell::{
+            KF_FLAG_DEFAULT, SHCNE_ASSOCCHANGED, SHCNF_IDLIST, SHChangeNotify,
+            SHGetKnownFolderPath, ShellExecuteW,
+        },
+        UI::WindowsAndMessaging::{
+            HWND_BROADCAST, SMTO_ABORTIFHUNG, SW_NORMAL, SendMessageTimeoutW, WM_SETTINGCHANGE,
+        },
     },
-    System::Console::{ATTACH_PARENT_PROCESS, AttachConsole},
-    System::Threading::{GetCurrentProcess, OpenProcessToken},
-    UI::Shell::ShellExecuteA,
-    UI::WindowsAndMessaging::SW_NORMAL,
+    core::{GUID, HSTRING, PCWSTR, w},
 };
 
 pub fn attach_and_ensure_admin() {
@@ -33,7 +54,7 @@ pub fn attach_and_ensure_admin() {
 }
 
 pub fn attach() -> Result<(), windows_result::Error> {
-unsafe { AttachConsole(ATTACH_PARENT_PROCESS) }
+    unsafe { AttachConsole(ATTACH_PARENT_PROCESS) }
 }
 
 pub fn elevated() -> Result<bool, windows_result::Error> {
@@ -63,17 +84,156 @@ fn get_process_tokem() -> Result<HANDLE, windows_result::Error> {
     Ok(token)
 }
 
+/// Architecture of the machine, named like `std::env::consts::ARCH`.
+///
+/// Unlike the architecture of the installer it isn't affected by emulation, e.g. of x86_64 programs on ARM.
+pub fn native_arch() -> Option<&'static str> {
+    let mut process_machine = IMAGE_FILE_MACHINE::default();
+    let mut native_machine = IMAGE_FILE_MACHINE::default();
+    unsafe {
+        IsWow64Process2(
+            GetCurrentProcess(),
+            &mut process_machine,
+            Some(&mut native_machine),
+        )
+    }
+    .ok()?;
+    match native_machine {
+        IMAGE_FILE_MACHINE_AMD64 => Some("x86_64"),
+        IMAGE_FILE_MACHINE_ARM64 => Some("aarch64"),
+        IMAGE_FILE_MACHINE_I386 => Some("x86"),
+        IMAGE_FILE_MACHINE_ARMNT => Some("arm"),
+        _ => None,
+    }
+}
+
+/// Starts the installer again with the same arguments and administrator rights
 pub fn restart_with_admin_prompt() {
-    let runas = CString::from(c"runas");
-    let runas_ptr = windows::core::PCSTR::from_raw(runas.as_ptr() as *const u8);
-    let exe = CString::new(
-        current_exe()
-            .expect("requesting current exe name should work")
-            .into_os_string()
-            .into_encoded_bytes(),
-    )
-    .unwrap();
-    let exe_ptr = windows::core::PCSTR::from_raw(exe.as_ptr() as *const u8);
-
-    let _instance = unsafe { ShellExecuteA(None, runas_ptr, exe_ptr, None, None, SW_NORMAL) };
+    restart_elevated(std::env::args_os().skip(1));
+}
+
+/// Starts the installer again with administrator rights, the user has to confirm the UAC prompt
+pub fn restart_elevated(args: impl IntoIterator<Item = impl AsRef<OsStr>>) {
+    let exe = current_exe().expect("requesting current exe name should work");
+    let parameters = args
+        .into_iter()
+        .map(|arg| quote_argument(arg.as_ref()))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let _instance = unsafe {
+        ShellExecuteW(
+            None,
+            w!("runas"),
+            &HSTRING::from(exe.as_os_str()),
+            &HSTRING::from(parameters),
+            PCWSTR::null(),
+            SW_NORMAL,
+        )
+    };
+}
+
+/// Quotes an argument so `CommandLineToArgvW` parses it back unchanged
+fn quote_argument(arg: &OsStr) -> String {
+    let arg = arg.to_string_lossy();
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.into_owned();
+    }
+
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+        // Backslashes are only special in front of quotes
+        let escaped = if c == '"' {
+            backslashes * 2 + 1
+        } else {
+            backslashes
+        };
+        quoted.extend(repeat_n('\\', escaped));
+        quoted.push(c);
+        backslashes = 0;
+    }
+    quoted.extend(repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// Opens a file or starts a program the way double-clicking it in Explorer would.
+///
+/// An elevated installer hands the path to Explorer instead, which runs with the rights of the logged in user,
+/// so the program doesn't inherit administrator rights.
+pub fn shell_open(path: &Path) -> std::io::Result<()> {
+    if elevated().unwrap_or(false) {
+        Command::new("explorer.exe").arg(path).spawn()?;
+        return Ok(());
+    }
+
+    let directory = path.parent().map(|dir| HSTRING::from(dir.as_os_str()));
+    let instance = unsafe {
+        ShellExecuteW(
+            None,
+            w!("open"),
+            &HSTRING::from(path.as_os_str()),
+            PCWSTR::null(),
+            directory
+                .as_ref()
+                .map_or(PCWSTR::null(), |dir| PCWSTR(dir.as_ptr())),
+            SW_NORMAL,
+        )
+    };
+    // Values up to 32 are error codes
+    if instance.0 as usize <= 32 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Path of a known folder like the Start Menu, identified by one of the `FOLDERID_*` constants
+pub fn known_folder(id: &GUID) -> std::io::Result<PathBuf> {
+    unsafe {
+        let path = SHGetKnownFolderPath(id, KF_FLAG_DEFAULT, None)?;
+        let result = PathBuf::from(path.to_hstring().to_os_string());
+        // The string is allocated by the shell and has to be freed by the caller
+        CoTaskMemFree(Some(path.as_ptr() as *const _));
+        Ok(result)
+    }
+}
+
+/// Tells running programs like Explorer to reload the environment variables from the registry
+pub fn broadcast_environment_change() {
+    unsafe {
+        SendMessageTimeoutW(
+            HWND_BROADCAST,
+            WM_SETTINGCHANGE,
+            WPARAM(0),
+            LPARAM(w!("Environment").as_ptr() as isize),
+            SMTO_ABORTIFHUNG,
+            5000,
+            None,
+        )
+    };
+}
+
+/// Tells Explorer that file associations changed, so icons and context menus are refreshed
+pub fn notify_association_change() {
+    unsafe { SHChangeNotify(SHCNE_ASSOCCHANGED, SHCNF_IDLIST, None, None) };
+}
+
+/// Deletes files and empty directories once the current process has exited, which is how a program removes itself
+pub fn delete_after_exit(paths: &[PathBuf]) {
+    // Pinging localhost is the usual way to wait a moment in cmd
+    let mut command = String::from("/C ping 127.0.0.1 -n 3 > nul");
+    for path in paths {
+        let delete = if path.is_dir() { "rmdir" } else { "del /F /Q" };
+        let _ = write!(command, " & {delete} \"{}\"", path.display());
+    }
+
+    let _ = Command::new("cmd.exe")
+        .raw_arg(command)
+        .creation_flags(CREATE_NO_WINDOW.0)
+        .spawn();
 }
@@ -1,8 +1,11 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, path::PathBuf};
 
 use iced::Task;
 
-use crate::config::{AppManifest, InstallConfig};
+use crate::{
+    config::{AppManifest, InstallConfig},
+    installer::PlanFormat,
+};
 
 pub mod basic;
 
@@ -23,6 +26,16 @@ pub trait Wizard: Sized + 'static {
     /// You can return Some(InstallConfig) to skip the wizard and install the system directly
     /// This is useful to allow unattended installation with e.g. a silent flag
     fn unattended_install(&mut self) -> Option<InstallConfig>;
+    /// Called before starting the graphical wizard
+    /// You can return the install path of a previous installation to remove it instead of installing
+    fn unattended_uninstall(&mut self) -> Option<PathBuf> {
+        None
+    }
+    /// Called before starting the graphical wizard
+    /// You can return Some(InstallConfig) to print what installing it would do, without changing anything
+    fn unattended_plan(&mut self) -> Option<(InstallConfig, PlanFormat)> {
+        None
+    }
     /// Called when the wizard is first shown
     fn start(&self) -> WizardAction<Self::Message>;
     /// Iced update method for the wizard
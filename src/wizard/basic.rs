@@ -1,16 +1,20 @@
+#[cfg(windows)]
+use std::ffi::OsStr;
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{CommandFactory, Parser, ValueEnum, error::ErrorKind};
 use iced::{
     Alignment::Center,
-    Task,
-    widget::{button, checkbox, horizontal_space, row, text},
+    Element, Length, Task,
+    widget::{Space, button, checkbox, column, horizontal_space, radio, row, text},
 };
 use rfd::AsyncFileDialog;
 
 use crate::{
-    config::{AppManifest, InstallConfig},
-    ui::scaffold::Scaffold,
+    config::{AppManifest, Component, InstallConfig, InstallScope},
+    disk_space::available_space,
+    installer::{PlanFormat, installed_components},
+    ui::{format_size, scaffold::Scaffold},
     wizard::{
         Wizard, WizardAction,
         basic::config::{BasicWizardBuilder, BasicWizardConfig},
@@ -21,6 +25,8 @@ mod config;
 
 enum Step {
     SelectInstallPath,
+    /// Only shown if there are optional components
+    SelectComponents,
     SetInstallOptions,
 }
 
@@ -29,6 +35,9 @@ pub struct BasicWizard {
     selecting_path: bool,
     step: Step,
     manifest: AppManifest,
+    available_space: Option<u64>,
+    /// Install path given to the builder, used for the manifest's default scope
+    default_install_path: PathBuf,
 }
 
 impl BasicWizard {
@@ -36,33 +45,224 @@ impl BasicWizard {
         BasicWizardConfig::build()
     }
 
-    fn from_config(config: InstallConfig, manifest: AppManifest) -> Self {
+    fn from_config(mut config: InstallConfig, manifest: AppManifest) -> Self {
+        if let Some(selection) = installed_components(&config.install_path) {
+            config.restore_component_selection(&selection);
+        }
+        let available_space = available_space(&config.install_path).ok();
         BasicWizard {
+            default_install_path: config.install_path.clone(),
             config: Some(config),
             selecting_path: false,
             step: Step::SelectInstallPath,
             manifest,
+            available_space,
+        }
+    }
+
+    /// The scope requested on the command line, or the manifest's default scope.
+    ///
+    /// See [`requires_elevation`](Self::requires_elevation) to decide whether the installer has to be elevated before it starts.
+    pub fn requested_scope(manifest: &AppManifest) -> InstallScope {
+        Args::try_parse()
+            .ok()
+            .and_then(|args| args.scope)
+            .filter(|_| manifest.allow_scope_choice)
+            .unwrap_or(manifest.default_scope)
+    }
+
+    /// Whether the installer has to be elevated before it starts.
+    ///
+    /// If the user can still choose the scope in the wizard, it restarts elevated once the choice requires it instead.
+    /// Staged installations and dry runs never need administrator rights.
+    pub fn requires_elevation(manifest: &AppManifest) -> bool {
+        Args::try_parse().is_ok_and(|args| args.requires_elevation(manifest))
+    }
+
+    /// Changes the install path, an earlier installation there determines the selected components
+    fn set_install_path(&mut self, path: PathBuf) {
+        if let Some(config) = self.config.as_mut() {
+            let staged = config.staged(&path);
+            self.available_space = available_space(&staged).ok();
+            if let Some(selection) = installed_components(&staged) {
+                config.restore_component_selection(&selection);
+            }
+            config.install_path = path;
+        }
+    }
+
+    /// Switches the scope and moves the install path to that scope's default location
+    fn set_scope(&mut self, scope: InstallScope) {
+        let path = if scope == self.manifest.default_scope {
+            self.default_install_path.clone()
+        } else {
+            scope.default_install_path(&self.manifest.bin_name)
+        };
+        if let Some(config) = self.config.as_mut() {
+            config.scope = scope;
+        }
+        self.set_install_path(path);
+    }
+
+    /// Whether the installer has to restart elevated before the selected scope can be installed
+    fn needs_restart_elevated(&self) -> bool {
+        #[cfg(windows)]
+        {
+            self.config
+                .as_ref()
+                .is_some_and(|config| config.scope.requires_elevation() && config.root.is_none())
+                && !crate::windows::elevated().unwrap_or(true)
+        }
+        #[cfg(not(windows))]
+        {
+            false
+        }
+    }
+
+    /// Applies the scope and install path given on the command line
+    fn apply_args(&mut self, args: &Args) {
+        if let Some(root) = &args.root
+            && let Some(config) = self.config.as_mut()
+        {
+            // Absolute, so the real paths of staged files can be told from their staging paths
+            config.root = Some(std::path::absolute(root).unwrap_or_else(|_| root.clone()));
+            let path = config.install_path.clone();
+            self.set_install_path(path);
+        }
+        if let Some(scope) = args.scope {
+            if !self.manifest.allow_scope_choice && scope != self.manifest.default_scope {
+                Args::command()
+                    .error(
+                        ErrorKind::ArgumentConflict,
+                        format!(
+                            "{} can only be installed with --scope {}",
+                            self.manifest.friendly_name,
+                            self.manifest
+                                .default_scope
+                                .to_possible_value()
+                                .expect("No variant is skipped")
+                                .get_name()
+                        ),
+                    )
+                    .exit();
+            }
+            self.set_scope(scope);
+        }
+        if let Some(path) = &args.install_path {
+            self.set_install_path(path.clone());
+        }
+        if let Some(log) = &args.log
+            && let Some(config) = self.config.as_mut()
+        {
+            config.log_file = Some(log.clone());
+        }
+        if let Some(ids) = &args.components
+            && let Some(config) = self.config.as_mut()
+        {
+            if let Some(unknown) = ids.iter().find(|id| {
+                !config
+                    .all_components()
+                    .any(|component| &component.id == *id)
+            }) {
+                Args::command()
+                    .error(
+                        ErrorKind::InvalidValue,
+                        format!(
+                            "{unknown:?} isn't a component of {}",
+                            self.manifest.friendly_name
+                        ),
+                    )
+                    .exit();
+            }
+            let optional = config
+                .all_components()
+                .map(|component| component.id.clone())
+                .collect::<Vec<_>>();
+            for id in optional {
+                config.select_component(&id, false);
+            }
+            for id in ids {
+                config.select_component(id, true);
+            }
+        }
+    }
+
+    fn has_components(&self) -> bool {
+        self.config
+            .as_ref()
+            .is_some_and(|config| !config.components.is_empty())
+    }
+
+    /// Whether the selected install location has room for all payloads.
+    /// If the free space is unknown the user may still continue.
+    fn has_enough_space(&self) -> bool {
+        match (&self.config, self.available_space) {
+            (Some(config), Some(available)) => available >= config.required_space(),
+            _ => true,
         }
     }
 }
 
+// Later arguments override earlier ones, so the wizard can restart elevated with its choices appended
 #[derive(Debug, clap::Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_override_self = true)]
 struct Args {
     /// In silent mode the wizard will install the Application silently, without any user interaction.
     #[arg(short, long, default_value_t = false)]
     silent: bool,
-    /// Path to install the Application to in silent mode.
+    /// Path to install the Application to. Preselects the path if not in silent mode.
     #[arg(short = 'p', long, default_value = None)]
     install_path: Option<PathBuf>,
+    /// Whether to install for all users or just the current one, if the application allows choosing.
+    #[arg(long, value_enum)]
+    scope: Option<InstallScope>,
+    /// Removes the Application from the install path instead of installing it. Never shows the wizard.
+    #[arg(long, default_value_t = false)]
+    uninstall: bool,
+    /// Starts the Application once it is installed in silent mode. The wizard always offers to start it.
+    #[arg(long, default_value_t = false)]
+    launch: bool,
+    /// Comma separated ids of the components to install, besides the required ones. Preselects them if not in silent mode.
+    #[arg(long, value_delimiter = ',')]
+    components: Option<Vec<String>>,
+    /// File to write the install log to, instead of one in the temporary directory.
+    #[arg(long)]
+    log: Option<PathBuf>,
+    /// Prints what would be installed, without changing anything. Never shows the wizard.
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+    /// Prints the plan of --dry-run as JSON.
+    #[arg(long, default_value_t = false, requires = "dry_run")]
+    json: bool,
+    /// Installs into this directory as if it were the file system root, e.g. to build a container image.
+    /// Doesn't require elevation and leaves the running system alone.
+    #[arg(long, conflicts_with_all = ["uninstall", "launch"])]
+    root: Option<PathBuf>,
+}
+
+impl Args {
+    fn requires_elevation(&self, manifest: &AppManifest) -> bool {
+        if self.root.is_some() || self.dry_run {
+            return false;
+        }
+        let wizard_chooses_scope = !self.silent && !self.uninstall && manifest.allow_scope_choice;
+        let scope = self
+            .scope
+            .filter(|_| manifest.allow_scope_choice)
+            .unwrap_or(manifest.default_scope);
+        !wizard_chooses_scope && scope.requires_elevation()
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     SelectInstallPath,
     SetInstallPath(Option<PathBuf>),
+    SetScope(InstallScope),
     StartMenuShortcut(bool),
     DesktopShortcut(bool),
+    AddToPath(bool),
+    SelectComponent(String, bool),
     Back,
     Next,
 }
@@ -80,20 +280,40 @@ impl Wizard for BasicWizard {
 
     fn unattended_install(&mut self) -> Option<InstallConfig> {
         let args = Args::parse();
+        self.apply_args(&args);
 
         if args.silent {
             let mut config = self.config.take()?;
-
-            if let Some(path) = args.install_path {
-                config.install_path = path;
-            }
-
+            config.launch = args.launch;
             Some(config)
         } else {
             None
         }
     }
 
+    fn unattended_plan(&mut self) -> Option<(InstallConfig, PlanFormat)> {
+        let args = Args::parse();
+        if !args.dry_run {
+            return None;
+        }
+        self.apply_args(&args);
+        let format = if args.json {
+            PlanFormat::Json
+        } else {
+            PlanFormat::Text
+        };
+        self.config.take().map(|config| (config, format))
+    }
+
+    fn unattended_uninstall(&mut self) -> Option<PathBuf> {
+        let args = Args::parse();
+        if !args.uninstall {
+            return None;
+        }
+        self.apply_args(&args);
+        self.config.take().map(|config| config.install_path)
+    }
+
     fn update(&mut self, message: Self::Message) -> crate::wizard::WizardAction<Self::Message> {
         match message {
             Message::SelectInstallPath => {
@@ -112,33 +332,80 @@ impl Wizard for BasicWizard {
             Message::SetInstallPath(path) => {
                 self.selecting_path = false;
                 if let Some(path) = path {
-                    if let Some(config) = self.config.as_mut() {
-                        config.install_path = path;
-                    }
+                    self.set_install_path(path);
                 }
                 WizardAction::None
             }
+            Message::SetScope(scope) => {
+                self.set_scope(scope);
+                WizardAction::None
+            }
             Message::StartMenuShortcut(create_shortcut) => {
                 if let Some(config) = self.config.as_mut() {
-                    config.create_desktop_shortcut = create_shortcut;
+                    config.create_start_menu_shortcut = create_shortcut;
                 }
                 WizardAction::None
             }
             Message::DesktopShortcut(create_shortcut) => {
                 if let Some(config) = self.config.as_mut() {
-                    config.create_start_menu_shortcut = create_shortcut;
+                    config.create_desktop_shortcut = create_shortcut;
+                }
+                WizardAction::None
+            }
+            Message::AddToPath(add_to_path) => {
+                if let Some(config) = self.config.as_mut() {
+                    config.add_to_path = add_to_path;
+                }
+                WizardAction::None
+            }
+            Message::SelectComponent(id, selected) => {
+                if let Some(config) = self.config.as_mut() {
+                    config.select_component(&id, selected);
                 }
                 WizardAction::None
             }
             Message::Back => match self.step {
                 Step::SelectInstallPath => WizardAction::Back,
+                Step::SelectComponents => {
+                    self.step = Step::SelectInstallPath;
+                    WizardAction::None
+                }
+                Step::SetInstallOptions if self.has_components() => {
+                    self.step = Step::SelectComponents;
+                    WizardAction::None
+                }
                 Step::SetInstallOptions => {
                     self.step = Step::SelectInstallPath;
                     WizardAction::None
                 }
             },
             Message::Next => match self.step {
-                Step::SelectInstallPath => {
+                #[cfg(windows)]
+                Step::SelectInstallPath if self.needs_restart_elevated() => {
+                    if let Some(config) = &self.config {
+                        let scope = config
+                            .scope
+                            .to_possible_value()
+                            .expect("No variant is skipped");
+                        // Appended, so they override what was given, everything else is passed on as it is
+                        let choices = [
+                            OsStr::new("--scope"),
+                            OsStr::new(scope.get_name()),
+                            OsStr::new("--install-path"),
+                            config.install_path.as_os_str(),
+                        ];
+                        let args = std::env::args_os().skip(1).collect::<Vec<_>>();
+                        crate::windows::restart_elevated(
+                            args.iter().map(|arg| arg.as_os_str()).chain(choices),
+                        );
+                    }
+                    WizardAction::Run(iced::exit())
+                }
+                Step::SelectInstallPath if self.has_components() => {
+                    self.step = Step::SelectComponents;
+                    WizardAction::None
+                }
+                Step::SelectInstallPath | Step::SelectComponents => {
                     self.step = Step::SetInstallOptions;
                     WizardAction::None
                 }
@@ -162,6 +429,21 @@ impl Wizard for BasicWizard {
                     horizontal_space(),
                     text(&self.manifest.version).size(24)
                 ])
+                .control_maybe(self.manifest.allow_scope_choice.then(|| {
+                    row([InstallScope::Machine, InstallScope::User].map(|scope| {
+                        radio(
+                            scope.to_string(),
+                            scope,
+                            Some(config.scope),
+                            Message::SetScope,
+                        )
+                        .into()
+                    }))
+                    .spacing(20)
+                }))
+                .control_maybe(self.needs_restart_elevated().then(|| {
+                    text("Installing for all users requires administrator rights, the installer will restart.")
+                }))
                 .control(text("Select install location").size(20))
                 .control(
                     row![
@@ -175,7 +457,50 @@ impl Wizard for BasicWizard {
                     .spacing(20)
                     .align_y(Center),
                 )
-                .on_next_maybe((!self.selecting_path).then(|| Message::Next))
+                .control(text(format!(
+                    "Required space: {}",
+                    format_size(config.required_space())
+                )))
+                .control(text(format!(
+                    "Available space: {}",
+                    self.available_space
+                        .map(format_size)
+                        .unwrap_or_else(|| "unknown".to_string())
+                )))
+                .control_maybe(
+                    (!self.has_enough_space())
+                        .then(|| text("There is not enough free space at the selected location!")),
+                )
+                .on_next_maybe(
+                    (!self.selecting_path && self.has_enough_space()).then(|| Message::Next),
+                )
+                .on_back(Message::Back)
+                .into(),
+            Step::SelectComponents => Scaffold::new()
+                .title(row![
+                    text(&self.manifest.friendly_name).size(24),
+                    horizontal_space(),
+                    text(&self.manifest.version).size(24)
+                ])
+                .control(text("Select components").size(20))
+                .control(column(config.components.iter().map(|component| {
+                    component_tree(config, component, 0)
+                })).spacing(10))
+                .control(text(format!(
+                    "Required space: {}",
+                    format_size(config.required_space())
+                )))
+                .control(text(format!(
+                    "Available space: {}",
+                    self.available_space
+                        .map(format_size)
+                        .unwrap_or_else(|| "unknown".to_string())
+                )))
+                .control_maybe(
+                    (!self.has_enough_space())
+                        .then(|| text("There is not enough free space at the selected location!")),
+                )
+                .on_next_maybe(self.has_enough_space().then_some(Message::Next))
                 .on_back(Message::Back)
                 .into(),
             Step::SetInstallOptions => Scaffold::new()
@@ -194,11 +519,141 @@ impl Wizard for BasicWizard {
                 )
                 .control(
                     checkbox("Create desktop shortcut", config.create_desktop_shortcut)
-                        .on_toggle(Message::StartMenuShortcut),
+                        .on_toggle(Message::DesktopShortcut),
                 )
+                .control_maybe(self.manifest.add_to_path.then(|| {
+                    checkbox(
+                        format!("Add {} to PATH", self.manifest.bin_name),
+                        config.add_to_path,
+                    )
+                    .on_toggle(Message::AddToPath)
+                }))
                 .on_next_maybe((!self.selecting_path).then(|| Message::Next))
                 .on_back(Message::Back)
                 .into(),
         }
     }
 }
+
+/// A checkbox for the component and, indented below it, those of its sub-components
+fn component_tree<'a>(
+    config: &InstallConfig,
+    component: &'a Component,
+    depth: u16,
+) -> Element<'a, Message> {
+    let selected = config.is_component_selected(&component.id);
+    let id = component.id.clone();
+    let entry = column![
+        checkbox(
+            format!(
+                "{} ({})",
+                component.name,
+                format_size(component.unpacked_size())
+            ),
+            selected,
+        )
+        .on_toggle_maybe(
+            (!component.is_required())
+                .then_some(move |selected| Message::SelectComponent(id.clone(), selected)),
+        )
+    ]
+    .push_maybe(
+        component
+            .description
+            .as_deref()
+            .map(|description| text(description).size(12)),
+    )
+    .spacing(4);
+
+    let children = component
+        .children
+        .iter()
+        .map(|child| component_tree(config, child, depth + 1));
+    let indented = row![
+        Space::with_width(Length::Fixed(f32::from(depth) * 20.0)),
+        entry
+    ];
+    column(std::iter::once(indented.into()).chain(children))
+        .spacing(10)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Args {
+        Args::try_parse_from(std::iter::once("installer").chain(args.iter().copied())).unwrap()
+    }
+
+    fn manifest(default_scope: InstallScope, allow_scope_choice: bool) -> AppManifest {
+        let mut manifest = crate::test_util::manifest();
+        manifest.default_scope = default_scope;
+        manifest.allow_scope_choice = allow_scope_choice;
+        manifest
+    }
+
+    #[test]
+    fn elevates_at_startup_only_once_the_scope_is_final() {
+        // Only installing for all users on Windows needs administrator rights
+        let machine = InstallScope::Machine.requires_elevation();
+
+        let fixed = manifest(InstallScope::Machine, false);
+        assert_eq!(args(&[]).requires_elevation(&fixed), machine);
+        assert_eq!(args(&["--silent"]).requires_elevation(&fixed), machine);
+        assert_eq!(args(&["--uninstall"]).requires_elevation(&fixed), machine);
+        // The scope can't be chosen, so asking for another one doesn't change it
+        assert_eq!(
+            args(&["--silent", "--scope", "user"]).requires_elevation(&fixed),
+            machine
+        );
+
+        // The wizard restarts elevated if the user keeps the machine scope
+        let choice = manifest(InstallScope::Machine, true);
+        assert!(!args(&[]).requires_elevation(&choice));
+        assert!(!args(&["--scope", "machine"]).requires_elevation(&choice));
+        assert_eq!(args(&["--silent"]).requires_elevation(&choice), machine);
+        assert!(!args(&["--silent", "--scope", "user"]).requires_elevation(&choice));
+        assert_eq!(
+            args(&["--uninstall", "--scope", "machine"]).requires_elevation(&choice),
+            machine
+        );
+
+        let user = manifest(InstallScope::User, true);
+        assert!(!args(&["--silent"]).requires_elevation(&user));
+        assert_eq!(
+            args(&["--silent", "--scope", "machine"]).requires_elevation(&user),
+            machine
+        );
+    }
+
+    #[test]
+    fn staging_and_dry_runs_never_elevate() {
+        let fixed = manifest(InstallScope::Machine, false);
+        assert!(!args(&["--silent", "--root", "/tmp/stage"]).requires_elevation(&fixed));
+        assert!(!args(&["--dry-run"]).requires_elevation(&fixed));
+    }
+
+    #[test]
+    fn choices_appended_on_restart_override_earlier_arguments() {
+        let args = args(&[
+            "--scope",
+            "user",
+            "-p",
+            "/home/user/app",
+            "--log",
+            "install.log",
+            "--components",
+            "docs",
+            "--scope",
+            "machine",
+            "--install-path",
+            "/opt/app",
+        ]);
+
+        assert_eq!(args.scope, Some(InstallScope::Machine));
+        assert_eq!(args.install_path, Some(PathBuf::from("/opt/app")));
+        assert_eq!(args.log, Some(PathBuf::from("install.log")));
+        assert_eq!(args.components, Some(vec!["docs".to_string()]));
+    }
+}
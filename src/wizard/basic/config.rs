@@ -1,7 +1,10 @@
-use std::path::PathBuf;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
 
 use crate::{
-    config::{AppManifest, FilePayload, InstallConfig},
+    config::{AppManifest, Component, FilePayload, InstallConfig},
     installer::Installer,
     wizard::basic::BasicWizard,
 };
@@ -14,16 +17,27 @@ impl BasicWizardConfig {
             install_path: (),
             manifest: (),
             payloads: Vec::new(),
+            components: Vec::new(),
+            variables: BTreeMap::new(),
         }
     }
 
     pub fn to_installer(self) -> Installer<BasicWizard> {
-        let install_config = InstallConfig {
+        let mut install_config = InstallConfig {
             install_path: self.install_path,
+            scope: self.manifest.default_scope,
             payloads: self.payloads,
+            components: self.components,
+            selected_components: BTreeSet::new(),
             create_start_menu_shortcut: true,
             create_desktop_shortcut: false,
+            add_to_path: self.manifest.add_to_path,
+            variables: self.variables,
+            launch: true,
+            log_file: None,
+            root: None,
         };
+        install_config.select_default_components();
         let wizard = BasicWizard::from_config(install_config, self.manifest);
         Installer::from_wizard(wizard)
     }
@@ -33,6 +47,8 @@ pub struct BasicWizardBuilder<A, B> {
     pub(super) install_path: A,
     manifest: B,
     payloads: Vec<FilePayload>,
+    components: Vec<Component>,
+    variables: BTreeMap<String, String>,
 }
 
 impl<A, B> BasicWizardBuilder<A, B> {
@@ -41,6 +57,8 @@ impl<A, B> BasicWizardBuilder<A, B> {
             install_path: path.into(),
             manifest: self.manifest,
             payloads: self.payloads,
+            components: self.components,
+            variables: self.variables,
         }
     }
 
@@ -49,6 +67,8 @@ impl<A, B> BasicWizardBuilder<A, B> {
             install_path: self.install_path,
             manifest,
             payloads: self.payloads,
+            components: self.components,
+            variables: self.variables,
         }
     }
 
@@ -56,4 +76,20 @@ impl<A, B> BasicWizardBuilder<A, B> {
         self.payloads.push(payload);
         self
     }
+
+    /// Adds an optional part of the application, which the user can choose to install
+    pub fn add_component(mut self, component: Component) -> BasicWizardBuilder<A, B> {
+        self.components.push(component);
+        self
+    }
+
+    /// Adds a value that can be used as `{name}` in templates
+    pub fn variable(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> BasicWizardBuilder<A, B> {
+        self.variables.insert(name.into(), value.into());
+        self
+    }
 }
@@ -0,0 +1,67 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Cursor},
+    path::Path,
+};
+
+use time::OffsetDateTime;
+use zip::{ZipWriter, result::ZipResult, write::SimpleFileOptions};
+
+/// Zips the contents of a directory, returns the archive and the size of the files once unpacked.
+///
+/// Symlinks are stored as symlinks, not as copies of what they point to, so a link to a parent directory
+/// doesn't make the archive endless. Entries are sorted, the same directory always results in the same archive.
+pub fn zip_directory(dir: &Path) -> ZipResult<(Vec<u8>, u64)> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let mut unpacked_size = 0;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let mut entries = fs::read_dir(&current)?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            let name = path
+                .strip_prefix(dir)
+                .expect("Entries are below the zipped directory")
+                .to_string_lossy()
+                .replace('\\', "/");
+            let metadata = fs::symlink_metadata(&path)?;
+            if metadata.is_symlink() {
+                // Taken as is, converting it like entry paths would drop `..` components
+                let target = fs::read_link(&path)?.to_string_lossy().replace('\\', "/");
+                zip.add_symlink(name, target, file_options(&metadata))?;
+            } else if metadata.is_dir() {
+                zip.add_directory(name, file_options(&metadata))?;
+                pending.push(path);
+            } else {
+                zip.start_file(name, file_options(&metadata))?;
+                unpacked_size += io::copy(&mut BufReader::new(File::open(&path)?), &mut zip)?;
+            }
+        }
+    }
+    Ok((zip.finish()?.into_inner(), unpacked_size))
+}
+
+/// Compression, permissions and modification time of a file in an archive
+pub fn file_options(metadata: &fs::Metadata) -> SimpleFileOptions {
+    #[cfg(unix)]
+    let mode = {
+        use std::os::unix::fs::PermissionsExt;
+
+        metadata.permissions().mode() & 0o7777
+    };
+    #[cfg(not(unix))]
+    let mode = 0o755;
+    let options = SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Xz)
+        .compression_level(Some(6i64))
+        .unix_permissions(mode);
+    match metadata
+        .modified()
+        .ok()
+        .and_then(|modified| zip::DateTime::try_from(OffsetDateTime::from(modified)).ok())
+    {
+        Some(modified) => options.last_modified_time(modified),
+        None => options,
+    }
+}